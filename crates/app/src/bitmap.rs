@@ -0,0 +1,150 @@
+//! Raster images placed on the board, decoded once and shared by every element showing them.
+
+use std::{fmt, fs, path::Path, sync::Arc};
+
+use anyhow::{bail, Context as _};
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, ImageFormat};
+use renderer::kurbo::{Rect, Size};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+/// Extensions of the image files which can be placed on the board.
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// SHA-256 of the file content of a bitmap, identifying it within a document.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BitmapHash([u8; 32]);
+
+/// Decoded pixels of an image, along with the content of the file they come from.
+#[derive(Clone)]
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    /// RGBA pixels with separate alpha, row by row.
+    pixels: Arc<[u8]>,
+    /// Content of the image file, as stored in drawing files.
+    encoded: Arc<[u8]>,
+    hash: BitmapHash,
+}
+
+impl fmt::Display for BitmapHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BitmapHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BitmapHash({self})")
+    }
+}
+
+/// Constructor functions
+impl Bitmap {
+    /// Decode the content of a PNG, JPEG or `WebP` file.
+    pub fn decode(encoded: Vec<u8>) -> anyhow::Result<Bitmap> {
+        let format = image::guess_format(&encoded)?;
+        if !matches!(
+            format,
+            ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP
+        ) {
+            bail!(AppError::UnsupportedImageFormat(format!("{format:?}")));
+        }
+        let image = image::load_from_memory_with_format(&encoded, format)?.into_rgba8();
+        Ok(Bitmap {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: Arc::from(image.into_raw()),
+            hash: BitmapHash(Sha256::digest(&encoded).into()),
+            encoded: Arc::from(encoded),
+        })
+    }
+
+    /// Read and decode the image file at `path`.
+    pub fn read(path: &Path) -> anyhow::Result<Bitmap> {
+        let encoded =
+            fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        Bitmap::decode(encoded).with_context(|| format!("Unable to decode {}", path.display()))
+    }
+
+    /// Bitmap of RGBA pixels with separate alpha, row by row, encoded as PNG to be stored.
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<u8>) -> anyhow::Result<Bitmap> {
+        if Some(pixels.len()) != width.checked_mul(height).and_then(|len| len.checked_mul(4)) {
+            bail!(AppError::InvalidImage {
+                width,
+                height,
+                len: pixels.len(),
+            });
+        }
+        let (Ok(png_width), Ok(png_height)) = (u32::try_from(width), u32::try_from(height)) else {
+            bail!(AppError::InvalidImage {
+                width,
+                height,
+                len: pixels.len(),
+            });
+        };
+        let mut encoded = Vec::new();
+        PngEncoder::new(&mut encoded).write_image(
+            &pixels,
+            png_width,
+            png_height,
+            ColorType::Rgba8,
+        )?;
+        Ok(Bitmap {
+            width,
+            height,
+            pixels: Arc::from(pixels),
+            hash: BitmapHash(Sha256::digest(&encoded).into()),
+            encoded: Arc::from(encoded),
+        })
+    }
+}
+
+/// Reference functions
+impl Bitmap {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size in pixels.
+    pub fn size(&self) -> Size {
+        Size::new(self.width as f64, self.height as f64)
+    }
+
+    /// Area covered by the pixels, from the origin.
+    pub fn rect(&self) -> Rect {
+        self.size().to_rect()
+    }
+
+    /// RGBA pixels with separate alpha, row by row.
+    pub fn pixels(&self) -> &Arc<[u8]> {
+        &self.pixels
+    }
+
+    /// Content of the file the bitmap was decoded from.
+    pub fn encoded(&self) -> &[u8] {
+        &self.encoded
+    }
+
+    pub fn hash(&self) -> BitmapHash {
+        self.hash
+    }
+}
+
+impl fmt::Debug for Bitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bitmap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("hash", &self.hash)
+            .finish_non_exhaustive()
+    }
+}
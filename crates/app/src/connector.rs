@@ -0,0 +1,506 @@
+//! Lines and arrows joining elements, rerouted whenever the elements they are bound to change.
+
+use std::f64::consts::PI;
+
+use renderer::{
+    kurbo::{
+        Affine, BezPath, Circle, ParamCurve as _, ParamCurveArclen as _, ParamCurveNearest as _,
+        PathSeg, Point, Rect, Shape as _, Vec2,
+    },
+    Color, Font, MeasuredText, SceneItem, Style,
+};
+
+use crate::document::{Document, Element, ElementId, TextBox};
+
+/// Distance elbow connectors go straight out of the elements they are bound to before turning.
+const ELBOW_MARGIN: f64 = 24.0;
+/// Distance of the curve control points from the ends, relative to the distance between the ends.
+const CURVE_TENSION: f64 = 0.4;
+const LABEL_FONT_SIZE: f64 = 16.0;
+/// Space around the label cleared from the connector line.
+const LABEL_PADDING: f64 = 4.0;
+/// The board color, the label background hides the connector line behind it.
+const LABEL_BACKGROUND: Color = Color::WHITE;
+/// Accuracy of the arc length computations locating the label.
+const ARCLEN_ACCURACY: f64 = 0.1;
+/// Length of the arrow head sides, at most half the connector length.
+const ARROW_HEAD_LENGTH: f64 = 16.0;
+/// Angle between the arrow head sides and the connector line.
+const ARROW_HEAD_ANGLE: f64 = PI / 6.0;
+
+/// How the connector line goes from one end to the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Routing {
+    #[default]
+    Straight,
+    /// Horizontal and vertical segments, leaving the bound elements perpendicular to their side.
+    Elbow,
+    /// A curve leaving the bound elements perpendicular to their side.
+    Curved,
+}
+
+/// Decoration at an end of a connector.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrowHead {
+    #[default]
+    None,
+    /// Two sides of a triangle, stroked.
+    Open,
+    Triangle,
+    Circle,
+    Diamond,
+}
+
+impl ArrowHead {
+    pub const ALL: [ArrowHead; 5] = [
+        ArrowHead::None,
+        ArrowHead::Open,
+        ArrowHead::Triangle,
+        ArrowHead::Circle,
+        ArrowHead::Diamond,
+    ];
+
+    /// The head following this one in [`ArrowHead::ALL`], back to the first after the last.
+    pub fn next(self) -> ArrowHead {
+        let index = ArrowHead::ALL
+            .iter()
+            .position(|head| *head == self)
+            .unwrap_or_default();
+        ArrowHead::ALL[(index + 1) % ArrowHead::ALL.len()]
+    }
+}
+
+/// Place of an element a connector end is bound to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    /// Middle of a side of the element bounds, before its transform.
+    Top,
+    Right,
+    Bottom,
+    Left,
+    /// Point of the element outline, in the element space.
+    Edge(Point),
+}
+
+impl Anchor {
+    pub const SIDES: [Anchor; 4] = [Anchor::Top, Anchor::Right, Anchor::Bottom, Anchor::Left];
+
+    /// Board position of the anchor on `element`, along with the direction pointing away from the element.
+    pub fn resolve(self, element: &Element) -> (Point, Vec2) {
+        let bounds = element.local_outline().bounding_box();
+        let center = bounds.center();
+        let (point, outward) = match self {
+            Anchor::Top => (Point::new(center.x, bounds.y0), Vec2::new(0.0, -1.0)),
+            Anchor::Right => (Point::new(bounds.x1, center.y), Vec2::new(1.0, 0.0)),
+            Anchor::Bottom => (Point::new(center.x, bounds.y1), Vec2::new(0.0, 1.0)),
+            Anchor::Left => (Point::new(bounds.x0, center.y), Vec2::new(-1.0, 0.0)),
+            Anchor::Edge(point) => (point, point - center),
+        };
+        (
+            element.transform * point,
+            unit(linear(element.transform, outward)),
+        )
+    }
+
+    /// The anchor of `element` closest to the board `point`.
+    /// Sides within `snap` of the point are preferred over the closest point of the outline.
+    pub fn nearest(element: &Element, point: Point, snap: f64) -> Anchor {
+        let side = Anchor::SIDES
+            .into_iter()
+            .map(|side| (side, side.resolve(element).0.distance(point)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((side, distance)) = side {
+            if distance <= snap {
+                return side;
+            }
+        }
+        if element.transform.determinant().abs() < f64::EPSILON {
+            return Anchor::Top;
+        }
+        let local = element.transform.inverse() * point;
+        element
+            .local_outline()
+            .segments()
+            .map(|segment| {
+                let nearest = segment.nearest(local, 0.1);
+                (nearest.distance_sq, segment.eval(nearest.t))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map_or(Anchor::Top, |(_, point)| Anchor::Edge(point))
+    }
+}
+
+/// Element and anchor a connector end sticks to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Binding {
+    pub element: ElementId,
+    pub anchor: Anchor,
+}
+
+/// An end of a connector, in the connector space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectorEnd {
+    pub point: Point,
+    /// Unit vector the connector leaves the point along, away from the element bound.
+    /// Zero for ends placed freely, the routing picks a direction towards the other end.
+    pub direction: Vec2,
+    /// Element the end follows, `None` for ends placed freely.
+    pub binding: Option<Binding>,
+}
+
+/// Constructor functions
+impl ConnectorEnd {
+    /// End placed freely at `point`.
+    pub fn free(point: Point) -> ConnectorEnd {
+        ConnectorEnd {
+            point,
+            direction: Vec2::ZERO,
+            binding: None,
+        }
+    }
+
+    /// End bound to the `anchor` of `element`, at the board position of the anchor.
+    pub fn bound(element: &Element, anchor: Anchor) -> ConnectorEnd {
+        let (point, direction) = anchor.resolve(element);
+        ConnectorEnd {
+            point,
+            direction,
+            binding: Some(Binding {
+                element: element.id(),
+                anchor,
+            }),
+        }
+    }
+}
+
+/// A line or an arrow from `start` to `end`, with an optional label halfway.
+#[derive(Clone, Debug)]
+pub struct Connector {
+    pub start: ConnectorEnd,
+    pub end: ConnectorEnd,
+    pub routing: Routing,
+    pub start_head: ArrowHead,
+    pub end_head: ArrowHead,
+    /// Text shown in the middle of the line, nothing is shown when empty.
+    pub label: String,
+}
+
+/// Reference functions
+impl Connector {
+    /// The connector line, without its arrow heads.
+    pub fn path(&self) -> BezPath {
+        let points = self.control_points();
+        let mut path = BezPath::new();
+        path.move_to(points[0]);
+        match (self.routing, points.as_slice()) {
+            (Routing::Curved, [_, first, second, end]) => path.curve_to(*first, *second, *end),
+            _ => {
+                for point in &points[1..] {
+                    path.line_to(*point);
+                }
+            }
+        }
+        path
+    }
+
+    /// Elements the ends of the connector are bound to.
+    pub fn bound_elements(&self) -> impl Iterator<Item = ElementId> {
+        [self.start.binding, self.end.binding]
+            .into_iter()
+            .flatten()
+            .map(|binding| binding.element)
+    }
+
+    /// The label, centered on the middle of the line.
+    pub fn label_box(&self) -> TextBox<'_> {
+        let size = MeasuredText::new(&self.label, Font::default(), LABEL_FONT_SIZE, None).size();
+        TextBox {
+            content: &self.label,
+            origin: self.middle() - size.to_vec2() / 2.0,
+            font_size: LABEL_FONT_SIZE,
+            font: Font::default(),
+            width: None,
+        }
+    }
+
+    /// Whether `point` is within `reach` of the line, or on the label.
+    pub fn hit_test(&self, point: Point, reach: f64) -> bool {
+        let on_line = self
+            .path()
+            .segments()
+            .any(|segment| segment.nearest(point, 0.1).distance_sq <= reach * reach);
+        on_line || (!self.label.is_empty() && self.label_rect().contains(point))
+    }
+
+    /// The connector painted with `style`, arrow heads are filled with the stroke color.
+    pub(crate) fn scene_item(&self, style: &Style) -> SceneItem {
+        let mut line = self.path();
+        let mut filled = BezPath::new();
+        for (head, tip, direction) in self.heads() {
+            let length = ARROW_HEAD_LENGTH.min(self.length() / 2.0);
+            match head {
+                ArrowHead::None => {}
+                ArrowHead::Open => line.extend(head_path(head, tip, direction, length)),
+                _ => filled.extend(head_path(head, tip, direction, length)),
+            }
+        }
+
+        let color = style
+            .stroke
+            .as_ref()
+            .map_or(Color::BLACK, |stroke| stroke.color);
+        let mut items = vec![SceneItem::Path {
+            path: line,
+            style: Style {
+                stroke: style.stroke.clone(),
+                fill: None,
+            },
+        }];
+        if !filled.elements().is_empty() {
+            items.push(SceneItem::Path {
+                path: filled,
+                style: Style {
+                    stroke: style.stroke.clone(),
+                    fill: Some(color),
+                },
+            });
+        }
+        if !self.label.is_empty() {
+            items.push(SceneItem::Path {
+                path: self.label_rect().to_path(0.1),
+                style: Style {
+                    stroke: None,
+                    fill: Some(LABEL_BACKGROUND),
+                },
+            });
+        }
+        // The label is painted even when empty, for the caret to show while it is edited
+        let label = self.label_box();
+        items.push(SceneItem::Text {
+            content: self.label.clone(),
+            origin: label.origin,
+            font_size: label.font_size,
+            font: Font::default(),
+            max_width: None,
+            style: Style {
+                stroke: None,
+                fill: Some(color),
+            },
+            editing: None,
+        });
+        SceneItem::Group {
+            transform: Affine::IDENTITY,
+            items,
+        }
+    }
+}
+
+/// Mutable functions
+impl Connector {
+    /// Move the bound ends to where their anchors are on the elements of `document`,
+    /// `transform` places the connector on the board.
+    pub fn reroute(&mut self, document: &Document, transform: Affine) {
+        if transform.determinant().abs() < f64::EPSILON {
+            return;
+        }
+        let inverse = transform.inverse();
+        for end in [&mut self.start, &mut self.end] {
+            let Some(binding) = end.binding else {
+                continue;
+            };
+            let Some(element) = document.get(binding.element) else {
+                continue;
+            };
+            let (point, direction) = binding.anchor.resolve(element);
+            end.point = inverse * point;
+            end.direction = unit(linear(inverse, direction));
+        }
+    }
+
+    /// Release the ends bound to the element `id`, they stay where they are.
+    pub fn unbind(&mut self, id: ElementId) {
+        for end in [&mut self.start, &mut self.end] {
+            if end.binding.map_or(false, |binding| binding.element == id) {
+                end.binding = None;
+            }
+        }
+    }
+}
+
+/// Private functions
+impl Connector {
+    /// Points the line goes through, or the start, control points and end of the curve.
+    fn control_points(&self) -> Vec<Point> {
+        let (start, end) = (self.start.point, self.end.point);
+        match self.routing {
+            Routing::Straight => vec![start, end],
+            Routing::Curved => {
+                let reach = start.distance(end) * CURVE_TENSION;
+                let leave = |end: &ConnectorEnd, towards: Point| {
+                    if end.direction == Vec2::ZERO {
+                        unit(towards - end.point)
+                    } else {
+                        end.direction
+                    }
+                };
+                vec![
+                    start,
+                    start + leave(&self.start, end) * reach,
+                    end + leave(&self.end, start) * reach,
+                    end,
+                ]
+            }
+            Routing::Elbow => self.elbow_points(),
+        }
+    }
+
+    /// Corners of the elbow line, turning halfway between the ends.
+    fn elbow_points(&self) -> Vec<Point> {
+        let (start, end) = (self.start.point, self.end.point);
+        let out = |end: &ConnectorEnd, towards: Point| {
+            let direction = axis(end.direction, towards - end.point);
+            // Bound ends go straight out of their element before turning
+            let margin = if end.binding.is_some() {
+                ELBOW_MARGIN
+            } else {
+                0.0
+            };
+            (direction, end.point + direction * margin)
+        };
+        let (start_direction, from) = out(&self.start, end);
+        let (end_direction, to) = out(&self.end, start);
+
+        let mut points = vec![start, from];
+        match (start_direction.x != 0.0, end_direction.x != 0.0) {
+            (true, true) => {
+                let x = (from.x + to.x) / 2.0;
+                points.push(Point::new(x, from.y));
+                points.push(Point::new(x, to.y));
+            }
+            (false, false) => {
+                let y = (from.y + to.y) / 2.0;
+                points.push(Point::new(from.x, y));
+                points.push(Point::new(to.x, y));
+            }
+            (true, false) => points.push(Point::new(to.x, from.y)),
+            (false, true) => points.push(Point::new(from.x, to.y)),
+        }
+        points.push(to);
+        points.push(end);
+        points.dedup_by(|a, b| a.distance(*b) < f64::EPSILON);
+        points
+    }
+
+    /// Head, tip and direction the line arrives at the tip along, for each end.
+    fn heads(&self) -> [(ArrowHead, Point, Vec2); 2] {
+        let points = self.control_points();
+        let arriving = |mut points: Vec<Point>| {
+            let tip = points.pop().unwrap_or_default();
+            let from = points
+                .into_iter()
+                .rev()
+                .find(|point| point.distance(tip) > f64::EPSILON)
+                .unwrap_or(tip);
+            (tip, unit(tip - from))
+        };
+        let (end, end_direction) = arriving(points.clone());
+        let (start, start_direction) = arriving(points.into_iter().rev().collect());
+        [
+            (self.start_head, start, start_direction),
+            (self.end_head, end, end_direction),
+        ]
+    }
+
+    /// Length of the line.
+    fn length(&self) -> f64 {
+        self.path()
+            .segments()
+            .map(|segment| segment.arclen(ARCLEN_ACCURACY))
+            .sum()
+    }
+
+    /// Point of the line halfway between the ends.
+    fn middle(&self) -> Point {
+        let segments: Vec<PathSeg> = self.path().segments().collect();
+        let mut remaining = self.length() / 2.0;
+        for segment in segments {
+            let length = segment.arclen(ARCLEN_ACCURACY);
+            if remaining <= length {
+                return segment.eval(segment.inv_arclen(remaining, ARCLEN_ACCURACY));
+            }
+            remaining -= length;
+        }
+        self.start.point.midpoint(self.end.point)
+    }
+
+    /// Area of the label background.
+    fn label_rect(&self) -> Rect {
+        let label = self.label_box();
+        let size = label.measure().size();
+        Rect::from_origin_size(label.origin, size).inflate(LABEL_PADDING, LABEL_PADDING)
+    }
+}
+
+/// Outline of `head` with its tip at `tip`, pointing along `direction`.
+fn head_path(head: ArrowHead, tip: Point, direction: Vec2, length: f64) -> BezPath {
+    let back = (-direction).atan2();
+    let side = |angle: f64| tip + Vec2::from_angle(back + angle) * length;
+    let mut path = BezPath::new();
+    match head {
+        ArrowHead::None => {}
+        ArrowHead::Open => {
+            path.move_to(side(ARROW_HEAD_ANGLE));
+            path.line_to(tip);
+            path.line_to(side(-ARROW_HEAD_ANGLE));
+        }
+        ArrowHead::Triangle => {
+            path.move_to(side(ARROW_HEAD_ANGLE));
+            path.line_to(tip);
+            path.line_to(side(-ARROW_HEAD_ANGLE));
+            path.close_path();
+        }
+        ArrowHead::Circle => {
+            path = Circle::new(tip - direction * length / 3.0, length / 3.0).to_path(0.1);
+        }
+        ArrowHead::Diamond => {
+            let across = Vec2::new(-direction.y, direction.x) * length / 4.0;
+            let middle = tip - direction * length / 2.0;
+            path.move_to(tip);
+            path.line_to(middle + across);
+            path.line_to(tip - direction * length);
+            path.line_to(middle - across);
+            path.close_path();
+        }
+    }
+    path
+}
+
+/// Unit vector along `vector`, zero for a zero vector.
+fn unit(vector: Vec2) -> Vec2 {
+    let length = vector.hypot();
+    if length < f64::EPSILON {
+        Vec2::ZERO
+    } else {
+        vector / length
+    }
+}
+
+/// `vector` transformed without the translation of `transform`.
+fn linear(transform: Affine, vector: Vec2) -> Vec2 {
+    let [a, b, c, d, _, _] = transform.as_coeffs();
+    Vec2::new(a * vector.x + c * vector.y, b * vector.x + d * vector.y)
+}
+
+/// Horizontal or vertical unit vector closest to `direction`, or to `fallback` when `direction` is zero.
+fn axis(direction: Vec2, fallback: Vec2) -> Vec2 {
+    let direction = if direction == Vec2::ZERO {
+        fallback
+    } else {
+        direction
+    };
+    if direction.x.abs() >= direction.y.abs() {
+        Vec2::new(if direction.x < 0.0 { -1.0 } else { 1.0 }, 0.0)
+    } else {
+        Vec2::new(0.0, direction.y.signum())
+    }
+}
@@ -0,0 +1,1274 @@
+//! The board content: an ordered collection of drawable elements.
+
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+
+use renderer::{
+    kurbo::{
+        Affine, BezPath, Circle, CubicBez, Line, ParamCurve as _, ParamCurveNearest as _, Point,
+        Rect, Shape as _, Vec2,
+    },
+    Color, Font, MeasuredText, SceneItem, Style, MIN_ZOOM,
+};
+
+use crate::{
+    bitmap::{Bitmap, BitmapHash},
+    connector::Connector,
+    smoothing::simplify,
+    spatial::SpatialIndex,
+};
+
+/// Stable identifier of an element. Identifiers are never reused within a [`Document`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(u64);
+
+impl ElementId {
+    pub(crate) fn new(raw: u64) -> ElementId {
+        ElementId(raw)
+    }
+
+    pub(crate) fn raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// Straight segments each curve of a curved stroke is flattened into, for outlines and hit testing.
+const CURVE_SAMPLES: usize = 8;
+/// Samples from which strokes carry simplified versions of themselves, painted when zoomed out.
+const MIN_SIMPLIFIED_SAMPLES: usize = 32;
+/// Distance in board units the finest simplified version of a stroke deviates from it.
+const SIMPLIFY_TOLERANCE: f64 = 1.0;
+/// Factor the deviation grows by from a simplified version to the next coarser one.
+const SIMPLIFY_TOLERANCE_STEP: f64 = 4.0;
+/// Deviation in pixels under which a simplified stroke looks the same as the stroke.
+const MAX_SIMPLIFIED_ERROR: f64 = 0.5;
+
+/// Side of new sticky notes.
+const NOTE_SIZE: f64 = 200.0;
+/// Space between the sides of a sticky note and its text.
+const NOTE_PADDING: f64 = 12.0;
+/// Font size of the sticky note text when it fits.
+const NOTE_FONT_SIZE: f64 = 24.0;
+/// Smallest font size the sticky note text shrinks to, longer text overflows the note.
+const NOTE_MIN_FONT_SIZE: f64 = 6.0;
+/// Factor the sticky note font size shrinks by at each step until the text fits.
+const NOTE_FONT_SHRINK: f64 = 0.9;
+const NOTE_CORNER_RADIUS: f64 = 4.0;
+const NOTE_TEXT_COLOR: Color = Color::rgb8(0x21, 0x21, 0x21);
+
+/// A freehand line going through every point in order.
+#[derive(Clone, Debug, Default)]
+pub struct Stroke {
+    points: Vec<Point>,
+    /// Width at each point relative to the style width, such as from the stylus pressure.
+    /// Empty for strokes of uniform width, one per point otherwise.
+    widths: Vec<f64>,
+    /// Whether cubic curves go smoothly through the points, straight segments join them otherwise.
+    curved: bool,
+    /// Simplified versions of the stroke, emptied whenever the stroke changes.
+    simplified: SimplifiedCache,
+}
+
+/// Versions of a stroke simplified further and further, each with the largest number of pixels per unit
+/// it is painted at.
+type SimplifiedLevels = Arc<[(f64, Stroke)]>;
+
+/// Simplified versions of a stroke along with the style width they were made for,
+/// shared by the clones of the stroke.
+#[derive(Clone, Debug, Default)]
+struct SimplifiedCache(Arc<Mutex<Option<(f64, SimplifiedLevels)>>>);
+
+/// Constructor functions
+impl Stroke {
+    /// Stroke going through `points`, `widths` is empty for a uniform width or holds one width per point.
+    pub fn new(points: Vec<Point>, widths: Vec<f64>, curved: bool) -> Stroke {
+        Stroke {
+            points,
+            widths,
+            curved,
+            simplified: SimplifiedCache::default(),
+        }
+    }
+}
+
+/// Mutable functions
+impl Stroke {
+    /// Add a point at the end of the stroke, with its width relative to the style width when known.
+    /// The points before a first known width keep the style width, the next ones the last width.
+    pub fn push(&mut self, point: Point, width: Option<f64>) {
+        match width {
+            Some(width) => {
+                self.widths.resize(self.points.len(), 1.0);
+                self.widths.push(width);
+            }
+            None if self.is_variable() => {
+                let last = self.widths.last().copied().unwrap_or(1.0);
+                self.widths.push(last);
+            }
+            None => {}
+        }
+        self.points.push(point);
+        self.simplified = SimplifiedCache::default();
+    }
+
+    pub fn set_curved(&mut self, curved: bool) {
+        self.curved = curved;
+        self.simplified = SimplifiedCache::default();
+    }
+}
+
+/// Reference functions
+impl Stroke {
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Width at each point relative to the style width, empty for strokes of uniform width.
+    pub fn widths(&self) -> &[f64] {
+        &self.widths
+    }
+
+    pub fn is_curved(&self) -> bool {
+        self.curved
+    }
+
+    /// Whether the width varies along the stroke.
+    pub fn is_variable(&self) -> bool {
+        !self.widths.is_empty()
+    }
+
+    /// Largest width along the stroke relative to the style width.
+    pub fn max_width(&self) -> f64 {
+        self.widths.iter().copied().fold(1.0, f64::max)
+    }
+
+    /// Width at the point at `index` relative to the style width.
+    pub fn width(&self, index: usize) -> f64 {
+        self.widths.get(index).copied().unwrap_or(1.0)
+    }
+
+    /// Outline of the stroke painted `width` wide, scaled at each point by the stroke widths.
+    /// The outline is meant to be filled with the nonzero rule, its pieces overlap.
+    pub fn outline(&self, width: f64) -> BezPath {
+        let samples = self.samples();
+        let radius = |relative: f64| width * relative.max(0.0) / 2.0;
+        let mut path = BezPath::new();
+        for (point, relative) in &samples {
+            path.extend(Circle::new(*point, radius(*relative)).path_elements(0.1));
+        }
+        for segment in samples.windows(2) {
+            let ((start, start_width), (end, end_width)) = (segment[0], segment[1]);
+            let length = (end - start).hypot();
+            if length < f64::EPSILON {
+                continue;
+            }
+            let direction = (end - start) / length;
+            let normal = Vec2::new(-direction.y, direction.x);
+            let (start_radius, end_radius) = (radius(start_width), radius(end_width));
+            // Same winding as the circles, overlaps must not cancel each other
+            path.move_to(start - normal * start_radius);
+            path.line_to(end - normal * end_radius);
+            path.line_to(end + normal * end_radius);
+            path.line_to(start + normal * start_radius);
+            path.close_path();
+        }
+        path
+    }
+
+    /// Describe how the stroke is painted with `style`, along with polylines simplified further and further
+    /// for long strokes, the renderer picks the coarsest one which looks the same at the frame scale.
+    pub fn scene_item(&self, style: Style) -> SceneItem {
+        let width = style.stroke.as_ref().map_or(0.0, |stroke| stroke.width);
+        let levels: Vec<(f64, SceneItem)> = self
+            .simplified(width)
+            .iter()
+            .map(|(scale, simplified)| (*scale, simplified.detailed_scene_item(style.clone())))
+            .collect();
+
+        let item = self.detailed_scene_item(style);
+        if levels.is_empty() {
+            return item;
+        }
+        SceneItem::Simplified {
+            item: Box::new(item),
+            levels,
+        }
+    }
+
+    /// Compute the simplified versions painted for the style `width` ahead of the first paint,
+    /// once the stroke is finished.
+    pub fn cache_simplified(&self, width: f64) {
+        self.simplified(width);
+    }
+
+    /// Area covered by the stroke painted `width` wide.
+    pub fn bounds(&self, width: f64) -> Rect {
+        let Some(first) = self.points.first() else {
+            return Rect::ZERO;
+        };
+        let mut bounds = Rect::from_points(*first, *first);
+        for index in 1..self.points.len() {
+            bounds = if self.curved {
+                bounds.union(self.curve(index - 1).bounding_box())
+            } else {
+                bounds.union_pt(self.points[index])
+            };
+        }
+        let half_width = width * self.max_width() / 2.0;
+        bounds.inflate(half_width, half_width)
+    }
+
+    /// Describe how every point of the stroke is painted with `style`, without the simplified versions.
+    /// Strokes still being drawn are painted this way, a new point would outdate their simplified versions.
+    pub fn detailed_scene_item(&self, style: Style) -> SceneItem {
+        match &style.stroke {
+            // Variable widths are painted as a filled outline
+            Some(stroke_style) if self.is_variable() => SceneItem::Path {
+                path: self.outline(stroke_style.width),
+                style: Style {
+                    stroke: None,
+                    fill: Some(stroke_style.color),
+                },
+            },
+            _ => SceneItem::Path {
+                path: self.path(),
+                style,
+            },
+        }
+    }
+
+    pub fn path(&self) -> BezPath {
+        let mut path = BezPath::new();
+        let Some(first) = self.points.first() else {
+            return path;
+        };
+        path.move_to(*first);
+        // A single point still shows up as a dot with round caps
+        path.line_to(*first);
+        for index in 1..self.points.len() {
+            if self.curved {
+                let curve = self.curve(index - 1);
+                path.curve_to(curve.p1, curve.p2, curve.p3);
+            } else {
+                path.line_to(self.points[index]);
+            }
+        }
+        path
+    }
+
+    /// Shortest distance from `point` to the center line of the stroke.
+    pub fn distance(&self, point: Point) -> f64 {
+        let samples = self.samples();
+        match samples.as_slice() {
+            [] => f64::INFINITY,
+            [(single, _)] => single.distance(point),
+            samples => samples
+                .windows(2)
+                .map(|segment| {
+                    Line::new(segment[0].0, segment[1].0)
+                        .nearest(point, 0.0)
+                        .distance_sq
+                        .sqrt()
+                })
+                .fold(f64::INFINITY, f64::min),
+        }
+    }
+
+    /// Cut out the parts of the stroke within `radius` of `center`, returns the pieces left on each side.
+    /// `None` when the stroke does not come within reach, the pieces are straight segments.
+    pub fn erase(&self, center: Point, radius: f64) -> Option<Vec<Stroke>> {
+        let samples = self.samples();
+        // The ends of the pieces cut earlier lie on the edge of the eraser, erasing again at the same
+        // place must keep them rather than count their last point within
+        let edge = radius * (1.0 - 1e-9);
+        let inside = |point: Point| point.distance(center) < edge;
+        if samples.iter().all(|(point, _)| !inside(*point))
+            && samples.windows(2).all(|segment| {
+                Line::new(segment[0].0, segment[1].0)
+                    .nearest(center, 0.0)
+                    .distance_sq
+                    > radius * radius
+            })
+        {
+            return None;
+        }
+
+        let mut pieces = Vec::new();
+        let mut piece: Vec<(Point, f64)> = Vec::new();
+        let lerp = |(start, start_width): (Point, f64), (end, end_width): (Point, f64), t: f64| {
+            (
+                start.lerp(end, t),
+                start_width + (end_width - start_width) * t,
+            )
+        };
+        if let Some(first) = samples.first().filter(|(point, _)| !inside(*point)) {
+            piece.push(*first);
+        }
+        for segment in samples.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            // Parameters where the segment enters and leaves the eraser circle
+            let direction = end.0 - start.0;
+            let offset = start.0 - center;
+            let a = direction.hypot2();
+            let b = 2.0 * offset.dot(direction);
+            let c = offset.hypot2() - radius * radius;
+            let discriminant = b * b - 4.0 * a * c;
+            let crossing = (a > f64::EPSILON && discriminant > 0.0).then(|| {
+                let root = discriminant.sqrt();
+                ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a))
+            });
+            match crossing {
+                Some((enter, leave)) if enter < 1.0 && leave > 0.0 => {
+                    if enter > 0.0 {
+                        piece.push(lerp(start, end, enter));
+                    }
+                    if piece.len() > 1 {
+                        pieces.push(std::mem::take(&mut piece));
+                    } else {
+                        piece.clear();
+                    }
+                    if leave < 1.0 {
+                        piece.push(lerp(start, end, leave));
+                        piece.push(end);
+                    }
+                }
+                _ => {
+                    if !inside(end.0) {
+                        piece.push(end);
+                    }
+                }
+            }
+        }
+        if piece.len() > 1 {
+            pieces.push(piece);
+        }
+
+        Some(
+            pieces
+                .into_iter()
+                .map(|piece| {
+                    let widths = if self.is_variable() {
+                        piece.iter().map(|(_, width)| *width).collect()
+                    } else {
+                        Vec::new()
+                    };
+                    Stroke::new(
+                        piece.iter().map(|(point, _)| *point).collect(),
+                        widths,
+                        false,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Versions of the stroke simplified further and further for the style `width`, each along with
+    /// the largest number of pixels per unit it looks the same as the stroke at. Computed once per width.
+    fn simplified(&self, width: f64) -> SimplifiedLevels {
+        let mut cache = self.simplified.0.lock();
+        if let Some((cached_width, levels)) = &*cache {
+            if *cached_width == width {
+                return Arc::clone(levels);
+            }
+        }
+        let polyline = self.polyline();
+        let mut levels = Vec::new();
+        let mut points = polyline.points.len();
+        let mut tolerance = SIMPLIFY_TOLERANCE;
+        // Coarser versions would only be painted below the smallest zoom
+        while points >= MIN_SIMPLIFIED_SAMPLES && MAX_SIMPLIFIED_ERROR / tolerance >= MIN_ZOOM {
+            let simplified = simplify(&polyline, tolerance, width);
+            // Versions dropping few points are not worth painting instead
+            if simplified.points.len() * 4 <= points * 3 {
+                points = simplified.points.len();
+                levels.push((MAX_SIMPLIFIED_ERROR / tolerance, simplified));
+            }
+            tolerance *= SIMPLIFY_TOLERANCE_STEP;
+        }
+        let levels: SimplifiedLevels = levels.into();
+        *cache = Some((width, Arc::clone(&levels)));
+        levels
+    }
+
+    /// Curve from the point at `index` to the next one, its tangents follow the neighbouring points.
+    fn curve(&self, index: usize) -> CubicBez {
+        let last = self.points.len() - 1;
+        let before = self.points[index.saturating_sub(1)];
+        let start = self.points[index];
+        let end = self.points[index + 1];
+        let after = self.points[(index + 2).min(last)];
+        // Catmull-Rom spline through the points, as cubic bezier control points
+        CubicBez::new(
+            start,
+            start + (end - before) / 6.0,
+            end - (after - start) / 6.0,
+            end,
+        )
+    }
+
+    /// Points along the stroke with their width relative to the style width, curves are flattened.
+    /// The stroke made of straight segments between its samples.
+    fn polyline(&self) -> Stroke {
+        let samples = self.samples();
+        let widths = if self.is_variable() {
+            samples.iter().map(|(_, width)| *width).collect()
+        } else {
+            Vec::new()
+        };
+        Stroke::new(
+            samples.iter().map(|(point, _)| *point).collect(),
+            widths,
+            false,
+        )
+    }
+
+    fn samples(&self) -> Vec<(Point, f64)> {
+        if !self.curved {
+            return self
+                .points
+                .iter()
+                .enumerate()
+                .map(|(index, point)| (*point, self.width(index)))
+                .collect();
+        }
+        let mut samples = Vec::with_capacity(self.points.len() * CURVE_SAMPLES);
+        if let Some(first) = self.points.first() {
+            samples.push((*first, self.width(0)));
+        }
+        for index in 1..self.points.len() {
+            let curve = self.curve(index - 1);
+            let (start_width, end_width) = (self.width(index - 1), self.width(index));
+            for step in 1..=CURVE_SAMPLES {
+                let t = step as f64 / CURVE_SAMPLES as f64;
+                samples.push((curve.eval(t), start_width + (end_width - start_width) * t));
+            }
+        }
+        samples
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeKind {
+    Rectangle,
+    Ellipse,
+    /// Four sided shape with its corners at the middle of the rect sides.
+    Diamond,
+}
+
+/// A primitive shape fitted into `rect`.
+#[derive(Clone, Debug)]
+pub struct Shape {
+    pub kind: ShapeKind,
+    pub rect: Rect,
+    /// Radius of the rounded corners of rectangles, `0.0` for sharp corners.
+    pub corner_radius: f64,
+}
+
+impl Shape {
+    pub fn path(&self) -> BezPath {
+        let rect = self.rect.abs();
+        match self.kind {
+            ShapeKind::Rectangle => {
+                let radius = self
+                    .corner_radius
+                    .clamp(0.0, rect.width().min(rect.height()) / 2.0);
+                if radius > 0.0 {
+                    rect.to_rounded_rect(radius).to_path(0.1)
+                } else {
+                    rect.to_path(0.1)
+                }
+            }
+            ShapeKind::Ellipse => rect.to_ellipse().to_path(0.1),
+            ShapeKind::Diamond => {
+                let center = rect.center();
+                let mut path = BezPath::new();
+                path.move_to(Point::new(center.x, rect.y0));
+                path.line_to(Point::new(rect.x1, center.y));
+                path.line_to(Point::new(center.x, rect.y1));
+                path.line_to(Point::new(rect.x0, center.y));
+                path.close_path();
+                path
+            }
+        }
+    }
+
+    /// Whether `point` is within `reach` of the shape outline, or inside the shape when `filled`.
+    pub fn hit_test(&self, point: Point, reach: f64, filled: bool) -> bool {
+        let path = self.path();
+        if filled && path.contains(point) {
+            return true;
+        }
+        let mut segments = path.segments();
+        segments.any(|segment| segment.nearest(point, 0.1).distance_sq <= reach * reach)
+    }
+}
+
+/// A block of text with its top left corner at `origin`, colored with the style fill.
+#[derive(Clone, Debug)]
+pub struct Text {
+    pub content: String,
+    pub origin: Point,
+    pub font_size: f64,
+    pub font: Font,
+    /// Width the lines wrap at, `None` to only break lines at line breaks.
+    pub width: Option<f64>,
+}
+
+/// An area of a bitmap scaled to fit `rect`.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub rect: Rect,
+    /// Pixels shared with every image of the document showing the same file.
+    pub bitmap: Arc<Bitmap>,
+    /// Area of the bitmap shown, in pixels.
+    pub crop: Rect,
+}
+
+/// Constructor functions
+impl Image {
+    /// The whole `bitmap` at its natural size, one board unit per pixel, centered on `center`.
+    pub fn new(bitmap: Arc<Bitmap>, center: Point) -> Image {
+        Image {
+            rect: Rect::from_center_size(center, bitmap.size()),
+            crop: bitmap.rect(),
+            bitmap,
+        }
+    }
+}
+
+/// Reference functions
+impl Image {
+    /// Area the whole bitmap would cover with the scale of the crop, the rect when uncropped.
+    pub fn uncropped_rect(&self) -> Rect {
+        let scale_x = self.rect.width() / self.crop.width();
+        let scale_y = self.rect.height() / self.crop.height();
+        Rect::new(
+            self.rect.x0 - self.crop.x0 * scale_x,
+            self.rect.y0 - self.crop.y0 * scale_y,
+            self.rect.x1 + (self.bitmap.size().width - self.crop.x1) * scale_x,
+            self.rect.y1 + (self.bitmap.size().height - self.crop.y1) * scale_y,
+        )
+    }
+}
+
+/// Colors of the sticky notes palette.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoteColor {
+    #[default]
+    Yellow,
+    Orange,
+    Pink,
+    Purple,
+    Blue,
+    Green,
+}
+
+impl NoteColor {
+    pub const ALL: [NoteColor; 6] = [
+        NoteColor::Yellow,
+        NoteColor::Orange,
+        NoteColor::Pink,
+        NoteColor::Purple,
+        NoteColor::Blue,
+        NoteColor::Green,
+    ];
+
+    pub fn color(self) -> Color {
+        match self {
+            NoteColor::Yellow => Color::rgb8(0xFF, 0xF1, 0x76),
+            NoteColor::Orange => Color::rgb8(0xFF, 0xCC, 0x80),
+            NoteColor::Pink => Color::rgb8(0xF8, 0xBB, 0xD0),
+            NoteColor::Purple => Color::rgb8(0xD1, 0xC4, 0xE9),
+            NoteColor::Blue => Color::rgb8(0xB3, 0xE5, 0xFC),
+            NoteColor::Green => Color::rgb8(0xC5, 0xE1, 0xA5),
+        }
+    }
+}
+
+/// A sticky note: a colored square with its text wrapped inside, the text shrinks to fit.
+#[derive(Clone, Debug)]
+pub struct Note {
+    pub rect: Rect,
+    pub content: String,
+    pub color: NoteColor,
+}
+
+/// Constructor functions
+impl Note {
+    /// Empty note of the default size centered on `center`.
+    pub fn new(center: Point, color: NoteColor) -> Note {
+        Note {
+            rect: Rect::from_center_size(center, (NOTE_SIZE, NOTE_SIZE)),
+            content: String::new(),
+            color,
+        }
+    }
+}
+
+/// Reference functions
+impl Note {
+    /// Width the text wraps at.
+    pub fn text_width(&self) -> f64 {
+        (self.rect.width().abs() - 2.0 * NOTE_PADDING).max(0.0)
+    }
+
+    /// Largest font size the text fits the note with, down to a minimum size.
+    /// The text is measured as it is laid out when painted.
+    pub fn font_size(&self) -> f64 {
+        let width = self.text_width();
+        let height = self.rect.height().abs() - 2.0 * NOTE_PADDING;
+        let fits = |font_size: f64| {
+            let text = MeasuredText::new(&self.content, Font::default(), font_size, Some(width));
+            text.size().height <= height
+        };
+        let mut font_size = NOTE_FONT_SIZE;
+        while font_size > NOTE_MIN_FONT_SIZE && !fits(font_size) {
+            font_size = (font_size * NOTE_FONT_SHRINK).max(NOTE_MIN_FONT_SIZE);
+        }
+        font_size
+    }
+
+    fn scene_item(&self, style: &Style) -> SceneItem {
+        let rect = self.rect.abs();
+        let radius = NOTE_CORNER_RADIUS.min(rect.width().min(rect.height()) / 2.0);
+        let square = SceneItem::Path {
+            path: rect.to_rounded_rect(radius).to_path(0.1),
+            style: Style {
+                stroke: style.stroke.clone(),
+                fill: Some(self.color.color()),
+            },
+        };
+        let text = SceneItem::Text {
+            content: self.content.clone(),
+            origin: rect.origin() + (NOTE_PADDING, NOTE_PADDING),
+            font_size: self.font_size(),
+            font: Font::default(),
+            max_width: Some(self.text_width()),
+            style: Style {
+                stroke: None,
+                fill: Some(NOTE_TEXT_COLOR),
+            },
+            editing: None,
+        };
+        SceneItem::Group {
+            transform: Affine::IDENTITY,
+            items: vec![square, text],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ElementKind {
+    Stroke(Stroke),
+    Shape(Shape),
+    Text(Text),
+    Image(Image),
+    Note(Note),
+    /// Boxed, connectors take much more room than the other elements.
+    Connector(Box<Connector>),
+}
+
+/// Text of an element laid out in the element space.
+#[derive(Clone, Copy, Debug)]
+pub struct TextBox<'a> {
+    pub content: &'a str,
+    /// Top left corner of the first line.
+    pub origin: Point,
+    pub font_size: f64,
+    pub font: Font,
+    /// Width the lines wrap at, `None` when they only break at line breaks.
+    pub width: Option<f64>,
+}
+
+/// Reference functions
+impl<'a> TextBox<'a> {
+    /// The text laid out as it is painted, relative to its origin.
+    pub fn measure(&self) -> MeasuredText<'a> {
+        MeasuredText::new(self.content, self.font, self.font_size, self.width)
+    }
+}
+
+impl ElementKind {
+    /// The text the element holds, `None` for elements without text.
+    pub fn text_box(&self) -> Option<TextBox<'_>> {
+        match self {
+            ElementKind::Text(text) => Some(TextBox {
+                content: &text.content,
+                origin: text.origin,
+                font_size: text.font_size,
+                font: text.font,
+                width: text.width,
+            }),
+            ElementKind::Note(note) => Some(TextBox {
+                content: &note.content,
+                origin: note.rect.abs().origin() + (NOTE_PADDING, NOTE_PADDING),
+                font_size: note.font_size(),
+                font: Font::default(),
+                width: Some(note.text_width()),
+            }),
+            ElementKind::Connector(connector) => Some(connector.label_box()),
+            ElementKind::Stroke(_) | ElementKind::Shape(_) | ElementKind::Image(_) => None,
+        }
+    }
+
+    /// Content of the elements holding text, to edit it.
+    pub fn content_mut(&mut self) -> Option<&mut String> {
+        match self {
+            ElementKind::Text(text) => Some(&mut text.content),
+            ElementKind::Note(note) => Some(&mut note.content),
+            ElementKind::Connector(connector) => Some(&mut connector.label),
+            ElementKind::Stroke(_) | ElementKind::Shape(_) | ElementKind::Image(_) => None,
+        }
+    }
+}
+
+/// A drawable item of the board.
+#[derive(Clone, Debug)]
+pub struct Element {
+    id: ElementId,
+    pub kind: ElementKind,
+    pub style: Style,
+    /// Placement of the element geometry on the board, such as a move, scale or rotation.
+    pub transform: Affine,
+}
+
+/// Constructor functions
+impl Element {
+    /// Recreate an element with a known id, such as one read back from a file.
+    pub(crate) fn with_id(id: ElementId, kind: ElementKind, style: Style) -> Element {
+        Element {
+            id,
+            kind,
+            style,
+            transform: Affine::IDENTITY,
+        }
+    }
+}
+
+/// Reference functions
+impl Element {
+    pub fn id(&self) -> ElementId {
+        self.id
+    }
+
+    /// Describe how the element is painted by the renderer.
+    pub fn scene_item(&self) -> SceneItem {
+        let item = self.local_scene_item();
+        if self.transform == Affine::IDENTITY {
+            item
+        } else {
+            SceneItem::Group {
+                transform: self.transform,
+                items: vec![item],
+            }
+        }
+    }
+
+    /// Area covered by the element on the board, including its outline.
+    pub fn bounds(&self) -> Rect {
+        self.transform.transform_rect_bbox(self.local_bounds())
+    }
+
+    /// Whether the board `point` is on the element, or within `tolerance` of it.
+    pub fn hit_test(&self, point: Point, tolerance: f64) -> bool {
+        let determinant = self.transform.determinant();
+        if determinant.abs() < f64::EPSILON {
+            return false;
+        }
+        // Test in the element space, where the tolerance shrinks or grows with the transform scale
+        let point = self.transform.inverse() * point;
+        let tolerance = tolerance / determinant.abs().sqrt();
+        let mut half_width = self
+            .style
+            .stroke
+            .as_ref()
+            .map_or(0.0, |stroke| stroke.width / 2.0);
+        if let ElementKind::Stroke(stroke) = &self.kind {
+            half_width *= stroke.max_width();
+        }
+        let reach = half_width + tolerance;
+
+        match &self.kind {
+            ElementKind::Stroke(stroke) => stroke.distance(point) <= reach,
+            // Unfilled shapes are only hit on their outline
+            ElementKind::Shape(shape) => shape.hit_test(point, reach, self.style.fill.is_some()),
+            ElementKind::Text(_) | ElementKind::Image(_) | ElementKind::Note(_) => self
+                .local_bounds()
+                .inflate(tolerance, tolerance)
+                .contains(point),
+            ElementKind::Connector(connector) => connector.hit_test(point, reach),
+        }
+    }
+
+    /// Whether connector ends can be bound to the element.
+    pub fn is_bindable(&self) -> bool {
+        match &self.kind {
+            ElementKind::Shape(_)
+            | ElementKind::Text(_)
+            | ElementKind::Image(_)
+            | ElementKind::Note(_) => true,
+            ElementKind::Stroke(_) | ElementKind::Connector(_) => false,
+        }
+    }
+
+    /// Area covered by the element geometry, before its transform.
+    pub(crate) fn local_bounds(&self) -> Rect {
+        match &self.kind {
+            // The simplified versions of strokes are within their bounds
+            ElementKind::Stroke(stroke) => {
+                let width = self
+                    .style
+                    .stroke
+                    .as_ref()
+                    .map_or(0.0, |stroke| stroke.width);
+                stroke.bounds(width)
+            }
+            _ => self.local_scene_item().bounding_box().unwrap_or_default(),
+        }
+    }
+
+    /// The element painted without its transform.
+    fn local_scene_item(&self) -> SceneItem {
+        let style = self.style.clone();
+        match &self.kind {
+            ElementKind::Stroke(stroke) => stroke.scene_item(style),
+            ElementKind::Shape(shape) => SceneItem::Path {
+                path: shape.path(),
+                style,
+            },
+            ElementKind::Text(text) => SceneItem::Text {
+                content: text.content.clone(),
+                origin: text.origin,
+                font_size: text.font_size,
+                font: text.font,
+                max_width: text.width,
+                style,
+                editing: None,
+            },
+            ElementKind::Image(image) => SceneItem::Image {
+                rect: image.rect,
+                width: image.bitmap.width(),
+                height: image.bitmap.height(),
+                pixels: Arc::clone(image.bitmap.pixels()),
+                source: (image.crop != image.bitmap.rect()).then_some(image.crop),
+            },
+            ElementKind::Note(note) => note.scene_item(&style),
+            ElementKind::Connector(connector) => connector.scene_item(&style),
+        }
+    }
+
+    /// Outline connector ends are bound to, before the element transform.
+    pub(crate) fn local_outline(&self) -> BezPath {
+        match &self.kind {
+            ElementKind::Shape(shape) => shape.path(),
+            _ => self.local_bounds().to_path(0.1),
+        }
+    }
+}
+
+/// Ordered collection of the board elements, painted back to front.
+/// The elements are indexed by id and by area, every change goes through the document to keep the indexes up to date.
+/// Elements are never changed in place, they are shared with the tile workers rather than copied.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    elements: Vec<Arc<Element>>,
+    next_id: u64,
+    /// Position of each element in the paint order.
+    positions: HashMap<ElementId, usize>,
+    /// Bounds of the elements, for the queries by area.
+    spatial: SpatialIndex,
+    /// Connectors bound to each element, to reroute them along with the element.
+    connectors: ConnectorIndex,
+    /// Areas of the board changed since last taken, where the cached rasterization of the board is outdated.
+    damage: Vec<Rect>,
+}
+
+/// Connectors bound to each element, by the id of the element.
+#[derive(Clone, Debug, Default)]
+struct ConnectorIndex(HashMap<ElementId, Vec<ElementId>>);
+
+impl ConnectorIndex {
+    fn bound_to(&self, id: ElementId) -> &[ElementId] {
+        self.0.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Record the elements the ends of `element` are bound to, when it is a connector.
+    fn insert(&mut self, element: &Element) {
+        let ElementKind::Connector(connector) = &element.kind else {
+            return;
+        };
+        for bound in connector.bound_elements() {
+            let connectors = self.0.entry(bound).or_default();
+            if !connectors.contains(&element.id) {
+                connectors.push(element.id);
+            }
+        }
+    }
+
+    /// Forget the elements the ends of `element` are bound to, when it is a connector.
+    fn remove(&mut self, element: &Element) {
+        let ElementKind::Connector(connector) = &element.kind else {
+            return;
+        };
+        for bound in connector.bound_elements() {
+            if let Some(connectors) = self.0.get_mut(&bound) {
+                connectors.retain(|id| *id != element.id);
+                if connectors.is_empty() {
+                    self.0.remove(&bound);
+                }
+            }
+        }
+    }
+}
+
+/// Constructor functions
+impl Document {
+    /// Document made of `elements` in paint order, their ids are expected to be unique.
+    pub(crate) fn from_elements(elements: Vec<Element>) -> Document {
+        let next_id = elements
+            .iter()
+            .map(|element| element.id.0 + 1)
+            .max()
+            .unwrap_or_default();
+        let positions = elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| (element.id, index))
+            .collect();
+        let spatial = SpatialIndex::new(&elements);
+        let mut connectors = ConnectorIndex::default();
+        for element in &elements {
+            connectors.insert(element);
+        }
+        Document {
+            elements: elements.into_iter().map(Arc::new).collect(),
+            next_id,
+            positions,
+            spatial,
+            connectors,
+            damage: Vec::new(),
+        }
+    }
+}
+
+/// Reference functions
+impl Document {
+    pub fn elements(&self) -> &[Arc<Element>] {
+        &self.elements
+    }
+
+    pub fn get(&self, id: ElementId) -> Option<&Element> {
+        self.elements.get(self.index_of(id)?).map(AsRef::as_ref)
+    }
+
+    /// Position of the element in the paint order.
+    pub fn index_of(&self, id: ElementId) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Elements whose bounds touch `area`, back to front.
+    pub fn elements_in(&self, area: Rect) -> Vec<&Arc<Element>> {
+        let mut indices: Vec<usize> = self
+            .spatial
+            .query(area)
+            .filter_map(|id| self.index_of(id))
+            .collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|index| &self.elements[index])
+            .collect()
+    }
+
+    /// Connectors with an end bound to the element `id`.
+    pub fn connectors_bound_to(&self, id: ElementId) -> &[ElementId] {
+        self.connectors.bound_to(id)
+    }
+
+    /// Elements hit within `tolerance` of the board `point`, front to back.
+    pub fn elements_at(&self, point: Point, tolerance: f64) -> impl Iterator<Item = &Element> {
+        let area = Rect::from_center_size(point, (2.0 * tolerance, 2.0 * tolerance));
+        self.elements_in(area)
+            .into_iter()
+            .rev()
+            .map(AsRef::as_ref)
+            .filter(move |element| element.hit_test(point, tolerance))
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// The sticky notes, back to front.
+    pub fn notes(&self) -> impl Iterator<Item = (&Element, &Note)> {
+        self.elements
+            .iter()
+            .filter_map(|element| match &element.kind {
+                ElementKind::Note(note) => Some((element.as_ref(), note)),
+                _ => None,
+            })
+    }
+
+    /// Bitmap with the given hash shown by an image of the document, to share its pixels with new images.
+    pub fn bitmap(&self, hash: BitmapHash) -> Option<&Arc<Bitmap>> {
+        self.elements
+            .iter()
+            .find_map(|element| match &element.kind {
+                ElementKind::Image(image) if image.bitmap.hash() == hash => Some(&image.bitmap),
+                _ => None,
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+/// Mutable functions
+impl Document {
+    /// Create an element with a fresh id, the element is not part of the document until inserted.
+    pub fn new_element(&mut self, kind: ElementKind, style: Style) -> Element {
+        let id = ElementId(self.next_id);
+        self.next_id += 1;
+        Element::with_id(id, kind, style)
+    }
+
+    /// Add a new element on top of every other element.
+    pub fn add(&mut self, kind: ElementKind, style: Style) -> ElementId {
+        let element = self.new_element(kind, style);
+        let id = element.id;
+        self.insert(self.elements.len(), element);
+        id
+    }
+
+    /// Insert the element at `index` in the paint order, on top when `index` is past the end.
+    pub fn insert(&mut self, index: usize, element: Element) {
+        let index = index.min(self.elements.len());
+        self.spatial.insert(&element);
+        self.connectors.insert(&element);
+        self.damage.push(element.bounds());
+        self.elements.insert(index, Arc::new(element));
+        self.update_positions(index);
+    }
+
+    /// Replace the element sharing the id of `element`, returns the previous state.
+    pub fn replace(&mut self, element: Element) -> Option<Arc<Element>> {
+        let index = self.index_of(element.id)?;
+        self.spatial.insert(&element);
+        self.damage.push(element.bounds());
+        let previous = std::mem::replace(&mut self.elements[index], Arc::new(element));
+        self.connectors.remove(&previous);
+        self.connectors.insert(&self.elements[index]);
+        self.damage.push(previous.bounds());
+        Some(previous)
+    }
+
+    pub fn remove(&mut self, id: ElementId) -> Option<Arc<Element>> {
+        let index = self.index_of(id)?;
+        let element = self.elements.remove(index);
+        self.positions.remove(&id);
+        self.spatial.remove(id);
+        self.connectors.remove(&element);
+        self.update_positions(index);
+        self.damage.push(element.bounds());
+        Some(element)
+    }
+
+    /// Areas of the board changed since the last call, by the elements added, replaced or removed.
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        std::mem::take(&mut self.damage)
+    }
+}
+
+/// Private functions
+impl Document {
+    /// Record the positions of the elements from `start` on, after they moved in the paint order.
+    fn update_positions(&mut self, start: usize) {
+        for (index, element) in self.elements.iter().enumerate().skip(start) {
+            self.positions.insert(element.id, index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::{Anchor, ArrowHead, ConnectorEnd, Routing};
+
+    fn stroke(points: &[(f64, f64)], widths: &[f64]) -> Stroke {
+        Stroke::new(
+            points.iter().map(|point| Point::from(*point)).collect(),
+            widths.to_vec(),
+            false,
+        )
+    }
+
+    fn rectangle(document: &mut Document) -> ElementId {
+        let shape = Shape {
+            kind: ShapeKind::Rectangle,
+            rect: Rect::new(0.0, 0.0, 10.0, 10.0),
+            corner_radius: 0.0,
+        };
+        document.add(ElementKind::Shape(shape), Style::default())
+    }
+
+    fn connector(document: &Document, start: ElementId, end_id: Option<ElementId>) -> ElementKind {
+        let end = |id: Option<ElementId>| match id.and_then(|id| document.get(id)) {
+            Some(element) => ConnectorEnd::bound(element, Anchor::Top),
+            None => ConnectorEnd::free(Point::ZERO),
+        };
+        ElementKind::Connector(Box::new(Connector {
+            start: end(Some(start)),
+            end: end(end_id),
+            routing: Routing::Straight,
+            start_head: ArrowHead::None,
+            end_head: ArrowHead::None,
+            label: String::new(),
+        }))
+    }
+
+    #[test]
+    fn connectors_are_indexed_by_bound_element() {
+        let mut document = Document::default();
+        let (first, second) = (rectangle(&mut document), rectangle(&mut document));
+        let kind = connector(&document, first, Some(first));
+        let id = document.add(kind, Style::default());
+        assert_eq!(document.connectors_bound_to(first), &[id]);
+        assert!(document.connectors_bound_to(second).is_empty());
+
+        let mut element = document
+            .get(id)
+            .cloned()
+            .expect("connector in the document");
+        element.kind = connector(&document, first, Some(second));
+        document.replace(element);
+        assert_eq!(document.connectors_bound_to(first), &[id]);
+        assert_eq!(document.connectors_bound_to(second), &[id]);
+
+        let mut element = document
+            .get(id)
+            .cloned()
+            .expect("connector in the document");
+        element.kind = connector(&document, second, None);
+        document.replace(element);
+        assert!(document.connectors_bound_to(first).is_empty());
+        assert_eq!(document.connectors_bound_to(second), &[id]);
+
+        let rebuilt = Document::from_elements(
+            document
+                .elements()
+                .iter()
+                .map(|element| Element::clone(element))
+                .collect(),
+        );
+        assert_eq!(rebuilt.connectors_bound_to(second), &[id]);
+
+        document.remove(id);
+        assert!(document.connectors_bound_to(second).is_empty());
+    }
+
+    #[test]
+    fn note_text_shrinks_to_fit() {
+        let mut note = Note::new(Point::ZERO, NoteColor::default());
+        assert_eq!(note.font_size(), NOTE_FONT_SIZE);
+
+        note.content = "a few words ".repeat(20);
+        let font_size = note.font_size();
+        assert!(font_size < NOTE_FONT_SIZE);
+        assert!(font_size >= NOTE_MIN_FONT_SIZE);
+
+        note.content = "a few words ".repeat(1000);
+        assert_eq!(note.font_size(), NOTE_MIN_FONT_SIZE);
+    }
+
+    #[test]
+    fn erase_out_of_reach_keeps_the_stroke() {
+        let line = stroke(&[(0.0, 0.0), (10.0, 0.0)], &[]);
+        assert!(line.erase(Point::new(5.0, 5.0), 2.0).is_none());
+    }
+
+    #[test]
+    fn erase_the_middle_splits_the_stroke() {
+        let line = stroke(&[(0.0, 0.0), (10.0, 0.0)], &[]);
+        let pieces = line
+            .erase(Point::new(5.0, 0.0), 1.0)
+            .expect("the eraser touches the stroke");
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[0].points,
+            vec![Point::new(0.0, 0.0), Point::new(4.0, 0.0)]
+        );
+        assert_eq!(
+            pieces[1].points,
+            vec![Point::new(6.0, 0.0), Point::new(10.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn erase_an_end_shortens_the_stroke() {
+        let line = stroke(&[(0.0, 0.0), (10.0, 0.0)], &[]);
+        let pieces = line
+            .erase(Point::new(10.0, 0.0), 2.0)
+            .expect("the eraser touches the stroke");
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(
+            pieces[0].points,
+            vec![Point::new(0.0, 0.0), Point::new(8.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn erase_everything_leaves_no_piece() {
+        let line = stroke(&[(0.0, 0.0), (1.0, 0.0)], &[]);
+        let pieces = line
+            .erase(Point::new(0.5, 0.0), 5.0)
+            .expect("the eraser touches the stroke");
+        assert!(pieces.is_empty());
+    }
+
+    #[test]
+    fn erase_interpolates_widths() {
+        let line = stroke(&[(0.0, 0.0), (10.0, 0.0)], &[1.0, 3.0]);
+        let pieces = line
+            .erase(Point::new(5.0, 0.0), 1.0)
+            .expect("the eraser touches the stroke");
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].widths.len(), 2);
+        assert!((pieces[0].widths[1] - 1.8).abs() < 1e-9);
+        assert!((pieces[1].widths[0] - 2.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stroke_bounds_cover_the_painted_stroke() {
+        let mut curved = stroke(&[(0.0, 0.0), (10.0, 10.0), (20.0, -5.0), (5.0, 0.0)], &[]);
+        curved.set_curved(true);
+        let variable = stroke(&[(0.0, 0.0), (10.0, 10.0), (20.0, 0.0)], &[1.0, 3.0, 0.5]);
+        for line in [curved, variable] {
+            let bounds = line.bounds(4.0);
+            let painted = line.outline(4.0).bounding_box();
+            assert!(bounds.union(painted).area() - bounds.area() < 1e-6);
+        }
+        assert_eq!(Stroke::default().bounds(4.0), Rect::ZERO);
+    }
+
+    #[test]
+    fn simplified_levels_are_cached() {
+        let points: Vec<(f64, f64)> = (0..200)
+            .map(|index| (f64::from(index), f64::from(index % 2) * 0.1))
+            .collect();
+        let mut line = stroke(&points, &[]);
+        let levels = line.simplified(2.0);
+        assert!(!levels.is_empty());
+        assert!(Arc::ptr_eq(&levels, &line.simplified(2.0)));
+        assert!(Arc::ptr_eq(&levels, &line.clone().simplified(2.0)));
+        assert!(!Arc::ptr_eq(&levels, &line.simplified(4.0)));
+
+        line.push(Point::new(200.0, 0.0), None);
+        assert!(!Arc::ptr_eq(&line.simplified(4.0), &line.simplified(2.0)));
+        assert_eq!(
+            line.simplified(2.0)[0].1.points().last(),
+            line.points().last()
+        );
+    }
+}
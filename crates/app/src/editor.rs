@@ -0,0 +1,236 @@
+//! The document being edited along with its undo history and selection.
+
+use std::sync::Arc;
+
+use renderer::{
+    kurbo::{Affine, Rect},
+    Style,
+};
+
+use crate::{
+    connector::Connector,
+    document::{Document, Element, ElementId, ElementKind},
+    history::{History, Operation},
+    selection::Selection,
+};
+
+/// Every document mutation goes through the editor so it can be undone.
+#[derive(Debug, Default)]
+pub struct Editor {
+    document: Document,
+    history: History,
+    selection: Selection,
+    /// Revision of the history the document was last saved or opened at.
+    saved_revision: u64,
+}
+
+/// Constructor functions
+impl Editor {
+    pub fn new(document: Document) -> Editor {
+        Editor {
+            document,
+            history: History::default(),
+            selection: Selection::default(),
+            saved_revision: 0,
+        }
+    }
+}
+
+/// Reference functions
+impl Editor {
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// Whether the document changed since it was last saved or opened.
+    pub fn is_modified(&self) -> bool {
+        self.history.revision() != self.saved_revision
+    }
+
+    /// The selected elements, in paint order.
+    pub fn selected_elements(&self) -> impl Iterator<Item = &Element> {
+        self.document
+            .elements()
+            .iter()
+            .map(Arc::as_ref)
+            .filter(|element| self.selection.contains(element.id()))
+    }
+}
+
+/// Mutable functions
+impl Editor {
+    /// Apply the operation to the document and record it in the undo history.
+    pub fn apply(&mut self, operation: Operation) {
+        self.history.apply(&mut self.document, operation);
+    }
+
+    /// Add a new element on top of every other element.
+    pub fn add(&mut self, kind: ElementKind, style: Style) -> ElementId {
+        let element = self.document.new_element(kind, style);
+        let id = element.id();
+        self.apply(Operation::add(&self.document, element));
+        id
+    }
+
+    /// Insert a new element at `index` in the paint order, placed on the board with `transform`.
+    pub fn insert(
+        &mut self,
+        index: usize,
+        kind: ElementKind,
+        style: Style,
+        transform: Affine,
+    ) -> ElementId {
+        let mut element = self.document.new_element(kind, style);
+        element.transform = transform;
+        let id = element.id();
+        self.apply(Operation::Insert { index, element });
+        id
+    }
+
+    /// Replace the element sharing the id of `element`, returns `false` when the document does not contain it.
+    /// The connectors bound to the element are rerouted along.
+    pub fn update(&mut self, mut element: Element) -> bool {
+        let id = element.id();
+        // Connectors keep to the elements they are bound to, whatever the update
+        if let ElementKind::Connector(connector) = &mut element.kind {
+            connector.reroute(&self.document, element.transform);
+        }
+        let Some(operation) = Operation::update(&self.document, element) else {
+            return false;
+        };
+        self.grouped(|editor| {
+            editor.apply(operation);
+            editor.reroute(id);
+        });
+        true
+    }
+
+    /// Remove the element, returns `false` when the document does not contain it.
+    /// The connectors bound to the element are released where they are.
+    pub fn remove(&mut self, id: ElementId) -> bool {
+        let Some(operation) = Operation::remove(&self.document, id) else {
+            return false;
+        };
+        self.grouped(|editor| {
+            editor.apply(operation);
+            editor.change_connectors(id, |connector, _, _| connector.unbind(id));
+        });
+        self.selection.retain_existing(&self.document);
+        true
+    }
+
+    /// Remove every selected element as a single undo step, returns `false` when nothing is selected.
+    pub fn remove_selected(&mut self) -> bool {
+        if self.selection.is_empty() {
+            return false;
+        }
+        let ids = self.selection.ids().to_vec();
+        self.begin();
+        for id in ids {
+            self.remove(id);
+        }
+        self.end();
+        true
+    }
+
+    /// Areas of the board changed since the last call, see [`Document::take_damage`].
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        self.document.take_damage()
+    }
+
+    /// Record that the document as it is now was saved.
+    pub fn mark_saved(&mut self) {
+        self.saved_revision = self.history.revision();
+    }
+
+    pub fn selection_mut(&mut self) -> &mut Selection {
+        &mut self.selection
+    }
+
+    /// Start a continuous edit, such as a drag, undone as a single step.
+    pub fn begin(&mut self) {
+        self.history.begin();
+    }
+
+    /// Finish the continuous edit started with [`Editor::begin`].
+    pub fn end(&mut self) {
+        self.history.end();
+    }
+
+    /// Revert the continuous edit started with [`Editor::begin`], it is not recorded in the undo history.
+    pub fn cancel(&mut self) {
+        self.history.cancel(&mut self.document);
+        self.selection.retain_existing(&self.document);
+    }
+
+    /// Revert the last change, returns `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let undone = self.history.undo(&mut self.document);
+        self.selection.retain_existing(&self.document);
+        undone
+    }
+
+    /// Apply again the last undone change, returns `false` when there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let redone = self.history.redo(&mut self.document);
+        self.selection.retain_existing(&self.document);
+        redone
+    }
+}
+
+/// Private functions
+impl Editor {
+    /// Run `edit` as a single undo step, or as part of the continuous edit going on.
+    fn grouped(&mut self, edit: impl FnOnce(&mut Editor)) {
+        if self.history.is_continuous() {
+            edit(self);
+            return;
+        }
+        self.begin();
+        edit(self);
+        self.end();
+    }
+
+    /// Move the ends of the connectors bound to the element `id` to where their anchors are.
+    fn reroute(&mut self, id: ElementId) {
+        self.change_connectors(id, Connector::reroute);
+    }
+
+    /// Apply `change` to every connector bound to the element `id`,
+    /// `change` is given the document and the transform of the connector.
+    fn change_connectors(
+        &mut self,
+        id: ElementId,
+        change: impl Fn(&mut Connector, &Document, Affine),
+    ) {
+        let changed: Vec<Element> = self
+            .document
+            .connectors_bound_to(id)
+            .iter()
+            .filter_map(|connector_id| {
+                let element = self.document.get(*connector_id)?;
+                let ElementKind::Connector(connector) = &element.kind else {
+                    return None;
+                };
+                let mut connector = connector.clone();
+                change(&mut connector, &self.document, element.transform);
+                let mut element = element.clone();
+                element.kind = ElementKind::Connector(connector);
+                Some(element)
+            })
+            .collect();
+        for element in changed {
+            if let Some(operation) = Operation::update(&self.document, element) {
+                self.apply(operation);
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("Not an oxdraw drawing, found format `{0}`")]
+    UnknownFormat(String),
+    #[error("Drawing saved with the newer format version {0}, update oxdraw to open it")]
+    UnsupportedVersion(u32),
+    #[error("Invalid color `{0}`, expected `#rrggbbaa`")]
+    InvalidColor(String),
+    #[error("Element id {0} is used more than once")]
+    DuplicateElementId(u64),
+    #[error("There is nothing to export")]
+    NothingToExport,
+    #[error("Stroke of {points} points has {widths} widths")]
+    InvalidStrokeWidths { points: usize, widths: usize },
+    #[error("Connector {connector} is bound to the missing element {element}")]
+    MissingBoundElement { connector: u64, element: u64 },
+    #[error("Image of {width}x{height} pixels has {len} bytes of pixel data")]
+    InvalidImage {
+        width: usize,
+        height: usize,
+        len: usize,
+    },
+    #[error("Unsupported image format {0}, expected PNG, JPEG or WebP")]
+    UnsupportedImageFormat(String),
+    #[error("Image element references the missing bitmap {0}")]
+    MissingBitmap(String),
+    #[error("Bitmap stored as {key} has the content hash {hash}")]
+    BitmapHashMismatch { key: String, hash: String },
+}
@@ -0,0 +1,54 @@
+//! Export of the board content to other file formats.
+
+use renderer::{scene_to_svg, Scene, SvgOptions};
+
+use crate::document::{Document, ElementId};
+
+/// Extension of the exported SVG files.
+pub const SVG_EXTENSION: &str = "svg";
+
+/// What is exported and how.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// Whether the board background is painted, the export is transparent otherwise.
+    pub background: bool,
+    /// Space kept around the exported elements, in board units.
+    pub padding: f64,
+    /// Elements exported, every element of the document when `None`.
+    pub selection: Option<Vec<ElementId>>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            background: true,
+            padding: 16.0,
+            selection: None,
+        }
+    }
+}
+
+/// Export the document elements as a SVG document fitted around them,
+/// `None` when there is nothing to export.
+pub fn export_svg(document: &Document, options: &ExportOptions) -> Option<String> {
+    let mut scene = Scene::default();
+    for element in document.elements() {
+        let selected = options
+            .selection
+            .as_ref()
+            .map_or(true, |selection| selection.contains(&element.id()));
+        if selected {
+            scene.push(element.scene_item());
+        }
+    }
+
+    let padding = options.padding.max(0.0);
+    let view_box = scene.bounding_box()?.inflate(padding, padding);
+    Some(scene_to_svg(
+        &scene,
+        &SvgOptions {
+            view_box,
+            background: options.background,
+        },
+    ))
+}
@@ -0,0 +1,883 @@
+//! The `.oxd` drawing file format.
+//!
+//! A drawing is stored as a JSON object carrying the format name and version, the file metadata,
+//! the camera of the window it was saved from and every element of the document in paint order.
+//! Image files are stored once, keyed by the hash of their content, however many images show them.
+//! The serialized types below are kept apart from the document model so the model can change
+//! without breaking the files already saved. Files of a newer format version are rejected.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context as _};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use renderer::{
+    kurbo::{Affine, Point, Rect, Vec2},
+    Camera, Color, Font, StrokeStyle, Style,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitmap::Bitmap,
+    connector::{Anchor, ArrowHead, Binding, Connector, ConnectorEnd, Routing},
+    document::{
+        Document, Element, ElementId, ElementKind, Image, Note, NoteColor, Shape, ShapeKind,
+        Stroke, Text,
+    },
+    error::AppError,
+};
+
+/// Extension of the drawing files.
+pub const FILE_EXTENSION: &str = "oxd";
+/// Version of the format written, bumped on every change of the serialized types.
+pub const FORMAT_VERSION: u32 = 1;
+/// Value of the `format` field identifying a drawing file.
+const FORMAT_NAME: &str = "oxdraw";
+
+/// Information about the drawing, stored along with its content.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    /// Version of the application which last saved the drawing.
+    pub app_version: String,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        let now = SystemTime::now();
+        Metadata {
+            created: now,
+            modified: now,
+            app_version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    }
+}
+
+/// Everything read back from a drawing file.
+#[derive(Debug)]
+pub struct DocumentFile {
+    pub document: Document,
+    pub camera: Camera,
+    pub metadata: Metadata,
+}
+
+/// Read the drawing stored at `path`.
+pub fn read(path: &Path) -> anyhow::Result<DocumentFile> {
+    let bytes = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    from_slice(&bytes).with_context(|| format!("Unable to open {}", path.display()))
+}
+
+/// Write the drawing to `path`, replacing the previous content of the file only once fully written.
+pub fn write(
+    path: &Path,
+    document: &Document,
+    camera: &Camera,
+    metadata: &Metadata,
+) -> anyhow::Result<()> {
+    let bytes = to_vec(document, camera, metadata)?;
+    let partial = path.with_extension(format!("{FILE_EXTENSION}.partial"));
+    fs::write(&partial, bytes).with_context(|| format!("Unable to write {}", partial.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// Parse a drawing from the content of a file.
+pub fn from_slice(bytes: &[u8]) -> anyhow::Result<DocumentFile> {
+    let header: FileHeader = serde_json::from_slice(bytes)?;
+    if header.format != FORMAT_NAME {
+        bail!(AppError::UnknownFormat(header.format));
+    }
+    if header.version > FORMAT_VERSION {
+        bail!(AppError::UnsupportedVersion(header.version));
+    }
+    let file: FileData = serde_json::from_slice(bytes)?;
+    file.into_document_file()
+}
+
+/// Serialize a drawing into the content of a file.
+pub fn to_vec(
+    document: &Document,
+    camera: &Camera,
+    metadata: &Metadata,
+) -> anyhow::Result<Vec<u8>> {
+    let file = FileData {
+        format: FORMAT_NAME.to_owned(),
+        version: FORMAT_VERSION,
+        metadata: MetadataData {
+            created: unix_seconds(metadata.created),
+            modified: unix_seconds(metadata.modified),
+            app_version: metadata.app_version.clone(),
+        },
+        camera: CameraData {
+            offset: to_pair(camera.offset().to_point()),
+            zoom: camera.zoom(),
+        },
+        elements: document
+            .elements()
+            .iter()
+            .map(|element| ElementData::from(&**element))
+            .collect(),
+        bitmaps: document
+            .elements()
+            .iter()
+            .filter_map(|element| match &element.kind {
+                ElementKind::Image(image) => Some((
+                    image.bitmap.hash().to_string(),
+                    STANDARD.encode(image.bitmap.encoded()),
+                )),
+                _ => None,
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_vec_pretty(&file)?)
+}
+
+/// Fields identifying the format of a file, read before the rest of the content.
+#[derive(Deserialize)]
+struct FileHeader {
+    format: String,
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileData {
+    format: String,
+    version: u32,
+    metadata: MetadataData,
+    camera: CameraData,
+    elements: Vec<ElementData>,
+    /// Base64 content of the image files shown, by hash of the content.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    bitmaps: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetadataData {
+    /// Seconds since the unix epoch.
+    created: u64,
+    /// Seconds since the unix epoch.
+    modified: u64,
+    app_version: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CameraData {
+    offset: [f64; 2],
+    zoom: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StrokeStyleData {
+    color: String,
+    width: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StyleData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stroke: Option<StrokeStyleData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fill: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ShapeKindData {
+    Rectangle,
+    Ellipse,
+    Diamond,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FontData {
+    SansSerif,
+    Serif,
+    Monospace,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoteColorData {
+    Yellow,
+    Orange,
+    Pink,
+    Purple,
+    Blue,
+    Green,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RoutingData {
+    Straight,
+    Elbow,
+    Curved,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ArrowHeadData {
+    None,
+    Open,
+    Triangle,
+    Circle,
+    Diamond,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AnchorData {
+    Top,
+    Right,
+    Bottom,
+    Left,
+    Edge([f64; 2]),
+}
+
+#[derive(Serialize, Deserialize)]
+struct BindingData {
+    element: u64,
+    anchor: AnchorData,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ConnectorEndData {
+    point: [f64; 2],
+    /// Direction the connector leaves the point along, omitted for ends placed freely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    direction: Option<[f64; 2]>,
+    /// Element the end follows, omitted for ends placed freely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binding: Option<BindingData>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KindData {
+    Stroke {
+        points: Vec<[f64; 2]>,
+        /// Width at each point relative to the style width, omitted for uniform strokes.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        widths: Vec<f64>,
+        /// Whether curves go through the points, omitted for straight segments.
+        #[serde(default, skip_serializing_if = "is_false")]
+        curved: bool,
+    },
+    Shape {
+        shape: ShapeKindData,
+        rect: [f64; 4],
+        /// Radius of the rounded corners of rectangles, omitted for sharp corners.
+        #[serde(default, skip_serializing_if = "is_zero")]
+        corner_radius: f64,
+    },
+    Text {
+        content: String,
+        origin: [f64; 2],
+        font_size: f64,
+        font: FontData,
+        /// Width the lines wrap at, omitted for text only broken at line breaks.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<f64>,
+    },
+    Image {
+        rect: [f64; 4],
+        /// Hash of the image file shown, from the `bitmaps` of the file.
+        bitmap: String,
+        /// Area of the image file shown in pixels, omitted for the whole image.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        crop: Option<[f64; 4]>,
+    },
+    Note {
+        rect: [f64; 4],
+        content: String,
+        color: NoteColorData,
+    },
+    Connector {
+        start: ConnectorEndData,
+        end: ConnectorEndData,
+        routing: RoutingData,
+        start_head: ArrowHeadData,
+        end_head: ArrowHeadData,
+        /// Omitted for connectors without label.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        label: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ElementData {
+    id: u64,
+    #[serde(flatten)]
+    kind: KindData,
+    style: StyleData,
+    /// Affine coefficients placing the element on the board, omitted for the identity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transform: Option<[f64; 6]>,
+}
+
+impl FileData {
+    fn into_document_file(self) -> anyhow::Result<DocumentFile> {
+        let mut bitmaps = HashMap::with_capacity(self.bitmaps.len());
+        for (hash, encoded) in self.bitmaps {
+            let bitmap = Bitmap::decode(STANDARD.decode(encoded)?)?;
+            // The hash identifies the images sharing the bitmap, a wrong one would mix them up
+            if bitmap.hash().to_string() != hash {
+                bail!(AppError::BitmapHashMismatch {
+                    key: hash,
+                    hash: bitmap.hash().to_string(),
+                });
+            }
+            bitmaps.insert(hash, Arc::new(bitmap));
+        }
+        let mut ids = HashSet::new();
+        let mut elements = Vec::with_capacity(self.elements.len());
+        for element in self.elements {
+            if !ids.insert(element.id) {
+                bail!(AppError::DuplicateElementId(element.id));
+            }
+            elements.push(element.into_element(&bitmaps)?);
+        }
+        for element in &elements {
+            let ElementKind::Connector(connector) = &element.kind else {
+                continue;
+            };
+            for binding in [connector.start.binding, connector.end.binding]
+                .iter()
+                .flatten()
+            {
+                if !ids.contains(&binding.element.raw()) {
+                    bail!(AppError::MissingBoundElement {
+                        connector: element.id().raw(),
+                        element: binding.element.raw(),
+                    });
+                }
+            }
+        }
+
+        let [x, y] = self.camera.offset;
+        Ok(DocumentFile {
+            document: Document::from_elements(elements),
+            camera: Camera::new(Vec2::new(x, y), self.camera.zoom),
+            metadata: Metadata {
+                created: from_unix_seconds(self.metadata.created),
+                modified: from_unix_seconds(self.metadata.modified),
+                app_version: self.metadata.app_version,
+            },
+        })
+    }
+}
+
+impl From<&Element> for ElementData {
+    fn from(element: &Element) -> Self {
+        let kind = match &element.kind {
+            ElementKind::Stroke(stroke) => KindData::Stroke {
+                points: stroke.points().iter().copied().map(to_pair).collect(),
+                widths: stroke.widths().to_vec(),
+                curved: stroke.is_curved(),
+            },
+            ElementKind::Shape(shape) => KindData::Shape {
+                shape: match shape.kind {
+                    ShapeKind::Rectangle => ShapeKindData::Rectangle,
+                    ShapeKind::Ellipse => ShapeKindData::Ellipse,
+                    ShapeKind::Diamond => ShapeKindData::Diamond,
+                },
+                rect: to_rect_array(shape.rect),
+                corner_radius: shape.corner_radius,
+            },
+            ElementKind::Text(text) => KindData::Text {
+                content: text.content.clone(),
+                origin: to_pair(text.origin),
+                font_size: text.font_size,
+                font: match text.font {
+                    Font::SansSerif => FontData::SansSerif,
+                    Font::Serif => FontData::Serif,
+                    Font::Monospace => FontData::Monospace,
+                },
+                width: text.width,
+            },
+            ElementKind::Image(image) => KindData::Image {
+                rect: to_rect_array(image.rect),
+                bitmap: image.bitmap.hash().to_string(),
+                crop: (image.crop != image.bitmap.rect()).then(|| to_rect_array(image.crop)),
+            },
+            ElementKind::Note(note) => KindData::Note {
+                rect: to_rect_array(note.rect),
+                content: note.content.clone(),
+                color: match note.color {
+                    NoteColor::Yellow => NoteColorData::Yellow,
+                    NoteColor::Orange => NoteColorData::Orange,
+                    NoteColor::Pink => NoteColorData::Pink,
+                    NoteColor::Purple => NoteColorData::Purple,
+                    NoteColor::Blue => NoteColorData::Blue,
+                    NoteColor::Green => NoteColorData::Green,
+                },
+            },
+            ElementKind::Connector(connector) => KindData::Connector {
+                start: to_end_data(&connector.start),
+                end: to_end_data(&connector.end),
+                routing: match connector.routing {
+                    Routing::Straight => RoutingData::Straight,
+                    Routing::Elbow => RoutingData::Elbow,
+                    Routing::Curved => RoutingData::Curved,
+                },
+                start_head: to_head_data(connector.start_head),
+                end_head: to_head_data(connector.end_head),
+                label: connector.label.clone(),
+            },
+        };
+        let style = StyleData {
+            stroke: element.style.stroke.as_ref().map(|stroke| StrokeStyleData {
+                color: to_hex(stroke.color),
+                width: stroke.width,
+            }),
+            fill: element.style.fill.map(to_hex),
+        };
+        ElementData {
+            id: element.id().raw(),
+            kind,
+            style,
+            transform: (element.transform != Affine::IDENTITY)
+                .then(|| element.transform.as_coeffs()),
+        }
+    }
+}
+
+impl ElementData {
+    /// Element of the document, showing the `bitmaps` of the file by hash.
+    fn into_element(self, bitmaps: &HashMap<String, Arc<Bitmap>>) -> anyhow::Result<Element> {
+        let kind = match self.kind {
+            KindData::Stroke {
+                points,
+                widths,
+                curved,
+            } => {
+                if !widths.is_empty() && widths.len() != points.len() {
+                    bail!(AppError::InvalidStrokeWidths {
+                        points: points.len(),
+                        widths: widths.len(),
+                    });
+                }
+                ElementKind::Stroke(Stroke::new(
+                    points.into_iter().map(from_pair).collect(),
+                    widths,
+                    curved,
+                ))
+            }
+            KindData::Shape {
+                shape,
+                rect,
+                corner_radius,
+            } => ElementKind::Shape(Shape {
+                kind: match shape {
+                    ShapeKindData::Rectangle => ShapeKind::Rectangle,
+                    ShapeKindData::Ellipse => ShapeKind::Ellipse,
+                    ShapeKindData::Diamond => ShapeKind::Diamond,
+                },
+                rect: from_rect_array(rect),
+                corner_radius,
+            }),
+            KindData::Text {
+                content,
+                origin,
+                font_size,
+                font,
+                width,
+            } => ElementKind::Text(Text {
+                content,
+                origin: from_pair(origin),
+                font_size,
+                font: match font {
+                    FontData::SansSerif => Font::SansSerif,
+                    FontData::Serif => Font::Serif,
+                    FontData::Monospace => Font::Monospace,
+                },
+                width,
+            }),
+            KindData::Image { rect, bitmap, crop } => {
+                let Some(bitmap) = bitmaps.get(&bitmap).map(Arc::clone) else {
+                    bail!(AppError::MissingBitmap(bitmap));
+                };
+                ElementKind::Image(Image {
+                    rect: from_rect_array(rect),
+                    crop: crop.map_or_else(|| bitmap.rect(), from_rect_array),
+                    bitmap,
+                })
+            }
+            KindData::Note {
+                rect,
+                content,
+                color,
+            } => ElementKind::Note(Note {
+                rect: from_rect_array(rect),
+                content,
+                color: match color {
+                    NoteColorData::Yellow => NoteColor::Yellow,
+                    NoteColorData::Orange => NoteColor::Orange,
+                    NoteColorData::Pink => NoteColor::Pink,
+                    NoteColorData::Purple => NoteColor::Purple,
+                    NoteColorData::Blue => NoteColor::Blue,
+                    NoteColorData::Green => NoteColor::Green,
+                },
+            }),
+            KindData::Connector {
+                start,
+                end,
+                routing,
+                start_head,
+                end_head,
+                label,
+            } => ElementKind::Connector(Box::new(Connector {
+                start: from_end_data(start),
+                end: from_end_data(end),
+                routing: match routing {
+                    RoutingData::Straight => Routing::Straight,
+                    RoutingData::Elbow => Routing::Elbow,
+                    RoutingData::Curved => Routing::Curved,
+                },
+                start_head: from_head_data(start_head),
+                end_head: from_head_data(end_head),
+                label,
+            })),
+        };
+        let style = Style {
+            stroke: match self.style.stroke {
+                Some(stroke) => Some(StrokeStyle {
+                    color: from_hex(&stroke.color)?,
+                    width: stroke.width,
+                }),
+                None => None,
+            },
+            fill: self.style.fill.as_deref().map(from_hex).transpose()?,
+        };
+        let mut element = Element::with_id(ElementId::new(self.id), kind, style);
+        if let Some(coeffs) = self.transform {
+            element.transform = Affine::new(coeffs);
+        }
+        Ok(element)
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+fn to_pair(point: Point) -> [f64; 2] {
+    [point.x, point.y]
+}
+
+fn from_pair([x, y]: [f64; 2]) -> Point {
+    Point::new(x, y)
+}
+
+fn to_end_data(end: &ConnectorEnd) -> ConnectorEndData {
+    ConnectorEndData {
+        point: to_pair(end.point),
+        direction: (end.direction != Vec2::ZERO).then_some([end.direction.x, end.direction.y]),
+        binding: end.binding.map(|binding| BindingData {
+            element: binding.element.raw(),
+            anchor: match binding.anchor {
+                Anchor::Top => AnchorData::Top,
+                Anchor::Right => AnchorData::Right,
+                Anchor::Bottom => AnchorData::Bottom,
+                Anchor::Left => AnchorData::Left,
+                Anchor::Edge(point) => AnchorData::Edge(to_pair(point)),
+            },
+        }),
+    }
+}
+
+fn from_end_data(end: ConnectorEndData) -> ConnectorEnd {
+    ConnectorEnd {
+        point: from_pair(end.point),
+        direction: end.direction.map_or(Vec2::ZERO, |[x, y]| Vec2::new(x, y)),
+        binding: end.binding.map(|binding| Binding {
+            element: ElementId::new(binding.element),
+            anchor: match binding.anchor {
+                AnchorData::Top => Anchor::Top,
+                AnchorData::Right => Anchor::Right,
+                AnchorData::Bottom => Anchor::Bottom,
+                AnchorData::Left => Anchor::Left,
+                AnchorData::Edge(point) => Anchor::Edge(from_pair(point)),
+            },
+        }),
+    }
+}
+
+fn to_head_data(head: ArrowHead) -> ArrowHeadData {
+    match head {
+        ArrowHead::None => ArrowHeadData::None,
+        ArrowHead::Open => ArrowHeadData::Open,
+        ArrowHead::Triangle => ArrowHeadData::Triangle,
+        ArrowHead::Circle => ArrowHeadData::Circle,
+        ArrowHead::Diamond => ArrowHeadData::Diamond,
+    }
+}
+
+fn from_head_data(head: ArrowHeadData) -> ArrowHead {
+    match head {
+        ArrowHeadData::None => ArrowHead::None,
+        ArrowHeadData::Open => ArrowHead::Open,
+        ArrowHeadData::Triangle => ArrowHead::Triangle,
+        ArrowHeadData::Circle => ArrowHead::Circle,
+        ArrowHeadData::Diamond => ArrowHead::Diamond,
+    }
+}
+
+fn to_rect_array(rect: Rect) -> [f64; 4] {
+    [rect.x0, rect.y0, rect.x1, rect.y1]
+}
+
+fn from_rect_array([x0, y0, x1, y1]: [f64; 4]) -> Rect {
+    Rect::new(x0, y0, x1, y1)
+}
+
+fn to_hex(color: Color) -> String {
+    format!("#{:08x}", color.as_rgba_u32())
+}
+
+fn from_hex(hex: &str) -> anyhow::Result<Color> {
+    let rgba = hex
+        .strip_prefix('#')
+        .filter(|digits| digits.len() == 8 && digits.bytes().all(|b| b.is_ascii_hexdigit()))
+        .and_then(|digits| u32::from_str_radix(digits, 16).ok());
+    match rgba {
+        Some(rgba) => Ok(Color::from_rgba32_u32(rgba)),
+        None => bail!(AppError::InvalidColor(hex.to_owned())),
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn from_unix_seconds(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::connector::ConnectorEnd;
+
+    /// Document with an element of every kind, the connector bound to the shape.
+    fn document() -> Document {
+        let mut document = Document::default();
+        let stroke_style = Style {
+            stroke: Some(StrokeStyle {
+                color: Color::rgba8(0x12, 0x34, 0x56, 0x78),
+                width: 3.0,
+            }),
+            fill: None,
+        };
+        document.add(
+            ElementKind::Stroke(Stroke::new(
+                vec![Point::new(0.0, 0.0), Point::new(10.0, 5.0)],
+                vec![0.5, 1.5],
+                true,
+            )),
+            stroke_style.clone(),
+        );
+        let shape = document.add(
+            ElementKind::Shape(Shape {
+                kind: ShapeKind::Rectangle,
+                rect: Rect::new(20.0, 20.0, 60.0, 50.0),
+                corner_radius: 4.0,
+            }),
+            Style {
+                fill: Some(Color::WHITE),
+                ..stroke_style.clone()
+            },
+        );
+        document.add(
+            ElementKind::Text(Text {
+                content: "Hello\nworld".to_owned(),
+                origin: Point::new(-5.0, 7.0),
+                font_size: 18.0,
+                font: Font::Serif,
+                width: Some(120.0),
+            }),
+            Style::default(),
+        );
+        document.add(
+            ElementKind::Note(Note::new(Point::new(100.0, 100.0), NoteColor::Blue)),
+            Style::default(),
+        );
+        let bitmap =
+            Bitmap::from_rgba(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 128]).expect("valid pixels");
+        let mut image = Image::new(Arc::new(bitmap), Point::new(-50.0, -50.0));
+        image.crop = Rect::new(1.0, 0.0, 2.0, 1.0);
+        document.add(ElementKind::Image(image), Style::default());
+        let bound = document.get(shape).cloned().expect("shape in the document");
+        document.add(
+            ElementKind::Connector(Box::new(Connector {
+                start: ConnectorEnd::free(Point::new(-20.0, 0.0)),
+                end: ConnectorEnd::bound(&bound, Anchor::Left),
+                routing: Routing::Elbow,
+                start_head: ArrowHead::Circle,
+                end_head: ArrowHead::Triangle,
+                label: "label".to_owned(),
+            })),
+            stroke_style,
+        );
+        document
+    }
+
+    fn saved() -> Vec<u8> {
+        let mut document = document();
+        let mut moved = Element::clone(&document.elements()[0]);
+        moved.transform = Affine::translate((3.0, 4.0)) * Affine::rotate(0.5);
+        document.replace(moved);
+        let camera = Camera::new(Vec2::new(-12.0, 34.0), 1.5);
+        to_vec(&document, &camera, &Metadata::default()).expect("serializable document")
+    }
+
+    /// Content of a saved file changed by `change`.
+    fn edited(change: impl FnOnce(&mut Value)) -> Vec<u8> {
+        let mut value: Value = serde_json::from_slice(&saved()).expect("valid json");
+        change(&mut value);
+        serde_json::to_vec(&value).expect("serializable json")
+    }
+
+    fn app_error(bytes: &[u8]) -> AppError {
+        let err = from_slice(bytes).expect_err("invalid file");
+        err.downcast::<AppError>().expect("app error")
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = saved();
+        let file = from_slice(&bytes).expect("valid file");
+        assert_eq!(file.document.len(), 6);
+        assert_eq!(file.camera.zoom(), 1.5);
+        assert_eq!(file.camera.offset(), Vec2::new(-12.0, 34.0));
+        // Writing the drawing read back gives the same file
+        let metadata = file.metadata;
+        let again = to_vec(&file.document, &file.camera, &metadata).expect("serializable");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&again).expect("valid json"),
+            serde_json::from_slice::<Value>(&bytes).expect("valid json"),
+        );
+    }
+
+    #[test]
+    fn images_share_their_bitmap() {
+        let mut document = document();
+        let image = document
+            .elements()
+            .iter()
+            .find(|element| matches!(element.kind, ElementKind::Image(_)))
+            .map(|element| Element::clone(element))
+            .expect("image in the document");
+        document.add(image.kind, image.style);
+        let bytes = to_vec(&document, &Camera::default(), &Metadata::default()).expect("valid");
+        let value: Value = serde_json::from_slice(&bytes).expect("valid json");
+        assert_eq!(
+            value["bitmaps"].as_object().map(|bitmaps| bitmaps.len()),
+            Some(1)
+        );
+
+        let file = from_slice(&bytes).expect("valid file");
+        let bitmaps: Vec<_> = file
+            .document
+            .elements()
+            .iter()
+            .filter_map(|element| match &element.kind {
+                ElementKind::Image(image) => Some(Arc::clone(&image.bitmap)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bitmaps.len(), 2);
+        assert!(Arc::ptr_eq(&bitmaps[0], &bitmaps[1]));
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let bytes = edited(|value| value["format"] = json!("other"));
+        assert!(matches!(app_error(&bytes), AppError::UnknownFormat(format) if format == "other"));
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let bytes = edited(|value| value["version"] = json!(FORMAT_VERSION + 1));
+        assert!(matches!(
+            app_error(&bytes),
+            AppError::UnsupportedVersion(version) if version == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let bytes = edited(|value| value["elements"][1]["id"] = value["elements"][0]["id"].clone());
+        assert!(matches!(app_error(&bytes), AppError::DuplicateElementId(0)));
+    }
+
+    #[test]
+    fn rejects_mismatched_stroke_widths() {
+        let bytes = edited(|value| value["elements"][0]["widths"] = json!([1.0]));
+        assert!(matches!(
+            app_error(&bytes),
+            AppError::InvalidStrokeWidths {
+                points: 2,
+                widths: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let bytes = edited(|value| value["elements"][1]["style"]["fill"] = json!("#fff"));
+        assert!(matches!(app_error(&bytes), AppError::InvalidColor(color) if color == "#fff"));
+    }
+
+    #[test]
+    fn rejects_connectors_bound_to_missing_elements() {
+        let bytes = edited(|value| {
+            if let Some(elements) = value["elements"].as_array_mut() {
+                elements.remove(1);
+            }
+        });
+        assert!(matches!(
+            app_error(&bytes),
+            AppError::MissingBoundElement {
+                connector: 5,
+                element: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_bitmaps() {
+        let bytes = edited(|value| value["bitmaps"] = json!({}));
+        assert!(matches!(app_error(&bytes), AppError::MissingBitmap(_)));
+    }
+
+    #[test]
+    fn rejects_bitmaps_not_matching_their_hash() {
+        let other = Bitmap::from_rgba(1, 1, vec![0, 255, 0, 255]).expect("valid pixels");
+        let bytes = edited(|value| {
+            if let Some(bitmaps) = value["bitmaps"].as_object_mut() {
+                for encoded in bitmaps.values_mut() {
+                    *encoded = json!(STANDARD.encode(other.encoded()));
+                }
+            }
+        });
+        assert!(matches!(
+            app_error(&bytes),
+            AppError::BitmapHashMismatch { hash, .. } if hash == other.hash().to_string()
+        ));
+    }
+}
@@ -0,0 +1,410 @@
+//! Reversible document operations and the undo/redo stacks recording them.
+
+use std::collections::VecDeque;
+
+use crate::document::{Document, Element, ElementId};
+
+/// Undo steps kept around, the oldest steps are forgotten first.
+const MAX_STEPS: usize = 500;
+
+/// A reversible document mutation.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    /// Insert `element` at `index` in the paint order.
+    Insert { index: usize, element: Element },
+    /// Remove `element`, found at `index` in the paint order.
+    Remove { index: usize, element: Element },
+    /// Replace the element state `before` with `after`, both share the same id.
+    Update { before: Element, after: Element },
+}
+
+/// Constructor functions
+impl Operation {
+    /// Insert `element` on top of every other element of the document.
+    pub fn add(document: &Document, element: Element) -> Operation {
+        Operation::Insert {
+            index: document.len(),
+            element,
+        }
+    }
+
+    /// Remove the element, `None` when the document does not contain it.
+    pub fn remove(document: &Document, id: ElementId) -> Option<Operation> {
+        let index = document.index_of(id)?;
+        let element = Element::clone(document.elements().get(index)?);
+        Some(Operation::Remove { index, element })
+    }
+
+    /// Replace the element sharing the id of `after`, `None` when the document does not contain it.
+    pub fn update(document: &Document, after: Element) -> Option<Operation> {
+        let before = document.get(after.id())?.clone();
+        Some(Operation::Update { before, after })
+    }
+}
+
+/// Reference functions
+impl Operation {
+    /// The operation reverting this one.
+    pub fn inverse(&self) -> Operation {
+        match self {
+            Operation::Insert { index, element } => Operation::Remove {
+                index: *index,
+                element: element.clone(),
+            },
+            Operation::Remove { index, element } => Operation::Insert {
+                index: *index,
+                element: element.clone(),
+            },
+            Operation::Update { before, after } => Operation::Update {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+
+    pub fn apply(&self, document: &mut Document) {
+        match self {
+            Operation::Insert { index, element } => document.insert(*index, element.clone()),
+            Operation::Remove { element, .. } => {
+                if document.remove(element.id()).is_none() {
+                    log::warn!("Unable to remove missing element {:?}", element.id());
+                }
+            }
+            Operation::Update { after, .. } => {
+                if document.replace(after.clone()).is_none() {
+                    log::warn!("Unable to update missing element {:?}", after.id());
+                }
+            }
+        }
+    }
+}
+
+/// Operations undone and redone together.
+#[derive(Debug)]
+struct Step {
+    /// Revision of the document once the step is applied, unique within the history.
+    revision: u64,
+    operations: Vec<Operation>,
+}
+
+impl Step {
+    /// Record the operation, updates of an element already updated in this step are merged.
+    fn push(&mut self, operation: Operation) {
+        if let Operation::Update { after, .. } = &operation {
+            let merged = self
+                .operations
+                .iter_mut()
+                .rev()
+                .find_map(|recorded| match recorded {
+                    Operation::Update {
+                        after: previous, ..
+                    } if previous.id() == after.id() => Some(previous),
+                    _ => None,
+                });
+            if let Some(previous) = merged {
+                *previous = after.clone();
+                return;
+            }
+        }
+        self.operations.push(operation);
+    }
+}
+
+/// Undo and redo stacks of the document operations.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest step first, forgotten first once the history is full.
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    /// Whether a continuous edit is going on, its operations are all recorded in the last undo step.
+    continuous: bool,
+    /// Revision given to the last step created.
+    last_revision: u64,
+    /// Revision of the document once every step kept is undone, that of the last step forgotten.
+    base_revision: u64,
+}
+
+/// Reference functions
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Whether a continuous edit started with [`History::begin`] is going on.
+    pub fn is_continuous(&self) -> bool {
+        self.continuous
+    }
+
+    /// Identifies the state of the document reached through the history, `0` before any change.
+    /// Undoing back to a state, or redoing forward to it, gives back its revision.
+    pub fn revision(&self) -> u64 {
+        self.undo
+            .back()
+            .map_or(self.base_revision, |step| step.revision)
+    }
+}
+
+/// Mutable functions
+impl History {
+    /// Apply the operation to the document and record it as a new undo step,
+    /// or as part of the current step while a continuous edit is going on.
+    pub fn apply(&mut self, document: &mut Document, operation: Operation) {
+        operation.apply(document);
+        self.redo.clear();
+
+        match self.undo.back_mut() {
+            Some(step) if self.continuous => step.push(operation),
+            _ => {
+                let mut step = self.new_step();
+                step.push(operation);
+                self.push_undo(step);
+            }
+        }
+    }
+
+    /// Start a continuous edit, such as a drag. Every operation until [`History::end`] is undone as a single step,
+    /// repeated updates of an element only keep the state before the first one and after the last one.
+    pub fn begin(&mut self) {
+        self.end();
+        let step = self.new_step();
+        self.push_undo(step);
+        self.continuous = true;
+    }
+
+    /// Finish the continuous edit started with [`History::begin`].
+    pub fn end(&mut self) {
+        if !self.continuous {
+            return;
+        }
+        self.continuous = false;
+        if self
+            .undo
+            .back()
+            .map_or(false, |step| step.operations.is_empty())
+        {
+            self.undo.pop_back();
+        }
+    }
+
+    /// Revert the continuous edit started with [`History::begin`] without recording it.
+    pub fn cancel(&mut self, document: &mut Document) {
+        if !self.continuous {
+            return;
+        }
+        self.continuous = false;
+        if let Some(step) = self.undo.pop_back() {
+            for operation in step.operations.iter().rev() {
+                operation.inverse().apply(document);
+            }
+        }
+    }
+
+    /// Revert the last undo step, returns `false` when there is nothing to undo.
+    pub fn undo(&mut self, document: &mut Document) -> bool {
+        self.end();
+        let Some(step) = self.undo.pop_back() else {
+            return false;
+        };
+        for operation in step.operations.iter().rev() {
+            operation.inverse().apply(document);
+        }
+        self.redo.push(step);
+        true
+    }
+
+    /// Apply again the last undone step, returns `false` when there is nothing to redo.
+    pub fn redo(&mut self, document: &mut Document) -> bool {
+        self.end();
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        for operation in &step.operations {
+            operation.apply(document);
+        }
+        self.undo.push_back(step);
+        true
+    }
+
+    fn new_step(&mut self) -> Step {
+        self.last_revision += 1;
+        Step {
+            revision: self.last_revision,
+            operations: Vec::new(),
+        }
+    }
+
+    fn push_undo(&mut self, step: Step) {
+        if self.undo.len() >= MAX_STEPS {
+            if let Some(forgotten) = self.undo.pop_front() {
+                self.base_revision = forgotten.revision;
+            }
+        }
+        self.undo.push_back(step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use renderer::{kurbo::Point, Style};
+
+    use super::*;
+    use crate::document::{ElementKind, Stroke};
+
+    fn stroke(points: &[(f64, f64)]) -> ElementKind {
+        ElementKind::Stroke(Stroke::new(
+            points.iter().map(|point| Point::from(*point)).collect(),
+            Vec::new(),
+            false,
+        ))
+    }
+
+    fn points(document: &Document, id: ElementId) -> Vec<Point> {
+        match document.get(id).map(|element| &element.kind) {
+            Some(ElementKind::Stroke(stroke)) => stroke.points().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Add a stroke at `(0, 0)`, as a new undo step unless a continuous edit is going on.
+    fn add(history: &mut History, document: &mut Document) -> ElementId {
+        let element = document.new_element(stroke(&[(0.0, 0.0)]), Style::default());
+        let id = element.id();
+        history.apply(document, Operation::add(document, element));
+        id
+    }
+
+    /// Move the stroke to `(x, 0)`.
+    fn move_to(history: &mut History, document: &mut Document, id: ElementId, x: f64) {
+        let mut element = document.get(id).cloned().expect("element in the document");
+        element.kind = stroke(&[(x, 0.0)]);
+        let operation = Operation::update(document, element).expect("element in the document");
+        history.apply(document, operation);
+    }
+
+    #[test]
+    fn undo_redo_single_operations() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        let id = add(&mut history, &mut document);
+        move_to(&mut history, &mut document, id, 1.0);
+
+        assert!(history.undo(&mut document));
+        assert_eq!(points(&document, id), [Point::new(0.0, 0.0)]);
+        assert!(history.undo(&mut document));
+        assert!(document.is_empty());
+        assert!(!history.undo(&mut document));
+
+        assert!(history.redo(&mut document));
+        assert!(history.redo(&mut document));
+        assert_eq!(points(&document, id), [Point::new(1.0, 0.0)]);
+        assert!(!history.redo(&mut document));
+    }
+
+    #[test]
+    fn continuous_updates_merge_into_one_step() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        let id = add(&mut history, &mut document);
+        history.begin();
+        for x in 1..=5 {
+            move_to(&mut history, &mut document, id, f64::from(x));
+        }
+        history.end();
+        assert_eq!(points(&document, id), [Point::new(5.0, 0.0)]);
+
+        assert!(history.undo(&mut document));
+        assert_eq!(points(&document, id), [Point::new(0.0, 0.0)]);
+        assert!(history.redo(&mut document));
+        assert_eq!(points(&document, id), [Point::new(5.0, 0.0)]);
+    }
+
+    #[test]
+    fn empty_continuous_edit_is_not_recorded() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        add(&mut history, &mut document);
+        history.begin();
+        history.end();
+        assert!(history.undo(&mut document));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn cancel_reverts_without_recording() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        let id = add(&mut history, &mut document);
+        history.begin();
+        move_to(&mut history, &mut document, id, 1.0);
+        let added = add(&mut history, &mut document);
+        history.cancel(&mut document);
+
+        assert!(!history.is_continuous());
+        assert_eq!(points(&document, id), [Point::new(0.0, 0.0)]);
+        assert!(document.get(added).is_none());
+        // Only the first addition is left to undo
+        assert!(history.undo(&mut document));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn new_operation_clears_redo() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        let id = add(&mut history, &mut document);
+        move_to(&mut history, &mut document, id, 1.0);
+        assert!(history.undo(&mut document));
+        assert!(history.can_redo());
+        move_to(&mut history, &mut document, id, 2.0);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn revision_follows_undo_and_redo() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        assert_eq!(history.revision(), 0);
+        let id = add(&mut history, &mut document);
+        let saved = history.revision();
+        move_to(&mut history, &mut document, id, 1.0);
+        assert_ne!(history.revision(), saved);
+        assert!(history.undo(&mut document));
+        assert_eq!(history.revision(), saved);
+        assert!(history.redo(&mut document));
+        assert_ne!(history.revision(), saved);
+
+        // A different change made after undoing gets a revision of its own
+        let changed = history.revision();
+        assert!(history.undo(&mut document));
+        move_to(&mut history, &mut document, id, 2.0);
+        assert_ne!(history.revision(), saved);
+        assert_ne!(history.revision(), changed);
+    }
+
+    #[test]
+    fn oldest_steps_are_forgotten() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        for _ in 0..MAX_STEPS + 10 {
+            add(&mut history, &mut document);
+        }
+        let mut undone = 0;
+        while history.undo(&mut document) {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_STEPS);
+        assert_eq!(document.len(), 10);
+    }
+
+    #[test]
+    fn undoing_every_step_kept_is_not_the_first_revision() {
+        let (mut history, mut document) = (History::default(), Document::default());
+        let saved = history.revision();
+        for _ in 0..MAX_STEPS + 1 {
+            add(&mut history, &mut document);
+        }
+        while history.undo(&mut document) {}
+        assert_eq!(document.len(), 1);
+        assert_ne!(history.revision(), saved);
+        while history.redo(&mut document) {}
+        assert_eq!(document.len(), MAX_STEPS + 1);
+        assert_eq!(history.revision(), (MAX_STEPS + 1) as u64);
+    }
+}
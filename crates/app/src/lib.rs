@@ -1,11 +1,738 @@
 //! The main app display functionality.
 
-#[derive(Default)]
-pub struct App {}
+mod bitmap;
+mod connector;
+mod document;
+mod editor;
+mod error;
+pub mod export;
+pub mod file;
+mod history;
+mod recent;
+mod selection;
+mod smoothing;
+mod spatial;
+mod tool;
 
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{bail, Context as _};
+use arboard::Clipboard;
+use log::{debug, error, info, warn};
+use renderer::{
+    kurbo::{Point, Rect},
+    Board, Camera, Frame, Renderer, Scene, Style, TileContent,
+};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageLevel};
+use window::{Command, Handler, KeyCode, KeyboardModifiers, TextInputEvent, TouchInputEvent};
+
+use crate::{
+    export::{ExportOptions, SVG_EXTENSION},
+    file::{DocumentFile, Metadata, FILE_EXTENSION},
+};
+
+pub use bitmap::{Bitmap, BitmapHash, IMAGE_EXTENSIONS};
+pub use connector::{Anchor, ArrowHead, Binding, Connector, ConnectorEnd, Routing};
+pub use document::{
+    Document, Element, ElementId, ElementKind, Image, Note, NoteColor, Shape, ShapeKind, Stroke,
+    Text, TextBox,
+};
+pub use editor::Editor;
+pub use error::AppError;
+pub use history::{History, Operation};
+pub use recent::RecentFiles;
+pub use selection::Selection;
+pub use smoothing::SmoothingOptions;
+pub use tool::{
+    ConnectorTool, CropTool, EraserMode, EraserTool, PenTool, SelectionTool, ShapeTool, TextTool,
+    Tool, ToolContext,
+};
+
+/// Longest time between the clicks of a double click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+/// Farthest distance in screen pixels between the clicks of a double click.
+const DOUBLE_CLICK_DISTANCE: f64 = 4.0;
+/// Screen pixels around an area of the board within which elements are painted too,
+/// as the estimated extent of text may fall short of the text painted.
+const CULL_MARGIN: f64 = 64.0;
+/// Largest part of the view an image placed on the board covers, larger images are scaled down.
+const MAX_IMAGE_VIEW_FRACTION: f64 = 0.8;
+
+pub struct App {
+    editor: Editor,
+    /// The tool receiving the pointer input.
+    tool: Box<dyn Tool>,
+    /// File the document is saved to, `None` until saved for the first time.
+    path: Option<PathBuf>,
+    metadata: Metadata,
+    /// Files opened or saved, for the Open Recent entry.
+    recent: RecentFiles,
+    /// Time, board position and count of the last clicks in quick succession.
+    last_click: Option<(Instant, Point, u32)>,
+    /// Board area seen in the window last rendered, pasted and inserted images are placed in its middle.
+    view: Rect,
+    /// Whether the document was replaced since the last render, no cached tile of the board shows it.
+    board_replaced: bool,
+    /// Element the tool painted on top of the board tiles at the last render.
+    restyled: Option<ElementId>,
+}
+
+/// Document the board tiles are rasterized from, without the element the tool paints over them.
+struct DocumentBoard<'a> {
+    document: &'a Document,
+    restyled: Option<ElementId>,
+    /// Board units around a tile within which elements are painted into it too.
+    margin: f64,
+}
+
+/// Elements touching a tile, shared with the document, back to front.
+struct TileElements(Vec<Arc<Element>>);
+
+impl Board for DocumentBoard<'_> {
+    fn tile(&self, area: Rect) -> Box<dyn TileContent> {
+        let elements = self
+            .document
+            .elements_in(area.inflate(self.margin, self.margin))
+            .into_iter()
+            .filter(|element| Some(element.id()) != self.restyled)
+            .map(Arc::clone)
+            .collect();
+        Box::new(TileElements(elements))
+    }
+}
+
+impl TileContent for TileElements {
+    fn scene(&self) -> Scene {
+        let mut scene = Scene::default();
+        for element in &self.0 {
+            scene.push(element.scene_item());
+        }
+        scene
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App::new(Document::default())
+    }
+}
+
+/// Constructor functions
+impl App {
+    pub fn new(document: Document) -> App {
+        App {
+            editor: Editor::new(document),
+            tool: Box::<PenTool>::default(),
+            path: None,
+            metadata: Metadata::default(),
+            recent: RecentFiles::default(),
+            last_click: None,
+            view: Rect::ZERO,
+            board_replaced: true,
+            restyled: None,
+        }
+    }
+}
+
+/// Reference functions
 impl App {
-    pub fn render(&self, _content: ()) -> anyhow::Result<()> {
-        // TODO(Quadri): Render the provided content using the graphic context
+    pub fn document(&self) -> &Document {
+        self.editor.document()
+    }
+
+    /// File the document is saved to, `None` until saved for the first time.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whether the document changed since it was last saved or opened.
+    pub fn is_modified(&self) -> bool {
+        self.editor.is_modified()
+    }
+
+    /// Paint the elements of the document seen in the frame, back to front.
+    /// The interaction of the active tool is painted on top.
+    pub fn render(
+        &self,
+        document: &Document,
+        renderer: &mut Renderer,
+        frame: &Frame,
+    ) -> anyhow::Result<()> {
+        let restyled = self.tool.restyled();
+        // The tiles show the document as is, the element the tool restyles is painted over them
+        let board = DocumentBoard {
+            document,
+            restyled,
+            margin: CULL_MARGIN / frame.camera.zoom(),
+        };
+        let mut overlay = Scene::default();
+        if let Some(element) = restyled.and_then(|id| document.get(id)) {
+            overlay.push(self.tool.scene_item(element));
+        }
+        self.tool
+            .paint(&self.editor, &mut overlay, frame.camera.zoom());
+        renderer.render_tiled(frame, &board, &overlay)
+    }
+}
+
+/// Mutable functions
+impl App {
+    /// Remember the files opened and saved in `recent`, such as a list persisted across sessions.
+    pub fn set_recent_files(&mut self, recent: RecentFiles) {
+        self.recent = recent;
+    }
+
+    /// Make `tool` receive the pointer input from now on, the selection is cleared.
+    /// The interaction of the previous tool is completed first.
+    pub fn set_tool(&mut self, tool: Box<dyn Tool>) {
+        self.tool.finish(&mut self.editor);
+        self.tool = tool;
+        self.editor.selection_mut().clear();
+    }
+
+    /// Replace the document with the drawing stored at `path`, the camera is moved to the saved viewpoint.
+    pub fn open(&mut self, path: &Path, camera: &mut Camera) -> anyhow::Result<()> {
+        let DocumentFile {
+            document,
+            camera: saved_camera,
+            metadata,
+        } = file::read(path)?;
+        self.editor = Editor::new(document);
+        self.board_replaced = true;
+        self.metadata = metadata;
+        *camera = saved_camera;
+        self.set_path(path);
+        info!("Opened {}", path.display());
+        Ok(())
+    }
+
+    /// Write the document along with the camera viewpoint to `path`, later saves go to the same file.
+    pub fn save(&mut self, path: &Path, camera: &Camera) -> anyhow::Result<()> {
+        let mut metadata = self.metadata.clone();
+        metadata.modified = SystemTime::now();
+        metadata.app_version = env!("CARGO_PKG_VERSION").to_owned();
+        file::write(path, self.editor.document(), camera, &metadata)?;
+        self.metadata = metadata;
+        self.editor.mark_saved();
+        self.set_path(path);
+        info!("Saved {}", path.display());
         Ok(())
     }
+
+    /// Place `bitmap` on the board centered on `center`, at its natural size or scaled down to fit the view.
+    /// The image is selected with the selection tool to be moved or resized right away.
+    pub fn insert_image(&mut self, bitmap: Bitmap, center: Point) -> ElementId {
+        // Images of the same file share their pixels
+        let bitmap = match self.document().bitmap(bitmap.hash()) {
+            Some(existing) => Arc::clone(existing),
+            None => Arc::new(bitmap),
+        };
+        let mut image = Image::new(bitmap, center);
+        let size = image.rect.size();
+        let scale = (self.view.width() * MAX_IMAGE_VIEW_FRACTION / size.width)
+            .min(self.view.height() * MAX_IMAGE_VIEW_FRACTION / size.height);
+        if scale < 1.0 && scale > 0.0 {
+            image.rect = Rect::from_center_size(center, size * scale);
+        }
+        self.set_tool(Box::<SelectionTool>::default());
+        let id = self.editor.add(ElementKind::Image(image), Style::default());
+        self.editor.selection_mut().set([id]);
+        info!("Inserted a {}x{} image", size.width, size.height);
+        id
+    }
+
+    /// Write the elements picked by `options` to `path` as a SVG document.
+    pub fn export_svg(&self, path: &Path, options: &ExportOptions) -> anyhow::Result<()> {
+        let Some(svg) = export::export_svg(self.editor.document(), options) else {
+            bail!(AppError::NothingToExport);
+        };
+        fs::write(path, svg).with_context(|| format!("Unable to write {}", path.display()))?;
+        info!("Exported {}", path.display());
+        Ok(())
+    }
+
+    /// Run the command, returns `true` when the board needs to be redrawn.
+    fn run_command(&mut self, command: Command, camera: &mut Camera) -> bool {
+        match command {
+            Command::Undo => {
+                let finished = self.tool.finish(&mut self.editor);
+                self.editor.undo() || finished
+            }
+            Command::Redo => {
+                let finished = self.tool.finish(&mut self.editor);
+                self.editor.redo() || finished
+            }
+            Command::Delete => self.editor.remove_selected(),
+            Command::SelectAll => {
+                self.set_tool(Box::<SelectionTool>::default());
+                let ids: Vec<_> = self
+                    .document()
+                    .elements()
+                    .iter()
+                    .map(|element| element.id())
+                    .collect();
+                self.editor.selection_mut().set(ids);
+                true
+            }
+            Command::SelectTool => {
+                self.set_tool(Box::<SelectionTool>::default());
+                true
+            }
+            Command::PenTool => {
+                self.set_tool(Box::<PenTool>::default());
+                true
+            }
+            Command::EraserTool => {
+                self.set_tool(Box::new(EraserTool::new(EraserMode::Whole)));
+                true
+            }
+            Command::PreciseEraserTool => {
+                self.set_tool(Box::new(EraserTool::new(EraserMode::Partial)));
+                true
+            }
+            Command::RectangleTool => {
+                self.set_tool(Box::new(ShapeTool::new(ShapeKind::Rectangle)));
+                true
+            }
+            Command::EllipseTool => {
+                self.set_tool(Box::new(ShapeTool::new(ShapeKind::Ellipse)));
+                true
+            }
+            Command::DiamondTool => {
+                self.set_tool(Box::new(ShapeTool::new(ShapeKind::Diamond)));
+                true
+            }
+            Command::LineTool => {
+                self.set_tool(Box::new(ConnectorTool::new(
+                    Routing::Straight,
+                    ArrowHead::None,
+                    ArrowHead::None,
+                )));
+                true
+            }
+            Command::ArrowTool => {
+                self.set_tool(Box::new(ConnectorTool::new(
+                    Routing::Straight,
+                    ArrowHead::None,
+                    ArrowHead::Open,
+                )));
+                true
+            }
+            Command::ElbowArrowTool => {
+                self.set_tool(Box::new(ConnectorTool::new(
+                    Routing::Elbow,
+                    ArrowHead::None,
+                    ArrowHead::Triangle,
+                )));
+                true
+            }
+            Command::CurvedArrowTool => {
+                self.set_tool(Box::new(ConnectorTool::new(
+                    Routing::Curved,
+                    ArrowHead::None,
+                    ArrowHead::Triangle,
+                )));
+                true
+            }
+            Command::StraightRouting => {
+                self.change_connectors(|connector| connector.routing = Routing::Straight)
+            }
+            Command::ElbowRouting => {
+                self.change_connectors(|connector| connector.routing = Routing::Elbow)
+            }
+            Command::CurvedRouting => {
+                self.change_connectors(|connector| connector.routing = Routing::Curved)
+            }
+            Command::NextStartArrowHead => self.change_connectors(|connector| {
+                connector.start_head = connector.start_head.next();
+            }),
+            Command::NextEndArrowHead => self.change_connectors(|connector| {
+                connector.end_head = connector.end_head.next();
+            }),
+            Command::TextTool => {
+                self.set_tool(Box::<TextTool>::default());
+                true
+            }
+            Command::Paste => {
+                let finished = self.tool.finish(&mut self.editor);
+                match self.paste_image() {
+                    Ok(pasted) => pasted || finished,
+                    Err(err) => {
+                        debug!("Nothing to paste: {err:#}");
+                        finished
+                    }
+                }
+            }
+            Command::InsertImage => {
+                let finished = self.tool.finish(&mut self.editor);
+                let path = FileDialog::new()
+                    .add_filter("Image", &IMAGE_EXTENSIONS)
+                    .pick_file();
+                let Some(path) = path else {
+                    return finished;
+                };
+                let center = self.view.center();
+                match Bitmap::read(&path) {
+                    Ok(bitmap) => {
+                        self.insert_image(bitmap, center);
+                        true
+                    }
+                    Err(err) => {
+                        show_error(&format!("Unable to run command {command:?}"), &err);
+                        finished
+                    }
+                }
+            }
+            Command::New
+            | Command::Open
+            | Command::OpenRecent
+            | Command::Save
+            | Command::SaveAs
+            | Command::ExportSvg => {
+                let finished = self.tool.finish(&mut self.editor);
+                match self.file_command(command, camera) {
+                    Ok(changed) => changed || finished,
+                    Err(err) => {
+                        show_error(&format!("Unable to run command {command:?}"), &err);
+                        finished
+                    }
+                }
+            }
+            _ => {
+                debug!("Command {} is not handled by the app", command.name());
+                false
+            }
+        }
+    }
+
+    /// Place the image of the clipboard in the middle of the view, returns `false` when the clipboard has no image.
+    fn paste_image(&mut self) -> anyhow::Result<bool> {
+        let image = match Clipboard::new()?.get_image() {
+            Ok(image) => image,
+            Err(arboard::Error::ContentNotAvailable) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        let bitmap = Bitmap::from_rgba(image.width, image.height, image.bytes.into_owned())?;
+        let center = self.view.center();
+        self.insert_image(bitmap, center);
+        Ok(true)
+    }
+
+    /// Apply `change` to the selected connectors as a single undo step,
+    /// returns `false` when no connector is selected.
+    fn change_connectors(&mut self, change: impl Fn(&mut Connector)) -> bool {
+        let changed: Vec<Element> = self
+            .editor
+            .selected_elements()
+            .filter_map(|element| {
+                let ElementKind::Connector(connector) = &element.kind else {
+                    return None;
+                };
+                let mut connector = connector.clone();
+                change(&mut connector);
+                let mut element = element.clone();
+                element.kind = ElementKind::Connector(connector);
+                Some(element)
+            })
+            .collect();
+        if changed.is_empty() {
+            return false;
+        }
+        self.editor.begin();
+        for element in changed {
+            self.editor.update(element);
+        }
+        self.editor.end();
+        true
+    }
+
+    /// Hand the input over to the tool the active one asks for, returns `true` when the tool changed.
+    fn switch_tool(&mut self) -> bool {
+        match self.tool.next_tool() {
+            Some(tool) => {
+                self.set_tool(tool);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of clicks in quick succession at the same place the touch of `event` began with,
+    /// on a board seen at `zoom`.
+    fn count_clicks(&mut self, event: &TouchInputEvent, zoom: f64) -> u32 {
+        let TouchInputEvent::Begin { position, .. } = event else {
+            return self.last_click.map_or(0, |(_, _, clicks)| clicks);
+        };
+        let now = Instant::now();
+        let position = Point::new(f64::from(position.x), f64::from(position.y));
+        let clicks = match self.last_click {
+            Some((time, last, clicks))
+                if now.duration_since(time) <= DOUBLE_CLICK_TIME
+                    && last.distance(position) * zoom <= DOUBLE_CLICK_DISTANCE =>
+            {
+                clicks + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, position, clicks));
+        clicks
+    }
+
+    /// Start over with an empty document which is not saved to any file yet.
+    fn new_document(&mut self, camera: &mut Camera) {
+        self.editor = Editor::default();
+        self.board_replaced = true;
+        self.metadata = Metadata::default();
+        self.path = None;
+        *camera = Camera::default();
+    }
+
+    /// Make `path` the file the document is saved to, and the most recent file.
+    fn set_path(&mut self, path: &Path) {
+        if let Err(err) = self.recent.add(path) {
+            warn!("{err:#}");
+        }
+        self.path = Some(path.to_owned());
+    }
+
+    /// Whether the changes made since the document was saved may be lost, the user is asked when there are some.
+    fn confirm_discard(&self) -> bool {
+        if !self.editor.is_modified() {
+            return true;
+        }
+        let name = self
+            .path
+            .as_deref()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or("Untitled");
+        MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title("Oxdraw")
+            .set_description(&format!("{name} has unsaved changes. Discard them?"))
+            .set_buttons(MessageButtons::YesNo)
+            .show()
+    }
+
+    /// Run a file command, returns `true` when the document was replaced.
+    fn file_command(&mut self, command: Command, camera: &mut Camera) -> anyhow::Result<bool> {
+        match command {
+            Command::New => {
+                if !self.confirm_discard() {
+                    return Ok(false);
+                }
+                self.new_document(camera);
+                Ok(true)
+            }
+            Command::Open => {
+                if !self.confirm_discard() {
+                    return Ok(false);
+                }
+                match file_dialog().pick_file() {
+                    Some(path) => self.open(&path, camera).map(|_| true),
+                    None => Ok(false),
+                }
+            }
+            Command::OpenRecent => {
+                let Some(path) = self.recent.latest_other(self.path.as_deref()) else {
+                    return Ok(false);
+                };
+                let path = path.to_owned();
+                if !self.confirm_discard() {
+                    return Ok(false);
+                }
+                if let Err(err) = self.open(&path, camera) {
+                    // The file was moved or deleted since
+                    if let Err(err) = self.recent.remove(&path) {
+                        warn!("{err:#}");
+                    }
+                    return Err(err);
+                }
+                Ok(true)
+            }
+            Command::Save => {
+                if let Some(path) = self.path.clone().or_else(|| save_dialog(None)) {
+                    self.save(&path, camera)?;
+                }
+                Ok(false)
+            }
+            Command::SaveAs => {
+                if let Some(path) = save_dialog(self.path.as_deref()) {
+                    self.save(&path, camera)?;
+                }
+                Ok(false)
+            }
+            Command::ExportSvg => {
+                let name = self
+                    .path
+                    .as_deref()
+                    .and_then(Path::file_stem)
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("Untitled");
+                let path = FileDialog::new()
+                    .add_filter("SVG image", &[SVG_EXTENSION])
+                    .set_file_name(&format!("{name}.{SVG_EXTENSION}"))
+                    .save_file();
+                if let Some(path) = path {
+                    let selection = self.editor.selection();
+                    let options = ExportOptions {
+                        selection: (!selection.is_empty()).then(|| selection.ids().to_vec()),
+                        ..ExportOptions::default()
+                    };
+                    self.export_svg(&path, &options)?;
+                }
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl Handler for App {
+    fn touch(
+        &mut self,
+        event: &TouchInputEvent,
+        modifiers: &KeyboardModifiers,
+        camera: &Camera,
+    ) -> bool {
+        let ctx = ToolContext {
+            modifiers: *modifiers,
+            zoom: camera.zoom(),
+            clicks: self.count_clicks(event, camera.zoom()),
+        };
+        let redraw = self.tool.touch(&mut self.editor, event, &ctx);
+        self.switch_tool() || redraw
+    }
+
+    fn dragging(&self) -> bool {
+        self.tool.dragging()
+    }
+
+    fn key(&mut self, key: &KeyCode, modifiers: &KeyboardModifiers) -> bool {
+        let used = self.tool.key(&mut self.editor, key, modifiers);
+        self.switch_tool() || used
+    }
+
+    fn text_input(&mut self, event: &TextInputEvent) -> bool {
+        let redraw = self.tool.text_input(&mut self.editor, event);
+        self.switch_tool() || redraw
+    }
+
+    fn text_cursor(&self) -> Option<Rect> {
+        self.tool.text_cursor(&self.editor)
+    }
+
+    fn command(&mut self, command: Command, camera: &mut Camera) -> bool {
+        let redraw = self.run_command(command, camera);
+        self.switch_tool() || redraw
+    }
+
+    fn close_requested(&mut self) -> bool {
+        self.tool.finish(&mut self.editor);
+        self.confirm_discard()
+    }
+
+    fn dropped_file(&mut self, path: &Path, position: Point) -> bool {
+        let is_image = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map_or(false, |extension| {
+                IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+            });
+        if !is_image {
+            debug!("Ignored the file dropped {}", path.display());
+            return false;
+        }
+        let finished = self.tool.finish(&mut self.editor);
+        match Bitmap::read(path) {
+            Ok(bitmap) => {
+                self.insert_image(bitmap, position);
+                true
+            }
+            Err(err) => {
+                show_error("Unable to insert the file dropped", &err);
+                finished
+            }
+        }
+    }
+
+    fn render(&mut self, renderer: &mut Renderer, frame: &Frame) -> anyhow::Result<()> {
+        self.view = visible_area(frame);
+        if std::mem::take(&mut self.board_replaced) {
+            renderer.invalidate_all();
+        }
+        let mut damage = self.editor.take_damage();
+        let restyled = self.tool.restyled();
+        if restyled != self.restyled {
+            // The elements restyled leave the tiles or come back into them
+            let document = self.editor.document();
+            damage.extend(
+                [self.restyled, restyled]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|id| document.get(id))
+                    .map(Element::bounds),
+            );
+            self.restyled = restyled;
+        }
+        let margin = CULL_MARGIN / frame.camera.zoom();
+        for area in damage {
+            renderer.invalidate(area.inflate(margin, margin));
+        }
+        App::render(self, self.editor.document(), renderer, frame)
+    }
+}
+
+/// Board area seen in the frame.
+fn visible_area(frame: &Frame) -> Rect {
+    let size = Point::new(
+        f64::from(frame.width) / frame.scale_factor,
+        f64::from(frame.height) / frame.scale_factor,
+    );
+    Rect::from_points(
+        frame.camera.to_world(Point::ZERO),
+        frame.camera.to_world(size),
+    )
+}
+
+/// Log the error and show it in a message box.
+fn show_error(context: &str, err: &anyhow::Error) {
+    error!("{context}: {err:#}");
+    MessageDialog::new()
+        .set_level(MessageLevel::Error)
+        .set_title("Oxdraw")
+        .set_description(&format!("{err:#}"))
+        .show();
+}
+
+fn file_dialog() -> FileDialog {
+    FileDialog::new().add_filter("Oxdraw drawing", &[FILE_EXTENSION])
+}
+
+/// Ask where to save the document, suggesting `current` when already saved.
+fn save_dialog(current: Option<&Path>) -> Option<PathBuf> {
+    let mut dialog = file_dialog();
+    match current {
+        Some(current) => {
+            if let Some(directory) = current.parent() {
+                dialog = dialog.set_directory(directory);
+            }
+            if let Some(name) = current.file_name().and_then(|name| name.to_str()) {
+                dialog = dialog.set_file_name(name);
+            }
+        }
+        None => dialog = dialog.set_file_name(&format!("Untitled.{FILE_EXTENSION}")),
+    }
+    let path = dialog.save_file()?;
+    // Some platforms do not append the extension of the selected filter
+    if path.extension().is_some() {
+        Some(path)
+    } else {
+        Some(path.with_extension(FILE_EXTENSION))
+    }
 }
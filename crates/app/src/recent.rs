@@ -0,0 +1,156 @@
+//! Files recently opened or saved, remembered across sessions for the Open Recent entry.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+
+/// Most recently opened or saved files remembered.
+const MAX_RECENT_FILES: usize = 10;
+/// Directory of the app within the user configuration directory.
+const CONFIG_DIR_NAME: &str = "oxdraw";
+/// File listing the recent files, one path per line, most recent first.
+const RECENT_FILES_NAME: &str = "recent_files";
+
+/// Files opened or saved, most recent first, written back to `storage` whenever the list changes.
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+    /// File the list is persisted to, the list only lasts for the session when `None`.
+    storage: Option<PathBuf>,
+}
+
+/// Constructor functions
+impl RecentFiles {
+    /// List persisted to `storage`, starting with the paths it holds. The list starts empty when it cannot be read.
+    pub fn load(storage: PathBuf) -> RecentFiles {
+        let paths = match fs::read_to_string(&storage) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .take(MAX_RECENT_FILES)
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                log::warn!(
+                    "Unable to read the recent files {}: {err}",
+                    storage.display()
+                );
+                Vec::new()
+            }
+        };
+        RecentFiles {
+            paths,
+            storage: Some(storage),
+        }
+    }
+
+    /// List persisted in the user configuration directory, for the session only when there is none.
+    pub fn from_config_dir() -> RecentFiles {
+        let Some(dir) = config_dir() else {
+            log::warn!("No configuration directory, the recent files are forgotten on exit");
+            return RecentFiles::default();
+        };
+        RecentFiles::load(dir.join(CONFIG_DIR_NAME).join(RECENT_FILES_NAME))
+    }
+}
+
+/// Reference functions
+impl RecentFiles {
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Most recent file other than `current`.
+    pub fn latest_other(&self, current: Option<&Path>) -> Option<&Path> {
+        self.paths
+            .iter()
+            .map(PathBuf::as_path)
+            .find(|path| Some(*path) != current)
+    }
+}
+
+/// Mutable functions
+impl RecentFiles {
+    /// Move `path` to the top of the list, the oldest files are forgotten past the most remembered.
+    pub fn add(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.paths.retain(|recent| recent != path);
+        self.paths.insert(0, path.to_owned());
+        self.paths.truncate(MAX_RECENT_FILES);
+        self.write()
+    }
+
+    /// Forget `path`, such as a file which could not be opened anymore.
+    pub fn remove(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.paths.retain(|recent| recent != path);
+        self.write()
+    }
+}
+
+/// Private functions
+impl RecentFiles {
+    fn write(&self) -> anyhow::Result<()> {
+        let Some(storage) = &self.storage else {
+            return Ok(());
+        };
+        if let Some(dir) = storage.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        let mut content = String::new();
+        for path in &self.paths {
+            content.push_str(&path.to_string_lossy());
+            content.push('\n');
+        }
+        fs::write(storage, content)
+            .with_context(|| format!("Unable to write the recent files {}", storage.display()))
+    }
+}
+
+/// User configuration directory of the platform.
+fn config_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_files_are_persisted_most_recent_first() {
+        let dir = env::temp_dir().join(format!("oxdraw_recent_{}", std::process::id()));
+        let storage = dir.join(RECENT_FILES_NAME);
+        let mut recent = RecentFiles::load(storage.clone());
+        assert!(recent.paths().is_empty());
+        for index in 0..MAX_RECENT_FILES + 2 {
+            recent
+                .add(Path::new(&format!("drawing_{index}.oxd")))
+                .expect("the list is written");
+        }
+        recent
+            .add(Path::new("drawing_5.oxd"))
+            .expect("the list is written");
+
+        let loaded = RecentFiles::load(storage);
+        assert_eq!(loaded.paths().len(), MAX_RECENT_FILES);
+        assert_eq!(loaded.paths()[0], Path::new("drawing_5.oxd"));
+        assert_eq!(loaded.paths()[1], Path::new("drawing_11.oxd"));
+        assert_eq!(
+            loaded.latest_other(Some(Path::new("drawing_5.oxd"))),
+            Some(Path::new("drawing_11.oxd"))
+        );
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,77 @@
+//! Elements picked by the user, the target of the edit commands.
+
+use std::collections::HashSet;
+
+use crate::document::{Document, ElementId};
+
+/// Selected elements, in the order they were selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    ids: Vec<ElementId>,
+    /// The same elements as `ids`, to tell whether an element is selected without going through them.
+    members: HashSet<ElementId>,
+}
+
+/// Reference functions
+impl Selection {
+    pub fn ids(&self) -> &[ElementId] {
+        &self.ids
+    }
+
+    pub fn contains(&self, id: ElementId) -> bool {
+        self.members.contains(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+/// Mutable functions
+impl Selection {
+    /// Select only the given elements.
+    pub fn set(&mut self, ids: impl IntoIterator<Item = ElementId>) {
+        self.clear();
+        self.extend(ids);
+    }
+
+    /// Add the elements which are not selected yet.
+    pub fn extend(&mut self, ids: impl IntoIterator<Item = ElementId>) {
+        for id in ids {
+            if self.members.insert(id) {
+                self.ids.push(id);
+            }
+        }
+    }
+
+    /// Select the element if it is not selected, unselect it otherwise.
+    pub fn toggle(&mut self, id: ElementId) {
+        if self.members.remove(&id) {
+            self.ids.retain(|selected| *selected != id);
+        } else {
+            self.members.insert(id);
+            self.ids.push(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.members.clear();
+    }
+
+    /// Forget the elements the document does not contain anymore.
+    pub fn retain_existing(&mut self, document: &Document) {
+        let members = &mut self.members;
+        self.ids.retain(|id| {
+            let exists = document.get(*id).is_some();
+            if !exists {
+                members.remove(id);
+            }
+            exists
+        });
+    }
+}
@@ -0,0 +1,192 @@
+//! Clean up of freehand input: smoothing while drawing, simplification and curve fitting once drawn.
+
+use renderer::kurbo::{Line, ParamCurveNearest as _, Point};
+
+use crate::document::Stroke;
+
+/// Strongest streamline, stronger values would keep the stroke from following the pointer.
+const MAX_STREAMLINE: f64 = 0.95;
+
+/// How freehand strokes are cleaned up.
+#[derive(Clone, Copy, Debug)]
+pub struct SmoothingOptions {
+    /// Low-pass smoothing of the pointer while drawing, from `0.0` for the raw input to `1.0` for the
+    /// smoothest, laggiest line.
+    pub streamline: f64,
+    /// Distance in screen pixels the simplified stroke may deviate from the drawn one, `0.0` keeps every point.
+    pub tolerance: f64,
+    /// Whether the simplified stroke goes through its points with smooth curves instead of straight segments.
+    pub fit_curves: bool,
+}
+
+impl Default for SmoothingOptions {
+    fn default() -> Self {
+        SmoothingOptions {
+            streamline: 0.5,
+            tolerance: 0.75,
+            fit_curves: true,
+        }
+    }
+}
+
+/// Reference functions
+impl SmoothingOptions {
+    /// Position the stroke moves to from its `previous` point when the pointer is at `input`.
+    pub fn streamline(&self, previous: Point, input: Point) -> Point {
+        let strength = self.streamline.clamp(0.0, MAX_STREAMLINE);
+        previous.lerp(input, 1.0 - strength)
+    }
+
+    /// The stroke with fewer points and curves fitted through them, once drawn.
+    /// `zoom` is the zoom the stroke was drawn at and `width` the style width it is painted with.
+    pub fn finish(&self, stroke: &Stroke, zoom: f64, width: f64) -> Stroke {
+        let mut finished = simplify(stroke, self.tolerance.max(0.0) / zoom, width);
+        finished.set_curved(self.fit_curves && finished.points().len() > 2);
+        finished
+    }
+}
+
+/// Ramer-Douglas-Peucker simplification, drops the points within `tolerance` of the line joining
+/// the points kept around them. Width changes of variable strokes count as deviations as well.
+pub fn simplify(stroke: &Stroke, tolerance: f64, width: f64) -> Stroke {
+    let len = stroke.points().len();
+    if len < 3 || tolerance <= 0.0 {
+        return stroke.clone();
+    }
+
+    let mut keep = vec![false; len];
+    keep[0] = true;
+    keep[len - 1] = true;
+    let mut ranges = vec![(0, len - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let line = Line::new(stroke.points()[first], stroke.points()[last]);
+        let (first_width, last_width) = (stroke.width(first), stroke.width(last));
+        let farthest = (first + 1..last)
+            .map(|index| {
+                let nearest = line.nearest(stroke.points()[index], 0.0);
+                let expected = first_width + (last_width - first_width) * nearest.t;
+                let width_deviation = (stroke.width(index) - expected).abs() * width / 2.0;
+                (index, nearest.distance_sq.sqrt().max(width_deviation))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((index, deviation)) = farthest {
+            if deviation > tolerance {
+                keep[index] = true;
+                ranges.push((first, index));
+                ranges.push((index, last));
+            }
+        }
+    }
+
+    let kept = |index: &usize| keep[*index];
+    let widths = if stroke.is_variable() {
+        (0..len)
+            .filter(kept)
+            .map(|index| stroke.width(index))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Stroke::new(
+        (0..len)
+            .filter(kept)
+            .map(|index| stroke.points()[index])
+            .collect(),
+        widths,
+        stroke.is_curved(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(points: &[(f64, f64)], widths: &[f64]) -> Stroke {
+        Stroke::new(
+            points.iter().map(|point| Point::from(*point)).collect(),
+            widths.to_vec(),
+            false,
+        )
+    }
+
+    #[test]
+    fn streamline_moves_part_way_to_the_input() {
+        let options = SmoothingOptions {
+            streamline: 0.5,
+            ..SmoothingOptions::default()
+        };
+        let point = options.streamline(Point::ZERO, Point::new(10.0, 0.0));
+        assert_eq!(point, Point::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn streamline_always_follows_the_input() {
+        let options = SmoothingOptions {
+            streamline: 1.0,
+            ..SmoothingOptions::default()
+        };
+        let point = options.streamline(Point::ZERO, Point::new(10.0, 0.0));
+        assert!(point.x > 0.0);
+    }
+
+    #[test]
+    fn simplify_drops_points_within_tolerance() {
+        let drawn = stroke(&[(0.0, 0.0), (5.0, 0.1), (10.0, -0.1), (20.0, 0.0)], &[]);
+        let simplified = simplify(&drawn, 0.5, 1.0);
+        assert_eq!(
+            simplified.points(),
+            vec![Point::new(0.0, 0.0), Point::new(20.0, 0.0)]
+        );
+        assert!(simplified.widths().is_empty());
+    }
+
+    #[test]
+    fn simplify_keeps_corners() {
+        let drawn = stroke(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (10.0, 10.0)], &[]);
+        let simplified = simplify(&drawn, 0.5, 1.0);
+        assert_eq!(
+            simplified.points(),
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 0.0),
+                Point::new(10.0, 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn simplify_keeps_width_changes() {
+        let drawn = stroke(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)], &[1.0, 3.0, 1.0]);
+        let simplified = simplify(&drawn, 0.5, 2.0);
+        assert_eq!(simplified.points().len(), 3);
+        assert_eq!(simplified.widths(), vec![1.0, 3.0, 1.0]);
+
+        let steady = stroke(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)], &[1.0, 1.0, 1.0]);
+        assert_eq!(simplify(&steady, 0.5, 2.0).widths(), vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn zero_tolerance_keeps_every_point() {
+        let drawn = stroke(&[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0)], &[]);
+        assert_eq!(simplify(&drawn, 0.0, 1.0).points(), drawn.points());
+    }
+
+    #[test]
+    fn finish_scales_tolerance_with_zoom_and_fits_curves() {
+        let drawn = stroke(&[(0.0, 0.0), (5.0, 0.4), (10.0, 0.0), (10.0, 10.0)], &[]);
+        let options = SmoothingOptions {
+            tolerance: 1.0,
+            ..SmoothingOptions::default()
+        };
+        // Zoomed out the deviation is below a screen pixel
+        let zoomed_out = options.finish(&drawn, 0.5, 1.0);
+        assert_eq!(zoomed_out.points().len(), 3);
+        assert!(zoomed_out.is_curved());
+        // Zoomed in the same deviation spans several screen pixels
+        let zoomed_in = options.finish(&drawn, 4.0, 1.0);
+        assert_eq!(zoomed_in.points().len(), 4);
+
+        let straight = stroke(&[(0.0, 0.0), (10.0, 0.0)], &[]);
+        assert!(!options.finish(&straight, 1.0, 1.0).is_curved());
+    }
+}
@@ -0,0 +1,79 @@
+//! Spatial index of the element bounds, so that picking and culling do not scan every element.
+
+use std::collections::HashMap;
+
+use renderer::kurbo::Rect;
+use rstar::{
+    primitives::{GeomWithData, Rectangle},
+    RTree, AABB,
+};
+
+use crate::document::{Element, ElementId};
+
+type Entry = GeomWithData<Rectangle<[f64; 2]>, ElementId>;
+
+/// R-tree of the bounds of the elements, answering which elements may be within an area.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SpatialIndex {
+    tree: RTree<Entry>,
+    /// Bounds each element is indexed with, to find its entry back in the tree.
+    bounds: HashMap<ElementId, Rect>,
+}
+
+/// Constructor functions
+impl SpatialIndex {
+    pub(crate) fn new(elements: &[Element]) -> SpatialIndex {
+        let bounds: HashMap<_, _> = elements
+            .iter()
+            .map(|element| (element.id(), element.bounds()))
+            .collect();
+        let entries = bounds
+            .iter()
+            .map(|(id, bounds)| entry(*id, *bounds))
+            .collect();
+        SpatialIndex {
+            tree: RTree::bulk_load(entries),
+            bounds,
+        }
+    }
+}
+
+/// Reference functions
+impl SpatialIndex {
+    /// Elements whose bounds touch `area`, in no particular order.
+    pub(crate) fn query(&self, area: Rect) -> impl Iterator<Item = ElementId> + '_ {
+        let envelope = AABB::from_corners([area.x0, area.y0], [area.x1, area.y1]);
+        self.tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|entry| entry.data)
+    }
+}
+
+/// Mutable functions
+impl SpatialIndex {
+    /// Index the element, replacing the bounds it was indexed with before.
+    pub(crate) fn insert(&mut self, element: &Element) {
+        let bounds = element.bounds();
+        match self.bounds.insert(element.id(), bounds) {
+            Some(previous) if previous == bounds => return,
+            Some(previous) => {
+                self.tree.remove(&entry(element.id(), previous));
+            }
+            None => {}
+        }
+        self.tree.insert(entry(element.id(), bounds));
+    }
+
+    pub(crate) fn remove(&mut self, id: ElementId) {
+        if let Some(bounds) = self.bounds.remove(&id) {
+            self.tree.remove(&entry(id, bounds));
+        }
+    }
+}
+
+fn entry(id: ElementId, bounds: Rect) -> Entry {
+    GeomWithData::new(
+        Rectangle::from_corners([bounds.x0, bounds.y0], [bounds.x1, bounds.y1]),
+        id,
+    )
+}
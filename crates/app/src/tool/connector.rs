@@ -0,0 +1,174 @@
+use std::f64::consts::FRAC_PI_4;
+
+use renderer::{
+    kurbo::{Circle, Point, Rect, Shape as _, Vec2},
+    Color, Scene, SceneItem, StrokeStyle, Style,
+};
+use window::TouchInputEvent;
+
+use crate::{
+    connector::{Anchor, ArrowHead, Connector, ConnectorEnd, Routing},
+    document::ElementKind,
+    editor::Editor,
+    tool::{touch_position, Tool, ToolContext},
+};
+
+/// Distance in screen pixels within which an end binds to the element under it.
+const BIND_TOLERANCE: f64 = 8.0;
+/// Distance in screen pixels within which an end snaps to the middle of a side.
+const ANCHOR_SNAP: f64 = 12.0;
+/// Radius of the side anchors shown on the elements bound, in screen pixels.
+const ANCHOR_RADIUS: f64 = 4.0;
+/// Size in screen pixels under which a drag is taken for a click, no connector is added.
+const MIN_DRAG: f64 = 2.0;
+/// Angle free connectors snap to when constrained.
+const LINE_SNAP: f64 = FRAC_PI_4;
+const ANCHOR_COLOR: Color = Color::rgb8(0x1E, 0x88, 0xE5);
+
+/// Draws lines and arrows from where the pointer goes down to where it goes up.
+/// Ends dropped on a shape, a text, an image or a sticky note are bound to it and follow it when it moves.
+/// Shift snaps the angle of free connectors to multiples of 45°.
+pub struct ConnectorTool {
+    pub routing: Routing,
+    pub start_head: ArrowHead,
+    pub end_head: ArrowHead,
+    /// Style of the line, arrow heads are filled with the stroke color.
+    pub style: Style,
+    /// Touch id and connector of the drag going on.
+    drawing: Option<(usize, Connector)>,
+}
+
+/// Constructor functions
+impl ConnectorTool {
+    pub fn new(routing: Routing, start_head: ArrowHead, end_head: ArrowHead) -> ConnectorTool {
+        ConnectorTool {
+            routing,
+            start_head,
+            end_head,
+            style: Style {
+                stroke: Some(StrokeStyle {
+                    color: Color::BLACK,
+                    width: 2.0,
+                }),
+                fill: None,
+            },
+            drawing: None,
+        }
+    }
+}
+
+impl Tool for ConnectorTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        match (event, &mut self.drawing) {
+            (TouchInputEvent::Begin { id, .. }, None) => {
+                let start = end_at(editor, position, None, ctx);
+                let connector = Connector {
+                    start,
+                    end: ConnectorEnd::free(start.point),
+                    routing: self.routing,
+                    start_head: self.start_head,
+                    end_head: self.end_head,
+                    label: String::new(),
+                };
+                self.drawing = Some((*id, connector));
+                true
+            }
+            (TouchInputEvent::Move { id, .. }, Some((pointer, connector))) if id == pointer => {
+                connector.end = end_at(editor, position, Some(&connector.start), ctx);
+                true
+            }
+            (TouchInputEvent::End { id, .. }, Some((pointer, connector))) if id == pointer => {
+                connector.end = end_at(editor, position, Some(&connector.start), ctx);
+                let Some((_, connector)) = self.drawing.take() else {
+                    return false;
+                };
+                if connector.start.point.distance(connector.end.point) * ctx.zoom >= MIN_DRAG {
+                    editor.add(
+                        ElementKind::Connector(Box::new(connector)),
+                        self.style.clone(),
+                    );
+                }
+                true
+            }
+            (TouchInputEvent::Cancel { id, .. }, Some((pointer, _))) if id == pointer => {
+                self.drawing = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn paint(&self, editor: &Editor, scene: &mut Scene, zoom: f64) {
+        let Some((_, connector)) = &self.drawing else {
+            return;
+        };
+        scene.push(connector.scene_item(&self.style));
+        // The sides of the elements bound show where the ends snap to
+        let anchor_style = Style {
+            stroke: Some(StrokeStyle {
+                color: ANCHOR_COLOR,
+                width: 1.0 / zoom,
+            }),
+            fill: Some(Color::WHITE),
+        };
+        for binding in [connector.start.binding, connector.end.binding]
+            .iter()
+            .flatten()
+        {
+            let Some(element) = editor.document().get(binding.element) else {
+                continue;
+            };
+            for side in Anchor::SIDES {
+                let (point, _) = side.resolve(element);
+                scene.push(SceneItem::Path {
+                    path: Circle::new(point, ANCHOR_RADIUS / zoom).to_path(0.1),
+                    style: anchor_style.clone(),
+                });
+            }
+        }
+    }
+
+    fn dragging(&self) -> bool {
+        self.drawing.is_some()
+    }
+}
+
+/// Connector end at the board `position`, bound to the topmost bindable element there.
+/// Ends following `start` stay free rather than binding to the element `start` is bound to.
+fn end_at(
+    editor: &Editor,
+    position: Point,
+    start: Option<&ConnectorEnd>,
+    ctx: &ToolContext,
+) -> ConnectorEnd {
+    let tolerance = BIND_TOLERANCE / ctx.zoom;
+    let excluded = start
+        .and_then(|start| start.binding)
+        .map(|binding| binding.element);
+    let area = Rect::from_center_size(position, (2.0 * tolerance, 2.0 * tolerance));
+    let target = editor
+        .document()
+        .elements_in(area)
+        .into_iter()
+        .rev()
+        .find(|element| {
+            element.is_bindable()
+                && Some(element.id()) != excluded
+                && (element.bounds().contains(position) || element.hit_test(position, tolerance))
+        });
+    if let Some(element) = target {
+        return ConnectorEnd::bound(
+            element,
+            Anchor::nearest(element, position, ANCHOR_SNAP / ctx.zoom),
+        );
+    }
+    match start {
+        Some(start) if ctx.modifiers.shift => {
+            let delta = position - start.point;
+            let angle = (delta.atan2() / LINE_SNAP).round() * LINE_SNAP;
+            ConnectorEnd::free(start.point + Vec2::from_angle(angle) * delta.hypot())
+        }
+        _ => ConnectorEnd::free(position),
+    }
+}
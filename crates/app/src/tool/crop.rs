@@ -0,0 +1,257 @@
+use renderer::{
+    kurbo::{Point, Rect, Shape as _},
+    Color, Scene, SceneItem, StrokeStyle, Style,
+};
+use window::{KeyCode, KeyboardModifiers, TouchInputEvent};
+
+use crate::{
+    document::{Element, ElementId, ElementKind, Image},
+    editor::Editor,
+    tool::{select::Handle, touch_position, Tool, ToolContext},
+};
+
+/// Distance in screen pixels within which a handle is hit.
+const HIT_TOLERANCE: f64 = 4.0;
+/// Side of the crop handles in screen pixels.
+const HANDLE_SIZE: f64 = 8.0;
+/// Smallest side of the area kept, in pixels of the bitmap.
+const MIN_CROP: f64 = 1.0;
+const CROP_COLOR: Color = Color::rgb8(0x1E, 0x88, 0xE5);
+/// Veil fading the parts of the bitmap cropped out.
+const CROPPED_OUT_VEIL: Color = Color::rgba8(0xFF, 0xFF, 0xFF, 0xB0);
+
+/// Crops an image with the handles around the area kept, the whole bitmap is shown faded meanwhile.
+/// Enter, Escape or a click away from the bitmap ends the crop and hands the input back.
+pub struct CropTool {
+    /// The image cropped.
+    id: ElementId,
+    /// Touch id, handle and area of the image when the drag going on started.
+    drag: Option<(usize, Handle, Rect)>,
+    /// Whether the crop ended, the input then goes to `then`.
+    done: bool,
+    then: Option<Box<dyn Tool>>,
+}
+
+/// Constructor functions
+impl CropTool {
+    /// Crop the image `id`, handing the input over to `then` once done.
+    pub fn new(id: ElementId, then: Box<dyn Tool>) -> CropTool {
+        CropTool {
+            id,
+            drag: None,
+            done: false,
+            then: Some(then),
+        }
+    }
+}
+
+impl Tool for CropTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        let Some((element, image)) = image(editor, self.id) else {
+            self.done = true;
+            return false;
+        };
+        match (event, self.drag) {
+            (TouchInputEvent::Begin { id, .. }, None) => {
+                let reach = HANDLE_SIZE / 2.0 / ctx.zoom + HIT_TOLERANCE / ctx.zoom;
+                let handle = Handle::ALL.into_iter().find(|handle| {
+                    let offset =
+                        element.transform * handle.position(image.rect, ctx.zoom) - position;
+                    *handle != Handle::Rotate && offset.x.abs() <= reach && offset.y.abs() <= reach
+                });
+                if let Some(handle) = handle {
+                    self.drag = Some((*id, handle, image.rect));
+                    editor.begin();
+                } else {
+                    // A click away from the bitmap ends the crop
+                    let local = element.transform.inverse() * position;
+                    self.done = !image.uncropped_rect().contains(local);
+                }
+                true
+            }
+            (TouchInputEvent::Move { id, .. }, Some((pointer, handle, start)))
+                if *id == pointer =>
+            {
+                crop_to(editor, self.id, handle, start, position, ctx.zoom)
+            }
+            (TouchInputEvent::End { id, .. }, Some((pointer, handle, start))) if *id == pointer => {
+                crop_to(editor, self.id, handle, start, position, ctx.zoom);
+                self.finish(editor)
+            }
+            (TouchInputEvent::Cancel { id, .. }, Some((pointer, _, _))) if *id == pointer => {
+                self.drag = None;
+                editor.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn paint(&self, editor: &Editor, scene: &mut Scene, zoom: f64) {
+        let Some((element, image)) = image(editor, self.id) else {
+            return;
+        };
+        let uncropped = image.uncropped_rect();
+        let bitmap = &image.bitmap;
+        scene.push(SceneItem::Group {
+            transform: element.transform,
+            items: vec![
+                SceneItem::Image {
+                    rect: uncropped,
+                    width: bitmap.width(),
+                    height: bitmap.height(),
+                    pixels: bitmap.pixels().clone(),
+                    source: None,
+                },
+                SceneItem::Path {
+                    path: uncropped.to_path(0.1),
+                    style: Style {
+                        stroke: None,
+                        fill: Some(CROPPED_OUT_VEIL),
+                    },
+                },
+            ],
+        });
+        scene.push(element.scene_item());
+
+        let outline = Style {
+            stroke: Some(StrokeStyle {
+                color: CROP_COLOR,
+                width: 1.0 / zoom,
+            }),
+            fill: None,
+        };
+        scene.push(SceneItem::Path {
+            path: element.transform * image.rect.to_path(0.1),
+            style: outline.clone(),
+        });
+        let half = HANDLE_SIZE / 2.0 / zoom;
+        for handle in Handle::ALL
+            .iter()
+            .filter(|handle| **handle != Handle::Rotate)
+        {
+            let center = element.transform * handle.position(image.rect, zoom);
+            scene.push(SceneItem::Path {
+                path: Rect::new(
+                    center.x - half,
+                    center.y - half,
+                    center.x + half,
+                    center.y + half,
+                )
+                .to_path(0.1),
+                style: Style {
+                    fill: Some(Color::WHITE),
+                    ..outline.clone()
+                },
+            });
+        }
+    }
+
+    fn dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn key(&mut self, editor: &mut Editor, key: &KeyCode, _modifiers: &KeyboardModifiers) -> bool {
+        match key {
+            KeyCode::Enter | KeyCode::Escape => {
+                self.finish(editor);
+                self.done = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn scene_item(&self, element: &Element) -> SceneItem {
+        // The image cropped is painted along with the rest of its bitmap, on top of the document
+        if element.id() == self.id {
+            return SceneItem::Group {
+                transform: element.transform,
+                items: Vec::new(),
+            };
+        }
+        element.scene_item()
+    }
+
+    fn restyled(&self) -> Option<ElementId> {
+        Some(self.id)
+    }
+
+    fn finish(&mut self, editor: &mut Editor) -> bool {
+        match self.drag.take() {
+            Some(_) => {
+                editor.end();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn next_tool(&mut self) -> Option<Box<dyn Tool>> {
+        if self.done {
+            self.then.take()
+        } else {
+            None
+        }
+    }
+}
+
+/// The image `id` of the document, `None` when it was removed.
+fn image(editor: &Editor, id: ElementId) -> Option<(&Element, &Image)> {
+    let element = editor.document().get(id)?;
+    match &element.kind {
+        ElementKind::Image(image) => Some((element, image)),
+        _ => None,
+    }
+}
+
+/// Move the sides of the area kept grabbed with `handle` to the board `position`,
+/// the area was `start` when the drag started. Returns `true` when the image changed.
+fn crop_to(
+    editor: &mut Editor,
+    id: ElementId,
+    handle: Handle,
+    start: Rect,
+    position: Point,
+    zoom: f64,
+) -> bool {
+    let Some((element, image)) = image(editor, id) else {
+        return false;
+    };
+    let uncropped = image.uncropped_rect();
+    let local = element.transform.inverse() * position;
+    let local = Point::new(
+        local.x.clamp(uncropped.x0, uncropped.x1),
+        local.y.clamp(uncropped.y0, uncropped.y1),
+    );
+    let anchor = handle.opposite().position(start, zoom);
+    let (horizontal, vertical) = handle.axes();
+    let mut rect = start;
+    if horizontal {
+        rect.x0 = anchor.x.min(local.x);
+        rect.x1 = anchor.x.max(local.x);
+    }
+    if vertical {
+        rect.y0 = anchor.y.min(local.y);
+        rect.y1 = anchor.y.max(local.y);
+    }
+    let scale_x = uncropped.width() / image.bitmap.size().width;
+    let scale_y = uncropped.height() / image.bitmap.size().height;
+    let crop = Rect::new(
+        (rect.x0 - uncropped.x0) / scale_x,
+        (rect.y0 - uncropped.y0) / scale_y,
+        (rect.x1 - uncropped.x0) / scale_x,
+        (rect.y1 - uncropped.y0) / scale_y,
+    );
+    if crop.width() < MIN_CROP || crop.height() < MIN_CROP || crop == image.crop {
+        return false;
+    }
+    let mut element = element.clone();
+    element.kind = ElementKind::Image(Image {
+        rect,
+        crop,
+        ..image.clone()
+    });
+    editor.update(element)
+}
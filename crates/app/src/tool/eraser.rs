@@ -0,0 +1,258 @@
+use renderer::{
+    kurbo::{Circle, Point, Shape as _},
+    Color, Scene, SceneItem, StrokeStyle, Style,
+};
+use window::TouchInputEvent;
+
+use crate::{
+    document::{Element, ElementKind, Stroke},
+    editor::Editor,
+    tool::{touch_position, Tool, ToolContext},
+};
+
+/// Radius of the eraser in screen pixels.
+const ERASER_RADIUS: f64 = 8.0;
+const ERASER_COLOR: Color = Color::grey8(0x80);
+
+/// What the eraser removes from the strokes it touches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EraserMode {
+    /// Every element touched is removed.
+    #[default]
+    Whole,
+    /// Strokes are cut along the eraser path, the pieces left on each side are kept.
+    /// Other elements touched are removed.
+    Partial,
+}
+
+/// Removes the elements under the pointer, every touch from begin to end is undone as a single step.
+pub struct EraserTool {
+    pub mode: EraserMode,
+    /// Radius of the eraser in screen pixels.
+    pub radius: f64,
+    /// Touch id and last position of the pointer erasing.
+    pointer: Option<(usize, Point)>,
+}
+
+impl Default for EraserTool {
+    fn default() -> Self {
+        EraserTool::new(EraserMode::default())
+    }
+}
+
+/// Constructor functions
+impl EraserTool {
+    pub fn new(mode: EraserMode) -> EraserTool {
+        EraserTool {
+            mode,
+            radius: ERASER_RADIUS,
+            pointer: None,
+        }
+    }
+}
+
+impl Tool for EraserTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        let radius = self.radius / ctx.zoom;
+        match (event, self.pointer) {
+            (TouchInputEvent::Begin { id, .. }, None) => {
+                self.pointer = Some((*id, position));
+                editor.begin();
+                self.erase(editor, position, radius);
+                true
+            }
+            (TouchInputEvent::Move { id, .. }, Some((pointer, previous))) if *id == pointer => {
+                self.pointer = Some((pointer, position));
+                // Erase along the way, the pointer moves further than the eraser size between events
+                let steps = (previous.distance(position) / (radius / 2.0))
+                    .ceil()
+                    .max(1.0);
+                for step in 1..=steps as usize {
+                    let point = previous.lerp(position, step as f64 / steps);
+                    self.erase(editor, point, radius);
+                }
+                true
+            }
+            (TouchInputEvent::End { id, .. }, Some((pointer, _))) if *id == pointer => {
+                self.erase(editor, position, radius);
+                self.pointer = None;
+                editor.end();
+                true
+            }
+            (TouchInputEvent::Cancel { id, .. }, Some((pointer, _))) if *id == pointer => {
+                self.pointer = None;
+                editor.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn paint(&self, _editor: &Editor, scene: &mut Scene, zoom: f64) {
+        if let Some((_, position)) = self.pointer {
+            scene.push(SceneItem::Path {
+                path: Circle::new(position, self.radius / zoom).to_path(0.1),
+                style: Style {
+                    stroke: Some(StrokeStyle {
+                        color: ERASER_COLOR,
+                        width: 1.0 / zoom,
+                    }),
+                    fill: None,
+                },
+            });
+        }
+    }
+
+    fn dragging(&self) -> bool {
+        self.pointer.is_some()
+    }
+}
+
+/// Private functions
+impl EraserTool {
+    /// Erase the elements within `radius` of the board `point`.
+    fn erase(&self, editor: &mut Editor, point: Point, radius: f64) {
+        let touched: Vec<Element> = editor
+            .document()
+            .elements_at(point, radius)
+            .cloned()
+            .collect();
+        for element in touched {
+            match (&element.kind, self.mode) {
+                (ElementKind::Stroke(stroke), EraserMode::Partial) => {
+                    cut_stroke(editor, &element, stroke, point, radius);
+                }
+                _ => {
+                    editor.remove(element.id());
+                }
+            }
+        }
+    }
+}
+
+/// Cut the stroke of `element` around the board `point`, the first piece left keeps the element id.
+fn cut_stroke(editor: &mut Editor, element: &Element, stroke: &Stroke, point: Point, radius: f64) {
+    let scale = element.transform.determinant().abs().sqrt();
+    if scale < f64::EPSILON {
+        return;
+    }
+    // The ink reaches half the stroke width around its center line
+    let half_width = element
+        .style
+        .stroke
+        .as_ref()
+        .map_or(0.0, |style| style.width * stroke.max_width() / 2.0);
+    let local = element.transform.inverse() * point;
+    let Some(pieces) = stroke.erase(local, radius / scale + half_width) else {
+        return;
+    };
+
+    let mut pieces = pieces.into_iter();
+    let Some(first) = pieces.next() else {
+        editor.remove(element.id());
+        return;
+    };
+    let mut updated = element.clone();
+    updated.kind = ElementKind::Stroke(first);
+    editor.update(updated);
+    let Some(mut index) = editor.document().index_of(element.id()) else {
+        return;
+    };
+    for piece in pieces {
+        index += 1;
+        editor.insert(
+            index,
+            ElementKind::Stroke(piece),
+            element.style.clone(),
+            element.transform,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        document::{Document, ElementId},
+        tool::tests::{click, context, drag},
+    };
+
+    /// Add a straight stroke from `start` to `end`, 2 wide.
+    fn line(editor: &mut Editor, start: (f64, f64), end: (f64, f64)) -> ElementId {
+        let stroke = Stroke::new(vec![start.into(), end.into()], Vec::new(), false);
+        let style = Style {
+            stroke: Some(StrokeStyle {
+                color: Color::BLACK,
+                width: 2.0,
+            }),
+            fill: None,
+        };
+        editor.add(ElementKind::Stroke(stroke), style)
+    }
+
+    fn points(editor: &Editor, index: usize) -> Vec<Point> {
+        match editor
+            .document()
+            .elements()
+            .get(index)
+            .map(|element| &element.kind)
+        {
+            Some(ElementKind::Stroke(stroke)) => stroke.points().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn whole_mode_removes_the_strokes_touched() {
+        let mut editor = Editor::new(Document::default());
+        line(&mut editor, (0.0, 0.0), (100.0, 0.0));
+        let mut tool = EraserTool::new(EraserMode::Whole);
+
+        click(&mut tool, &mut editor, (50.0, 20.0), &context(false));
+        assert_eq!(editor.document().len(), 1);
+        click(&mut tool, &mut editor, (50.0, 5.0), &context(false));
+        assert_eq!(editor.document().len(), 0);
+        assert!(!tool.dragging());
+    }
+
+    #[test]
+    fn partial_mode_keeps_the_pieces_on_each_side() {
+        let mut editor = Editor::new(Document::default());
+        let id = line(&mut editor, (0.0, 0.0), (100.0, 0.0));
+        let mut tool = EraserTool::new(EraserMode::Partial);
+
+        click(&mut tool, &mut editor, (50.0, 0.0), &context(false));
+        assert_eq!(editor.document().len(), 2);
+        // The first piece keeps the id of the stroke cut, the second one is painted right above it
+        assert_eq!(editor.document().elements()[0].id(), id);
+        assert!(points(&editor, 0).iter().all(|point| point.x < 50.0));
+        assert!(points(&editor, 1).iter().all(|point| point.x > 50.0));
+        assert_eq!(points(&editor, 0)[0], Point::new(0.0, 0.0));
+        assert_eq!(points(&editor, 1).last(), Some(&Point::new(100.0, 0.0)));
+    }
+
+    #[test]
+    fn drag_erasing_several_elements_is_a_single_undo_step() {
+        let mut editor = Editor::new(Document::default());
+        for x in [10.0, 30.0, 50.0] {
+            line(&mut editor, (x, 0.0), (x, 10.0));
+        }
+        let mut tool = EraserTool::new(EraserMode::Whole);
+
+        // The eraser is smaller than the gaps between the strokes, it erases along the way
+        drag(
+            &mut tool,
+            &mut editor,
+            (0.0, 5.0),
+            &[(60.0, 5.0)],
+            &context(false),
+        );
+        assert_eq!(editor.document().len(), 0);
+        assert!(editor.undo());
+        assert_eq!(editor.document().len(), 3);
+        // The previous step is the addition of the last stroke
+        assert!(editor.undo());
+        assert_eq!(editor.document().len(), 2);
+    }
+}
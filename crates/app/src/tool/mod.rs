@@ -0,0 +1,191 @@
+//! Tools turning pointer input into document changes.
+
+mod connector;
+mod crop;
+mod eraser;
+mod pen;
+mod select;
+mod shape;
+mod text;
+
+use renderer::{
+    kurbo::{Point, Rect},
+    Scene, SceneItem,
+};
+use window::{KeyCode, KeyboardModifiers, TextInputEvent, TouchInputEvent};
+
+use crate::{
+    document::{Element, ElementId},
+    editor::Editor,
+};
+
+pub use connector::ConnectorTool;
+pub use crop::CropTool;
+pub use eraser::{EraserMode, EraserTool};
+pub use pen::PenTool;
+pub use select::SelectionTool;
+pub use shape::ShapeTool;
+pub use text::TextTool;
+
+/// State of the input devices and of the view when a touch event happens.
+#[derive(Clone, Copy, Debug)]
+pub struct ToolContext {
+    /// Keyboard modifiers held down.
+    pub modifiers: KeyboardModifiers,
+    /// Zoom of the camera the board is seen through, sizes in screen pixels are divided by it.
+    pub zoom: f64,
+    /// Number of clicks in quick succession at the same place a touch begins with, 2 for a double click.
+    pub clicks: u32,
+}
+
+/// An interaction mode of the board, receives the pointer input while it is active.
+pub trait Tool {
+    /// Handle pointer input, returns `true` when the board needs to be redrawn.
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool;
+
+    /// Paint the in-progress interaction on top of the document seen at `zoom`.
+    fn paint(&self, editor: &Editor, scene: &mut Scene, zoom: f64);
+
+    /// Whether a drag is going on which should keep the cursor within the window.
+    fn dragging(&self) -> bool {
+        false
+    }
+
+    /// Handle a key pressed before it triggers any shortcut, returns `true` when the key is used.
+    fn key(
+        &mut self,
+        _editor: &mut Editor,
+        _key: &KeyCode,
+        _modifiers: &KeyboardModifiers,
+    ) -> bool {
+        false
+    }
+
+    /// Handle text typed or composed with an input method, returns `true` when the board needs to be redrawn.
+    fn text_input(&mut self, _editor: &mut Editor, _event: &TextInputEvent) -> bool {
+        false
+    }
+
+    /// Board area of the caret of the text edited, `None` when no text is edited.
+    fn text_cursor(&self, _editor: &Editor) -> Option<Rect> {
+        None
+    }
+
+    /// How an element of the document is painted while the tool is active.
+    fn scene_item(&self, element: &Element) -> SceneItem {
+        element.scene_item()
+    }
+
+    /// Element painted differently from the document by [`Tool::scene_item`], it is left out of the
+    /// cached rasterization of the board and painted on top of it instead.
+    fn restyled(&self) -> Option<ElementId> {
+        None
+    }
+
+    /// Complete the interaction going on, before the tool is replaced or the undo history is browsed.
+    /// Returns `true` when the board needs to be redrawn.
+    fn finish(&mut self, _editor: &mut Editor) -> bool {
+        false
+    }
+
+    /// Tool to hand the input over to, polled after every input handled by the tool.
+    fn next_tool(&mut self) -> Option<Box<dyn Tool>> {
+        None
+    }
+}
+
+/// Position of the touch event in board coordinates.
+fn touch_position(event: &TouchInputEvent) -> Point {
+    let position = match event {
+        TouchInputEvent::Begin { position, .. }
+        | TouchInputEvent::Move { position, .. }
+        | TouchInputEvent::End { position, .. }
+        | TouchInputEvent::Cancel { position, .. } => position,
+    };
+    Point::new(f64::from(position.x), f64::from(position.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+
+    /// Context of a touch seen at zoom `1.0`, with shift held down when `shift` is set.
+    pub(super) fn context(shift: bool) -> ToolContext {
+        ToolContext {
+            modifiers: KeyboardModifiers {
+                shift,
+                ..KeyboardModifiers::default()
+            },
+            zoom: 1.0,
+            clicks: 1,
+        }
+    }
+
+    pub(super) fn begin(
+        tool: &mut dyn Tool,
+        editor: &mut Editor,
+        at: (f64, f64),
+        ctx: &ToolContext,
+    ) {
+        let event = TouchInputEvent::Begin {
+            id: 0,
+            position: vec2(at),
+            stylus: None,
+        };
+        tool.touch(editor, &event, ctx);
+    }
+
+    pub(super) fn move_to(
+        tool: &mut dyn Tool,
+        editor: &mut Editor,
+        at: (f64, f64),
+        ctx: &ToolContext,
+    ) {
+        let event = TouchInputEvent::Move {
+            id: 0,
+            position: vec2(at),
+            delta: Vec2::ZERO,
+            stylus: None,
+        };
+        tool.touch(editor, &event, ctx);
+    }
+
+    pub(super) fn end(tool: &mut dyn Tool, editor: &mut Editor, at: (f64, f64), ctx: &ToolContext) {
+        let event = TouchInputEvent::End {
+            id: 0,
+            position: vec2(at),
+        };
+        tool.touch(editor, &event, ctx);
+    }
+
+    /// Press at `from`, move through `through` and release at the last position.
+    pub(super) fn drag(
+        tool: &mut dyn Tool,
+        editor: &mut Editor,
+        from: (f64, f64),
+        through: &[(f64, f64)],
+        ctx: &ToolContext,
+    ) {
+        begin(tool, editor, from, ctx);
+        for at in through {
+            move_to(tool, editor, *at, ctx);
+        }
+        end(tool, editor, through.last().copied().unwrap_or(from), ctx);
+    }
+
+    /// Press and release at `at`.
+    pub(super) fn click(
+        tool: &mut dyn Tool,
+        editor: &mut Editor,
+        at: (f64, f64),
+        ctx: &ToolContext,
+    ) {
+        drag(tool, editor, at, &[], ctx);
+    }
+
+    fn vec2((x, y): (f64, f64)) -> Vec2 {
+        Vec2::new(x as f32, y as f32)
+    }
+}
@@ -0,0 +1,169 @@
+use std::f64::consts::FRAC_PI_2;
+
+use renderer::{kurbo::Point, Color, Scene, StrokeStyle, Style};
+use window::{Stylus, TouchInputEvent};
+
+use crate::{
+    document::{ElementKind, Stroke},
+    editor::Editor,
+    smoothing::SmoothingOptions,
+    tool::{touch_position, Tool, ToolContext},
+};
+
+/// Stroke width at the lightest stylus pressure, relative to the style width.
+const MIN_PRESSURE_WIDTH: f64 = 0.2;
+/// Stroke width at the strongest stylus pressure, relative to the style width.
+const MAX_PRESSURE_WIDTH: f64 = 1.6;
+/// Extra width of a stylus lying flat on the surface, relative to the pressure width.
+const TILT_WIDTH: f64 = 1.0;
+
+/// Freehand drawing, every touch from begin to end becomes a stroke.
+/// Several fingers draw several strokes at once.
+/// The stroke width follows the pressure and tilt of the stylus, for devices reporting them.
+pub struct PenTool {
+    pub style: Style,
+    /// Clean up of the input, while drawing and once drawn.
+    pub smoothing: SmoothingOptions,
+    /// The strokes being drawn keyed by touch id, in the order they started.
+    strokes: Vec<(usize, Stroke)>,
+}
+
+impl Default for PenTool {
+    fn default() -> Self {
+        Self {
+            style: Style {
+                stroke: Some(StrokeStyle {
+                    color: Color::BLACK,
+                    width: 3.0,
+                }),
+                fill: None,
+            },
+            smoothing: SmoothingOptions::default(),
+            strokes: Vec::new(),
+        }
+    }
+}
+
+impl Tool for PenTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        let id = event.id();
+        let index = self
+            .strokes
+            .iter()
+            .position(|(stroke_id, _)| *stroke_id == id);
+        match event {
+            TouchInputEvent::Begin { .. } => {
+                let mut stroke = Stroke::default();
+                push_point(&mut stroke, position, event.stylus());
+                match index {
+                    Some(index) => self.strokes[index].1 = stroke,
+                    None => self.strokes.push((id, stroke)),
+                }
+                true
+            }
+            TouchInputEvent::Move { .. } => {
+                let Some(index) = index else {
+                    return false;
+                };
+                let stroke = &mut self.strokes[index].1;
+                let position = match stroke.points().last() {
+                    Some(last) => self.smoothing.streamline(*last, position),
+                    None => position,
+                };
+                if stroke.points().last() == Some(&position) {
+                    return false;
+                }
+                push_point(stroke, position, event.stylus());
+                true
+            }
+            TouchInputEvent::End { .. } => {
+                let Some(index) = index else {
+                    return false;
+                };
+                let (_, mut stroke) = self.strokes.remove(index);
+                // The stroke catches up with the smoothing lag, up to where the pointer went up
+                if stroke.points().last() != Some(&position) {
+                    push_point(&mut stroke, position, None);
+                }
+                let width = self
+                    .style
+                    .stroke
+                    .as_ref()
+                    .map_or(0.0, |stroke| stroke.width);
+                let stroke = self.smoothing.finish(&stroke, ctx.zoom, width);
+                // Simplified once drawn, rather than after every point
+                stroke.cache_simplified(width);
+                editor.add(ElementKind::Stroke(stroke), self.style.clone());
+                true
+            }
+            TouchInputEvent::Cancel { .. } => match index {
+                Some(index) => {
+                    self.strokes.remove(index);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn paint(&self, _editor: &Editor, scene: &mut Scene, _zoom: f64) {
+        for (_, stroke) in &self.strokes {
+            scene.push(stroke.detailed_scene_item(self.style.clone()));
+        }
+    }
+}
+
+/// Add a point to the stroke, with its width when drawn with a stylus.
+fn push_point(stroke: &mut Stroke, position: Point, stylus: Option<Stylus>) {
+    stroke.push(position, stylus.map(stylus_width));
+}
+
+/// Width of the stroke for the stylus state, relative to the style width.
+fn stylus_width(stylus: Stylus) -> f64 {
+    let pressure = f64::from(stylus.pressure).clamp(0.0, 1.0);
+    // Light touches already leave a visible line
+    let mut width =
+        MIN_PRESSURE_WIDTH + (MAX_PRESSURE_WIDTH - MIN_PRESSURE_WIDTH) * pressure.sqrt();
+    if let Some(altitude) = stylus.altitude {
+        let tilt = 1.0 - (f64::from(altitude) / FRAC_PI_2).clamp(0.0, 1.0);
+        width *= 1.0 + TILT_WIDTH * tilt;
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use renderer::SceneItem;
+
+    use super::*;
+    use crate::{
+        document::Document,
+        tool::tests::{begin, context, end, move_to},
+    };
+
+    #[test]
+    fn strokes_are_simplified_once_drawn() {
+        let mut editor = Editor::new(Document::default());
+        let mut tool = PenTool::default();
+        let ctx = context(false);
+
+        // A long wavy stroke, worth simplifying when seen from afar
+        let wave = |step: u32| {
+            let x = f64::from(step) * 2.0;
+            (x, 10.0 * (x / 8.0).sin())
+        };
+        begin(&mut tool, &mut editor, wave(0), &ctx);
+        for step in 1..=400 {
+            move_to(&mut tool, &mut editor, wave(step), &ctx);
+        }
+        let mut scene = Scene::default();
+        tool.paint(&editor, &mut scene, 1.0);
+        assert!(matches!(scene.items(), [SceneItem::Path { .. }]));
+
+        end(&mut tool, &mut editor, wave(400), &ctx);
+        assert_eq!(editor.document().len(), 1);
+        let element = &editor.document().elements()[0];
+        assert!(matches!(element.scene_item(), SceneItem::Simplified { .. }));
+    }
+}
@@ -0,0 +1,737 @@
+use std::f64::consts::PI;
+
+use renderer::{
+    kurbo::{Affine, Circle, Line, Point, Rect, Shape as _},
+    Color, Scene, SceneItem, StrokeStyle, Style,
+};
+use window::{KeyCode, KeyboardModifiers, TouchInputEvent};
+
+use crate::{
+    document::{Element, ElementId, ElementKind, NoteColor},
+    editor::Editor,
+    tool::{touch_position, CropTool, TextTool, Tool, ToolContext},
+};
+
+/// Distance in screen pixels within which an element or a handle is hit.
+const HIT_TOLERANCE: f64 = 4.0;
+/// Side of the resize handles in screen pixels.
+const HANDLE_SIZE: f64 = 8.0;
+/// Distance in screen pixels between the top of the selection bounds and the rotate handle.
+const ROTATE_HANDLE_OFFSET: f64 = 24.0;
+/// Rotations snap to multiples of this angle while shift is held.
+const ROTATE_SNAP: f64 = PI / 12.0;
+/// Smallest scale factor of a resize, keeps the element transforms invertible.
+const MIN_SCALE: f64 = 0.01;
+const SELECTION_COLOR: Color = Color::rgb8(0x1E, 0x88, 0xE5);
+const MARQUEE_FILL: Color = Color::rgba8(0x1E, 0x88, 0xE5, 0x20);
+
+/// A control around the selection bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Handle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+    Rotate,
+}
+
+impl Handle {
+    pub(super) const ALL: [Handle; 9] = [
+        Handle::TopLeft,
+        Handle::Top,
+        Handle::TopRight,
+        Handle::Right,
+        Handle::BottomRight,
+        Handle::Bottom,
+        Handle::BottomLeft,
+        Handle::Left,
+        Handle::Rotate,
+    ];
+
+    /// Position of the handle around `bounds` seen at `zoom`.
+    pub(super) fn position(self, bounds: Rect, zoom: f64) -> Point {
+        let center = bounds.center();
+        match self {
+            Handle::TopLeft => Point::new(bounds.x0, bounds.y0),
+            Handle::Top => Point::new(center.x, bounds.y0),
+            Handle::TopRight => Point::new(bounds.x1, bounds.y0),
+            Handle::Right => Point::new(bounds.x1, center.y),
+            Handle::BottomRight => Point::new(bounds.x1, bounds.y1),
+            Handle::Bottom => Point::new(center.x, bounds.y1),
+            Handle::BottomLeft => Point::new(bounds.x0, bounds.y1),
+            Handle::Left => Point::new(bounds.x0, center.y),
+            Handle::Rotate => Point::new(center.x, bounds.y0 - ROTATE_HANDLE_OFFSET / zoom),
+        }
+    }
+
+    /// The handle staying in place while resizing with this one.
+    pub(super) fn opposite(self) -> Handle {
+        match self {
+            Handle::TopLeft => Handle::BottomRight,
+            Handle::Top => Handle::Bottom,
+            Handle::TopRight => Handle::BottomLeft,
+            Handle::Right => Handle::Left,
+            Handle::BottomRight => Handle::TopLeft,
+            Handle::Bottom => Handle::Top,
+            Handle::BottomLeft => Handle::TopRight,
+            Handle::Left => Handle::Right,
+            Handle::Rotate => Handle::Rotate,
+        }
+    }
+
+    /// Whether resizing with the handle scales horizontally and vertically.
+    pub(super) fn axes(self) -> (bool, bool) {
+        match self {
+            Handle::TopLeft | Handle::TopRight | Handle::BottomRight | Handle::BottomLeft => {
+                (true, true)
+            }
+            Handle::Left | Handle::Right => (true, false),
+            Handle::Top | Handle::Bottom => (false, true),
+            Handle::Rotate => (false, false),
+        }
+    }
+}
+
+/// The interaction going on while the pointer is down.
+enum Drag {
+    /// Rubber band selecting the elements it fully contains, on top of the `base` selection.
+    Marquee {
+        start: Point,
+        current: Point,
+        base: Vec<ElementId>,
+    },
+    /// Move of the selected elements, `originals` are their state when the drag started.
+    Move {
+        start: Point,
+        originals: Vec<Element>,
+    },
+    /// Scale of the selected elements around the handle opposite to `handle`.
+    Resize {
+        handle: Handle,
+        bounds: Rect,
+        originals: Vec<Element>,
+    },
+    /// Rotation of the selected elements around `center`.
+    Rotate {
+        center: Point,
+        start_angle: f64,
+        originals: Vec<Element>,
+    },
+}
+
+/// Picks elements and moves, scales or rotates them.
+///
+/// A click selects the topmost element under the pointer, shift-click toggles it in the selection.
+/// A drag on an empty area of the board selects the elements within the marquee, Escape abandons the drag.
+/// A double click edits the text of a text or a sticky note, crops an image, or adds a sticky note on an empty area.
+#[derive(Default)]
+pub struct SelectionTool {
+    /// Color of the sticky notes added with a double click.
+    pub note_color: NoteColor,
+    drag: Option<Drag>,
+    /// Touch id of the finger or pointer driving the drag, other touches are ignored meanwhile.
+    pointer: usize,
+    /// Text or crop tool editing the element double clicked, until it hands the input back.
+    next: Option<Box<dyn Tool>>,
+}
+
+impl Tool for SelectionTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        if self.drag.is_some() && event.id() != self.pointer {
+            return false;
+        }
+        let position = touch_position(event);
+        match event {
+            TouchInputEvent::Begin { id, .. } => {
+                self.finish(editor);
+                if ctx.clicks == 2 && self.double_click(editor, position, ctx) {
+                    return true;
+                }
+                self.pointer = *id;
+                self.begin(editor, position, ctx);
+                true
+            }
+            TouchInputEvent::Move { .. } => self.drag_to(editor, position, ctx),
+            TouchInputEvent::End { .. } => {
+                let redraw = self.drag_to(editor, position, ctx);
+                self.finish(editor) || redraw
+            }
+            TouchInputEvent::Cancel { .. } => self.cancel(editor),
+        }
+    }
+
+    fn paint(&self, editor: &Editor, scene: &mut Scene, zoom: f64) {
+        let outline = Style {
+            stroke: Some(StrokeStyle {
+                color: SELECTION_COLOR,
+                width: 1.0 / zoom,
+            }),
+            fill: None,
+        };
+
+        if let Some(Drag::Marquee { start, current, .. }) = &self.drag {
+            scene.push(SceneItem::Path {
+                path: Rect::from_points(*start, *current).to_path(0.1),
+                style: Style {
+                    fill: Some(MARQUEE_FILL),
+                    ..outline.clone()
+                },
+            });
+        }
+
+        let mut bounds: Option<Rect> = None;
+        for element in editor.selected_elements() {
+            let element_bounds = element.bounds();
+            scene.push(SceneItem::Path {
+                path: element_bounds.to_path(0.1),
+                style: outline.clone(),
+            });
+            bounds = Some(bounds.map_or(element_bounds, |bounds| bounds.union(element_bounds)));
+        }
+        let Some(bounds) = bounds else {
+            return;
+        };
+        if editor.selection().len() > 1 {
+            scene.push(SceneItem::Path {
+                path: bounds.to_path(0.1),
+                style: outline.clone(),
+            });
+        }
+
+        let handle_style = Style {
+            fill: Some(Color::WHITE),
+            ..outline.clone()
+        };
+        let top = Handle::Top.position(bounds, zoom);
+        let rotate = Handle::Rotate.position(bounds, zoom);
+        scene.push(SceneItem::Path {
+            path: Line::new(top, rotate).to_path(0.1),
+            style: outline,
+        });
+        scene.push(SceneItem::Path {
+            path: Circle::new(rotate, HANDLE_SIZE / 2.0 / zoom).to_path(0.1),
+            style: handle_style.clone(),
+        });
+        let half = HANDLE_SIZE / 2.0 / zoom;
+        for handle in Handle::ALL
+            .iter()
+            .filter(|handle| **handle != Handle::Rotate)
+        {
+            let center = handle.position(bounds, zoom);
+            scene.push(SceneItem::Path {
+                path: Rect::new(
+                    center.x - half,
+                    center.y - half,
+                    center.x + half,
+                    center.y + half,
+                )
+                .to_path(0.1),
+                style: handle_style.clone(),
+            });
+        }
+    }
+
+    fn dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn key(&mut self, editor: &mut Editor, key: &KeyCode, _modifiers: &KeyboardModifiers) -> bool {
+        match key {
+            // Escape abandons the drag going on, the rest of the touch is ignored
+            KeyCode::Escape => self.cancel(editor),
+            _ => false,
+        }
+    }
+
+    fn next_tool(&mut self) -> Option<Box<dyn Tool>> {
+        self.next.take()
+    }
+}
+
+/// Private functions
+impl SelectionTool {
+    /// Start an interaction at `position`, depending on what is under the pointer.
+    fn begin(&mut self, editor: &mut Editor, position: Point, ctx: &ToolContext) {
+        let tolerance = HIT_TOLERANCE / ctx.zoom;
+        let shift = ctx.modifiers.shift;
+
+        if let Some(bounds) = selection_bounds(editor) {
+            let reach = HANDLE_SIZE / 2.0 / ctx.zoom + tolerance;
+            let handle = Handle::ALL.into_iter().find(|handle| {
+                let offset = handle.position(bounds, ctx.zoom) - position;
+                offset.x.abs() <= reach && offset.y.abs() <= reach
+            });
+            if let Some(handle) = handle {
+                let originals = editor.selected_elements().cloned().collect();
+                let center = bounds.center();
+                self.drag = Some(if handle == Handle::Rotate {
+                    Drag::Rotate {
+                        center,
+                        start_angle: (position - center).atan2(),
+                        originals,
+                    }
+                } else {
+                    Drag::Resize {
+                        handle,
+                        bounds,
+                        originals,
+                    }
+                });
+                editor.begin();
+                return;
+            }
+        }
+
+        let hit = editor
+            .document()
+            .elements_at(position, tolerance)
+            .next()
+            .map(Element::id);
+        match hit {
+            Some(id) if shift => editor.selection_mut().toggle(id),
+            Some(id) => {
+                if !editor.selection().contains(id) {
+                    editor.selection_mut().set([id]);
+                }
+                self.drag = Some(Drag::Move {
+                    start: position,
+                    originals: editor.selected_elements().cloned().collect(),
+                });
+                editor.begin();
+            }
+            None => {
+                let base = if shift {
+                    editor.selection().ids().to_vec()
+                } else {
+                    editor.selection_mut().clear();
+                    Vec::new()
+                };
+                self.drag = Some(Drag::Marquee {
+                    start: position,
+                    current: position,
+                    base,
+                });
+            }
+        }
+    }
+
+    /// Edit the text or crop the image under `position`, or add a sticky note there when the board is empty.
+    /// Returns `false` when another element is under the pointer, the double click is taken for a click.
+    fn double_click(&mut self, editor: &mut Editor, position: Point, ctx: &ToolContext) -> bool {
+        let tolerance = HIT_TOLERANCE / ctx.zoom;
+        let hit = editor
+            .document()
+            .elements_at(position, tolerance)
+            .next()
+            .map(Element::id);
+        // The text and crop tools hand the input back to a selection tool once the edit is finished
+        let then = Box::new(SelectionTool {
+            note_color: self.note_color,
+            ..SelectionTool::default()
+        });
+        let next: Box<dyn Tool> = match hit.and_then(|id| editor.document().get(id)) {
+            Some(element) if element.kind.text_box().is_some() => {
+                Box::new(TextTool::edit(editor, element.id(), position, then))
+            }
+            Some(element) if matches!(element.kind, ElementKind::Image(_)) => {
+                Box::new(CropTool::new(element.id(), then))
+            }
+            Some(_) => return false,
+            None => Box::new(TextTool::add_note(editor, position, self.note_color, then)),
+        };
+        self.next = Some(next);
+        true
+    }
+
+    /// Update the interaction with the pointer at `position`, returns `true` when the board needs to be redrawn.
+    fn drag_to(&mut self, editor: &mut Editor, position: Point, ctx: &ToolContext) -> bool {
+        let shift = ctx.modifiers.shift;
+        match &mut self.drag {
+            None => false,
+            Some(Drag::Marquee {
+                start,
+                current,
+                base,
+            }) => {
+                *current = position;
+                let marquee = Rect::from_points(*start, *current);
+                let inside: Vec<_> = editor
+                    .document()
+                    .elements_in(marquee)
+                    .into_iter()
+                    .filter(|element| marquee.union(element.bounds()) == marquee)
+                    .map(|element| element.id())
+                    .collect();
+                let selection = editor.selection_mut();
+                selection.set(base.iter().copied());
+                selection.extend(inside);
+                true
+            }
+            Some(Drag::Move { start, originals }) => {
+                let mut delta = position - *start;
+                // Shift keeps the move horizontal or vertical
+                if shift {
+                    if delta.x.abs() > delta.y.abs() {
+                        delta.y = 0.0;
+                    } else {
+                        delta.x = 0.0;
+                    }
+                }
+                transform_all(editor, originals, Affine::translate(delta))
+            }
+            Some(Drag::Resize {
+                handle,
+                bounds,
+                originals,
+            }) => {
+                let anchor = handle.opposite().position(*bounds, ctx.zoom);
+                let grabbed = handle.position(*bounds, ctx.zoom);
+                let (horizontal, vertical) = handle.axes();
+                let mut scale_x = if horizontal {
+                    scale_factor(position.x - anchor.x, grabbed.x - anchor.x)
+                } else {
+                    1.0
+                };
+                let mut scale_y = if vertical {
+                    scale_factor(position.y - anchor.y, grabbed.y - anchor.y)
+                } else {
+                    1.0
+                };
+                // Shift keeps the aspect ratio of the selection
+                if shift && horizontal && vertical {
+                    let scale = scale_x.abs().max(scale_y.abs());
+                    scale_x = scale.copysign(scale_x);
+                    scale_y = scale.copysign(scale_y);
+                }
+                let transform = Affine::translate(anchor.to_vec2())
+                    * Affine::scale_non_uniform(scale_x, scale_y)
+                    * Affine::translate(-anchor.to_vec2());
+                transform_all(editor, originals, transform)
+            }
+            Some(Drag::Rotate {
+                center,
+                start_angle,
+                originals,
+            }) => {
+                let mut angle = (position - *center).atan2() - *start_angle;
+                if shift {
+                    angle = (angle / ROTATE_SNAP).round() * ROTATE_SNAP;
+                }
+                transform_all(editor, originals, Affine::rotate_about(angle, *center))
+            }
+        }
+    }
+
+    /// Stop the interaction, returns `true` when there was one.
+    fn finish(&mut self, editor: &mut Editor) -> bool {
+        match self.drag.take() {
+            Some(Drag::Marquee { .. }) => true,
+            Some(_) => {
+                editor.end();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Abandon the interaction, transformed elements go back to their state before it.
+    /// Returns `true` when there was one.
+    fn cancel(&mut self, editor: &mut Editor) -> bool {
+        match self.drag.take() {
+            Some(Drag::Marquee { base, .. }) => {
+                editor.selection_mut().set(base);
+                true
+            }
+            Some(_) => {
+                editor.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Union of the bounds of the selected elements, `None` when nothing is selected.
+fn selection_bounds(editor: &Editor) -> Option<Rect> {
+    editor
+        .selected_elements()
+        .map(Element::bounds)
+        .reduce(|bounds, other| bounds.union(other))
+}
+
+/// Ratio between the pointer and the grabbed handle distances to the anchor, kept away from zero.
+fn scale_factor(current: f64, original: f64) -> f64 {
+    if original.abs() < f64::EPSILON {
+        return 1.0;
+    }
+    let scale = current / original;
+    if scale.abs() < MIN_SCALE {
+        MIN_SCALE.copysign(scale)
+    } else {
+        scale
+    }
+}
+
+/// Apply `transform` on top of the original transform of each element,
+/// returns `true` when any element changed.
+fn transform_all(editor: &mut Editor, originals: &[Element], transform: Affine) -> bool {
+    let mut changed = false;
+    for original in originals {
+        let transform = transform * original.transform;
+        let unchanged = editor
+            .document()
+            .get(original.id())
+            .map_or(true, |current| current.transform == transform);
+        if unchanged {
+            continue;
+        }
+        let mut element = original.clone();
+        element.transform = transform;
+        changed |= editor.update(element);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use renderer::kurbo::Vec2;
+
+    use super::*;
+    use crate::{
+        document::{Document, Shape, ShapeKind},
+        tool::tests::{begin, click, context, drag, end, move_to},
+    };
+
+    /// Editor with two filled squares of side 40, `a` at the origin and `b` 20 to the right of it.
+    fn squares() -> (Editor, ElementId, ElementId) {
+        let mut editor = Editor::new(Document::default());
+        let mut square = |x: f64| {
+            let shape = Shape {
+                kind: ShapeKind::Rectangle,
+                rect: Rect::new(x, 0.0, x + 40.0, 40.0),
+                corner_radius: 0.0,
+            };
+            let style = Style {
+                stroke: None,
+                fill: Some(Color::BLACK),
+            };
+            editor.add(ElementKind::Shape(shape), style)
+        };
+        let (a, b) = (square(0.0), square(60.0));
+        (editor, a, b)
+    }
+
+    fn transform(editor: &Editor, id: ElementId) -> Affine {
+        editor
+            .document()
+            .get(id)
+            .map_or(Affine::IDENTITY, |element| element.transform)
+    }
+
+    fn bounds(editor: &Editor, id: ElementId) -> Rect {
+        editor
+            .document()
+            .get(id)
+            .map_or(Rect::ZERO, Element::bounds)
+    }
+
+    fn assert_near(actual: Affine, expected: Affine) {
+        let offsets = actual.as_coeffs().into_iter().zip(expected.as_coeffs());
+        for (actual_coeff, expected_coeff) in offsets {
+            assert!(
+                (actual_coeff - expected_coeff).abs() < 1e-6,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn click_selects_the_element_under_the_pointer() {
+        let (mut editor, a, b) = squares();
+        let mut tool = SelectionTool::default();
+        let ctx = context(false);
+
+        click(&mut tool, &mut editor, (20.0, 20.0), &ctx);
+        assert_eq!(editor.selection().ids(), [a]);
+        click(&mut tool, &mut editor, (80.0, 20.0), &ctx);
+        assert_eq!(editor.selection().ids(), [b]);
+        click(&mut tool, &mut editor, (200.0, 200.0), &ctx);
+        assert!(editor.selection().is_empty());
+        assert!(!tool.dragging());
+    }
+
+    #[test]
+    fn shift_click_toggles_the_element() {
+        let (mut editor, a, b) = squares();
+        let mut tool = SelectionTool::default();
+
+        click(&mut tool, &mut editor, (20.0, 20.0), &context(false));
+        click(&mut tool, &mut editor, (80.0, 20.0), &context(true));
+        assert_eq!(editor.selection().ids(), [a, b]);
+        click(&mut tool, &mut editor, (20.0, 20.0), &context(true));
+        assert_eq!(editor.selection().ids(), [b]);
+    }
+
+    #[test]
+    fn marquee_selects_the_elements_it_contains() {
+        let (mut editor, a, b) = squares();
+        let mut tool = SelectionTool::default();
+
+        // `b` is only partly within the marquee
+        drag(
+            &mut tool,
+            &mut editor,
+            (-5.0, -5.0),
+            &[(70.0, 45.0)],
+            &context(false),
+        );
+        assert_eq!(editor.selection().ids(), [a]);
+        // Shift adds to the selection
+        drag(
+            &mut tool,
+            &mut editor,
+            (50.0, -15.0),
+            &[(110.0, 45.0)],
+            &context(true),
+        );
+        assert_eq!(editor.selection().ids(), [a, b]);
+        drag(
+            &mut tool,
+            &mut editor,
+            (50.0, -15.0),
+            &[(110.0, 45.0)],
+            &context(false),
+        );
+        assert_eq!(editor.selection().ids(), [b]);
+    }
+
+    #[test]
+    fn drag_moves_the_selection() {
+        let (mut editor, a, b) = squares();
+        let mut tool = SelectionTool::default();
+
+        click(&mut tool, &mut editor, (20.0, 20.0), &context(false));
+        click(&mut tool, &mut editor, (80.0, 20.0), &context(true));
+        drag(
+            &mut tool,
+            &mut editor,
+            (80.0, 20.0),
+            &[(85.0, 21.0), (90.0, 23.0)],
+            &context(false),
+        );
+        assert_eq!(transform(&editor, a), Affine::translate((10.0, 3.0)));
+        assert_eq!(transform(&editor, b), Affine::translate((10.0, 3.0)));
+
+        // Shift held once the move started keeps it horizontal or vertical
+        begin(&mut tool, &mut editor, (90.0, 23.0), &context(false));
+        move_to(&mut tool, &mut editor, (91.0, 35.0), &context(true));
+        end(&mut tool, &mut editor, (91.0, 35.0), &context(true));
+        assert_eq!(transform(&editor, a), Affine::translate((10.0, 15.0)));
+    }
+
+    #[test]
+    fn shift_resize_keeps_the_aspect_ratio() {
+        let (mut editor, a, _) = squares();
+        let mut tool = SelectionTool::default();
+
+        click(&mut tool, &mut editor, (20.0, 20.0), &context(false));
+        drag(
+            &mut tool,
+            &mut editor,
+            (40.0, 40.0),
+            &[(80.0, 70.0)],
+            &context(false),
+        );
+        assert_eq!(bounds(&editor, a), Rect::new(0.0, 0.0, 80.0, 70.0));
+
+        // The largest of the horizontal and vertical scales applies to both
+        drag(
+            &mut tool,
+            &mut editor,
+            (80.0, 70.0),
+            &[(120.0, 100.0)],
+            &context(true),
+        );
+        assert_eq!(bounds(&editor, a), Rect::new(0.0, 0.0, 120.0, 105.0));
+    }
+
+    #[test]
+    fn rotate_handle_turns_the_selection_about_its_center() {
+        let (mut editor, a, _) = squares();
+        let mut tool = SelectionTool::default();
+        let center = Point::new(20.0, 20.0);
+
+        click(&mut tool, &mut editor, center.into(), &context(false));
+        let handle = Handle::Rotate.position(bounds(&editor, a), 1.0);
+        let quarter = center + Vec2::new(60.0, 0.0);
+        drag(
+            &mut tool,
+            &mut editor,
+            handle.into(),
+            &[quarter.into()],
+            &context(false),
+        );
+        assert_near(
+            transform(&editor, a),
+            Affine::rotate_about(PI / 2.0, center),
+        );
+
+        // Shift snaps the angle, the rotation adds up to the previous one
+        let handle = Handle::Rotate.position(bounds(&editor, a), 1.0);
+        let half_turn = center + Vec2::new(-4.0, 60.0);
+        drag(
+            &mut tool,
+            &mut editor,
+            handle.into(),
+            &[half_turn.into()],
+            &context(true),
+        );
+        assert_near(
+            transform(&editor, a),
+            Affine::rotate_about(1.5 * PI, center),
+        );
+    }
+
+    #[test]
+    fn escape_abandons_the_drag() {
+        let (mut editor, a, _) = squares();
+        let mut tool = SelectionTool::default();
+        let ctx = context(false);
+
+        begin(&mut tool, &mut editor, (20.0, 20.0), &ctx);
+        move_to(&mut tool, &mut editor, (30.0, 20.0), &ctx);
+        assert!(tool.dragging());
+        assert!(tool.key(&mut editor, &KeyCode::Escape, &ctx.modifiers));
+        assert!(!tool.dragging());
+        assert_eq!(transform(&editor, a), Affine::IDENTITY);
+
+        // The rest of the touch is ignored and nothing is recorded
+        move_to(&mut tool, &mut editor, (40.0, 20.0), &ctx);
+        end(&mut tool, &mut editor, (40.0, 20.0), &ctx);
+        assert_eq!(transform(&editor, a), Affine::IDENTITY);
+        assert!(editor.undo());
+        assert_eq!(editor.document().len(), 1);
+    }
+
+    #[test]
+    fn drag_is_a_single_undo_step() {
+        let (mut editor, a, b) = squares();
+        let mut tool = SelectionTool::default();
+
+        let path: Vec<_> = (1..=10)
+            .map(|step| (20.0 + f64::from(step), 20.0))
+            .collect();
+        drag(&mut tool, &mut editor, (20.0, 20.0), &path, &context(false));
+        assert_eq!(transform(&editor, a), Affine::translate((10.0, 0.0)));
+        assert!(editor.undo());
+        assert_eq!(transform(&editor, a), Affine::IDENTITY);
+        // The previous step is the addition of `b`
+        assert!(editor.undo());
+        assert!(editor.document().get(b).is_none());
+        assert!(editor.document().get(a).is_some());
+    }
+}
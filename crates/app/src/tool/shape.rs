@@ -0,0 +1,112 @@
+use renderer::{
+    kurbo::{Point, Rect, Vec2},
+    Color, Scene, SceneItem, StrokeStyle, Style,
+};
+use window::{KeyboardModifiers, TouchInputEvent};
+
+use crate::{
+    document::{ElementKind, Shape, ShapeKind},
+    editor::Editor,
+    tool::{touch_position, Tool, ToolContext},
+};
+
+/// Size in screen pixels under which a drag is taken for a click, no shape is added.
+const MIN_DRAG: f64 = 2.0;
+
+/// Draws a primitive shape from where the pointer goes down to where it goes up.
+/// Shift keeps the sides equal and alt draws from the center out.
+/// Lines and arrows are drawn with the connector tool.
+pub struct ShapeTool {
+    pub kind: ShapeKind,
+    pub style: Style,
+    /// Radius of the rounded corners of rectangles.
+    pub corner_radius: f64,
+    /// Touch id, start position and shape of the drag going on.
+    drawing: Option<(usize, Point, Shape)>,
+}
+
+/// Constructor functions
+impl ShapeTool {
+    pub fn new(kind: ShapeKind) -> ShapeTool {
+        ShapeTool {
+            kind,
+            style: Style {
+                stroke: Some(StrokeStyle {
+                    color: Color::BLACK,
+                    width: 2.0,
+                }),
+                fill: None,
+            },
+            corner_radius: 0.0,
+            drawing: None,
+        }
+    }
+}
+
+impl Tool for ShapeTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        match (event, &self.drawing) {
+            (TouchInputEvent::Begin { id, .. }, None) => {
+                let shape = self.shape(position, position, &ctx.modifiers);
+                self.drawing = Some((*id, position, shape));
+                true
+            }
+            (TouchInputEvent::Move { id, .. }, Some((pointer, start, _))) if id == pointer => {
+                let shape = self.shape(*start, position, &ctx.modifiers);
+                self.drawing = Some((*pointer, *start, shape));
+                true
+            }
+            (TouchInputEvent::End { id, .. }, Some((pointer, start, _))) if id == pointer => {
+                let shape = self.shape(*start, position, &ctx.modifiers);
+                self.drawing = None;
+                let rect = shape.rect.abs();
+                if rect.width().max(rect.height()) * ctx.zoom >= MIN_DRAG {
+                    editor.add(ElementKind::Shape(shape), self.style.clone());
+                }
+                true
+            }
+            (TouchInputEvent::Cancel { id, .. }, Some((pointer, _, _))) if id == pointer => {
+                self.drawing = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn paint(&self, _editor: &Editor, scene: &mut Scene, _zoom: f64) {
+        if let Some((_, _, shape)) = &self.drawing {
+            scene.push(SceneItem::Path {
+                path: shape.path(),
+                style: self.style.clone(),
+            });
+        }
+    }
+
+    fn dragging(&self) -> bool {
+        self.drawing.is_some()
+    }
+}
+
+/// Private functions
+impl ShapeTool {
+    /// Shape dragged from the `start` board position to the `end` one.
+    fn shape(&self, start: Point, end: Point, modifiers: &KeyboardModifiers) -> Shape {
+        let mut delta = end - start;
+        if modifiers.shift {
+            let side = delta.x.abs().max(delta.y.abs());
+            delta = Vec2::new(side.copysign(delta.x), side.copysign(delta.y));
+        }
+        let (from, to) = if modifiers.alt {
+            (start - delta, start + delta)
+        } else {
+            (start, start + delta)
+        };
+        let rect = Rect::new(from.x, from.y, to.x, to.y);
+        Shape {
+            kind: self.kind,
+            rect: rect.abs(),
+            corner_radius: self.corner_radius,
+        }
+    }
+}
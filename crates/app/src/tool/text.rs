@@ -0,0 +1,475 @@
+use std::ops::Range;
+
+use renderer::{
+    kurbo::{Point, Rect},
+    Color, Font, MeasuredText, Scene, SceneItem, Style, TextEditing,
+};
+use window::{KeyCode, KeyboardModifiers, TextInputEvent, TouchInputEvent};
+
+use crate::{
+    document::{Element, ElementId, ElementKind, Note, NoteColor, Text, TextBox},
+    editor::Editor,
+    tool::{touch_position, Tool, ToolContext},
+};
+
+/// Font size of new texts.
+const FONT_SIZE: f64 = 20.0;
+
+/// Text being edited, its changes are undone as a single step.
+#[derive(Debug)]
+struct Editing {
+    id: ElementId,
+    /// Whether the element was added for this edit, texts are dropped when left empty.
+    added: bool,
+    /// Byte offset of the caret into the content.
+    caret: usize,
+    /// Byte offset the selection extends from to the caret, the caret offset when nothing is selected.
+    anchor: usize,
+    /// Text being composed with an input method, along with the byte range selected within it.
+    preedit: Option<(String, Option<(usize, usize)>)>,
+    /// Touch id of the pointer dragging a selection.
+    pointer: Option<usize>,
+}
+
+impl Editing {
+    fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    /// Move the caret to `offset`, the selection extends to it when `extend` is set.
+    fn move_to(&mut self, offset: usize, extend: bool) {
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+    }
+}
+
+/// Adds and edits blocks of text in place, as well as the text of sticky notes.
+/// A click on a text edits it, a click elsewhere adds a text or finishes the one edited.
+pub struct TextTool {
+    /// Style of new texts, the fill colors the text.
+    pub style: Style,
+    pub font: Font,
+    pub font_size: f64,
+    /// Width new texts wrap at, `None` to only break lines at line breaks.
+    pub width: Option<f64>,
+    editing: Option<Editing>,
+    /// Tool taking over once the edit is finished, when the tool was entered for a single edit.
+    then: Option<Box<dyn Tool>>,
+}
+
+impl Default for TextTool {
+    fn default() -> Self {
+        Self {
+            style: Style {
+                stroke: None,
+                fill: Some(Color::BLACK),
+            },
+            font: Font::default(),
+            font_size: FONT_SIZE,
+            width: None,
+            editing: None,
+            then: None,
+        }
+    }
+}
+
+/// Constructor functions
+impl TextTool {
+    /// Edit the text of the element `id` with the caret closest to the board `position`,
+    /// `then` takes over once the edit is finished.
+    pub fn edit(
+        editor: &mut Editor,
+        id: ElementId,
+        position: Point,
+        then: Box<dyn Tool>,
+    ) -> TextTool {
+        let mut tool = TextTool {
+            then: Some(then),
+            ..TextTool::default()
+        };
+        let offset = offset_at(editor, id, position).unwrap_or_default();
+        editor.begin();
+        tool.editing = Some(Editing {
+            id,
+            added: false,
+            caret: offset,
+            anchor: offset,
+            preedit: None,
+            pointer: None,
+        });
+        tool
+    }
+
+    /// Add a sticky note centered on the board `center` and edit its text,
+    /// `then` takes over once the edit is finished.
+    pub fn add_note(
+        editor: &mut Editor,
+        center: Point,
+        color: NoteColor,
+        then: Box<dyn Tool>,
+    ) -> TextTool {
+        let mut tool = TextTool {
+            then: Some(then),
+            ..TextTool::default()
+        };
+        editor.begin();
+        let id = editor.add(
+            ElementKind::Note(Note::new(center, color)),
+            Style::default(),
+        );
+        tool.editing = Some(Editing {
+            id,
+            added: true,
+            caret: 0,
+            anchor: 0,
+            preedit: None,
+            pointer: None,
+        });
+        tool
+    }
+}
+
+impl Tool for TextTool {
+    fn touch(&mut self, editor: &mut Editor, event: &TouchInputEvent, ctx: &ToolContext) -> bool {
+        let position = touch_position(event);
+        match event {
+            TouchInputEvent::Begin { id, .. } => {
+                let hit = text_at(editor, position);
+                let editing_id = self.editing.as_ref().map(|editing| editing.id);
+                match hit {
+                    Some((hit, offset)) if Some(hit) == editing_id => {
+                        if let Some(editing) = &mut self.editing {
+                            editing.move_to(offset, ctx.modifiers.shift);
+                            editing.pointer = Some(*id);
+                        }
+                    }
+                    Some((hit, offset)) => {
+                        self.finish(editor);
+                        editor.begin();
+                        self.editing = Some(Editing {
+                            id: hit,
+                            added: false,
+                            caret: offset,
+                            anchor: offset,
+                            preedit: None,
+                            pointer: Some(*id),
+                        });
+                    }
+                    // A click away finishes the text edited, the next one adds a text
+                    None if editing_id.is_some() => {
+                        self.finish(editor);
+                    }
+                    None => self.add_text(editor, position),
+                }
+                true
+            }
+            TouchInputEvent::Move { id, .. } => {
+                let Some(editing) = self.editing.as_mut().filter(|e| e.pointer == Some(*id)) else {
+                    return false;
+                };
+                let Some(offset) = offset_at(editor, editing.id, position) else {
+                    return false;
+                };
+                editing.move_to(offset, true);
+                true
+            }
+            TouchInputEvent::End { id, .. } | TouchInputEvent::Cancel { id, .. } => {
+                match self.editing.as_mut().filter(|e| e.pointer == Some(*id)) {
+                    Some(editing) => {
+                        editing.pointer = None;
+                        true
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+
+    fn paint(&self, _editor: &Editor, _scene: &mut Scene, _zoom: f64) {
+        // The caret and selection are painted along with the text, see `scene_item`
+    }
+
+    fn dragging(&self) -> bool {
+        self.editing
+            .as_ref()
+            .map_or(false, |editing| editing.pointer.is_some())
+    }
+
+    fn key(&mut self, editor: &mut Editor, key: &KeyCode, modifiers: &KeyboardModifiers) -> bool {
+        let Some(editing) = &self.editing else {
+            return false;
+        };
+        let Some(text) = text_box(editor, editing.id) else {
+            return false;
+        };
+        let content = text.content.to_owned();
+        let measured = MeasuredText::new(&content, text.font, text.font_size, text.width);
+        let caret = editing.caret;
+        let selection = editing.selection();
+        let extend = modifiers.shift;
+        let lines = measured.lines();
+        let line = lines
+            .iter()
+            .rposition(|line| line.start <= caret)
+            .map_or(0..0, |index| lines[index].clone());
+        // Offset of the caret one line above or below, `lines` being negative to go up
+        let vertical = |lines: f64| {
+            let caret = measured.caret(caret);
+            measured.offset_at(Point::new(
+                caret.x0,
+                caret.center().y + lines * caret.height(),
+            ))
+        };
+
+        match key {
+            // Shortcuts other than select all stay available while editing
+            KeyCode::Character(c, _) if modifiers.cmd_or_ctrl() => {
+                if !c.eq_ignore_ascii_case("a") {
+                    return false;
+                }
+                if let Some(editing) = &mut self.editing {
+                    editing.anchor = 0;
+                    editing.caret = content.len();
+                }
+            }
+            _ if modifiers.cmd_or_ctrl() => return false,
+            KeyCode::BackspaceOrDelete => {
+                let start = if selection.is_empty() {
+                    previous_boundary(&content, caret)
+                } else {
+                    selection.start
+                };
+                self.replace(editor, start..selection.end, "");
+            }
+            KeyCode::FDelete => {
+                let end = if selection.is_empty() {
+                    next_boundary(&content, caret)
+                } else {
+                    selection.end
+                };
+                self.replace(editor, selection.start..end, "");
+            }
+            KeyCode::Enter => self.replace(editor, selection, "\n"),
+            KeyCode::Escape => {
+                self.finish(editor);
+            }
+            KeyCode::ArrowLeft
+            | KeyCode::ArrowRight
+            | KeyCode::ArrowUp
+            | KeyCode::ArrowDown
+            | KeyCode::Home
+            | KeyCode::End => {
+                let offset = match key {
+                    // Moving away from a selection starts from the side moved to
+                    KeyCode::ArrowLeft if !selection.is_empty() && !extend => selection.start,
+                    KeyCode::ArrowRight if !selection.is_empty() && !extend => selection.end,
+                    KeyCode::ArrowLeft => previous_boundary(&content, caret),
+                    KeyCode::ArrowRight => next_boundary(&content, caret),
+                    KeyCode::ArrowUp => vertical(-1.0),
+                    KeyCode::ArrowDown => vertical(1.0),
+                    KeyCode::Home => line.start,
+                    _ => line.end,
+                };
+                if let Some(editing) = &mut self.editing {
+                    editing.move_to(offset, extend);
+                }
+            }
+            // Characters are typed through text input, they must not trigger the tool shortcuts
+            KeyCode::Character(..) | KeyCode::Space => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn text_input(&mut self, editor: &mut Editor, event: &TextInputEvent) -> bool {
+        let Some(editing) = &mut self.editing else {
+            return false;
+        };
+        match event {
+            TextInputEvent::Commit(text) => {
+                editing.preedit = None;
+                let selection = editing.selection();
+                self.replace(editor, selection, text);
+            }
+            TextInputEvent::Preedit { text, cursor } => {
+                editing.preedit = (!text.is_empty()).then(|| (text.clone(), *cursor));
+            }
+        }
+        true
+    }
+
+    fn text_cursor(&self, editor: &Editor) -> Option<Rect> {
+        let editing = self.editing.as_ref()?;
+        let element = editor.document().get(editing.id)?;
+        let text = element.kind.text_box()?;
+        let caret = text.measure().caret(editing.caret) + text.origin.to_vec2();
+        Some(element.transform.transform_rect_bbox(caret))
+    }
+
+    fn scene_item(&self, element: &Element) -> SceneItem {
+        let Some(editing) = self.editing.as_ref().filter(|e| e.id == element.id()) else {
+            return element.scene_item();
+        };
+        let mut shown = element.clone();
+        let Some(content) = shown.kind.content_mut() else {
+            return element.scene_item();
+        };
+        let selection = editing.selection();
+        let text_editing = match &editing.preedit {
+            // The composition takes the place of the selection until committed
+            Some((preedit, cursor)) => {
+                content.replace_range(selection.clone(), preedit);
+                let start = selection.start;
+                let caret = start + cursor.map_or(preedit.len(), |(_, end)| end);
+                TextEditing {
+                    caret,
+                    selection: caret..caret,
+                    composition: Some(start..start + preedit.len()),
+                }
+            }
+            None => TextEditing {
+                caret: editing.caret,
+                selection,
+                composition: None,
+            },
+        };
+        let mut item = shown.scene_item();
+        set_editing(&mut item, &text_editing);
+        item
+    }
+
+    fn restyled(&self) -> Option<ElementId> {
+        self.editing.as_ref().map(|editing| editing.id)
+    }
+
+    fn finish(&mut self, editor: &mut Editor) -> bool {
+        let Some(editing) = self.editing.take() else {
+            return false;
+        };
+        // Empty notes stay on the board, only empty texts are dropped
+        let empty = editor.document().get(editing.id).map_or(
+            true,
+            |element| matches!(&element.kind, ElementKind::Text(text) if text.content.is_empty()),
+        );
+        match (empty, editing.added) {
+            // Nothing was written, the text is not worth an undo step
+            (true, true) => editor.cancel(),
+            (true, false) => {
+                editor.remove(editing.id);
+                editor.end();
+            }
+            (false, _) => editor.end(),
+        }
+        true
+    }
+
+    fn next_tool(&mut self) -> Option<Box<dyn Tool>> {
+        if self.editing.is_some() {
+            return None;
+        }
+        self.then.take()
+    }
+}
+
+/// Private functions
+impl TextTool {
+    /// Add an empty text with the vertical middle of its first line at `position` and edit it.
+    fn add_text(&mut self, editor: &mut Editor, position: Point) {
+        let line = MeasuredText::new("", self.font, self.font_size, self.width).caret(0);
+        let origin = position - (0.0, line.center().y);
+        editor.begin();
+        let id = editor.add(
+            ElementKind::Text(Text {
+                content: String::new(),
+                origin,
+                font_size: self.font_size,
+                font: self.font,
+                width: self.width,
+            }),
+            self.style.clone(),
+        );
+        self.editing = Some(Editing {
+            id,
+            added: true,
+            caret: 0,
+            anchor: 0,
+            preedit: None,
+            pointer: None,
+        });
+    }
+
+    /// Replace the `range` of the content edited with `text`, the caret goes after it.
+    fn replace(&mut self, editor: &mut Editor, range: Range<usize>, text: &str) {
+        let Some(editing) = &mut self.editing else {
+            return;
+        };
+        let Some(mut element) = editor.document().get(editing.id).cloned() else {
+            return;
+        };
+        let Some(content) = element.kind.content_mut() else {
+            return;
+        };
+        content.replace_range(range.clone(), text);
+        editor.update(element);
+        editing.move_to(range.start + text.len(), false);
+    }
+}
+
+/// Text held by the element `id`.
+fn text_box(editor: &Editor, id: ElementId) -> Option<TextBox<'_>> {
+    editor.document().get(id)?.kind.text_box()
+}
+
+/// The topmost element holding text at the board `position`,
+/// along with the byte offset of the caret closest to it.
+fn text_at(editor: &Editor, position: Point) -> Option<(ElementId, usize)> {
+    let element = editor
+        .document()
+        .elements_at(position, 0.0)
+        .find(|element| element.kind.text_box().is_some())?;
+    let offset = offset_at(editor, element.id(), position)?;
+    Some((element.id(), offset))
+}
+
+/// Byte offset of the caret of the text element `id` closest to the board `position`.
+fn offset_at(editor: &Editor, id: ElementId, position: Point) -> Option<usize> {
+    let element = editor.document().get(id)?;
+    let text = element.kind.text_box()?;
+    if element.transform.determinant().abs() < f64::EPSILON {
+        return None;
+    }
+    let local = element.transform.inverse() * position - text.origin;
+    Some(text.measure().offset_at(local.to_point()))
+}
+
+fn previous_boundary(content: &str, offset: usize) -> usize {
+    content[..offset]
+        .chars()
+        .next_back()
+        .map_or(0, |c| offset - c.len_utf8())
+}
+
+fn next_boundary(content: &str, offset: usize) -> usize {
+    content[offset..]
+        .chars()
+        .next()
+        .map_or(offset, |c| offset + c.len_utf8())
+}
+
+/// Paint the text items of `item` with the caret and selection of `editing`.
+fn set_editing(item: &mut SceneItem, editing: &TextEditing) {
+    match item {
+        SceneItem::Text {
+            editing: painted, ..
+        } => *painted = Some(editing.clone()),
+        SceneItem::Group { items, .. } => {
+            for item in items {
+                set_editing(item, editing);
+            }
+        }
+        SceneItem::Simplified { item, .. } => set_editing(item, editing),
+        SceneItem::Path { .. } | SceneItem::Image { .. } => {}
+    }
+}
@@ -1,6 +1,6 @@
 //! The `oxdraw` binary application.
 //! A virtual space for sketching hand drawing and whiteboards in rust
-use app::App;
+use app::{App, RecentFiles};
 use window::Windows;
 
 fn main() -> anyhow::Result<()> {
@@ -11,11 +11,11 @@ fn main() -> anyhow::Result<()> {
     }
 
     let wm = Windows::new()?;
-    let app = App::default();
+    let mut app = App::default();
+    app.set_recent_files(RecentFiles::from_config_dir());
 
-    app.render(())?;
     // launch the app with main window
-    wm.run()?;
+    wm.run(app)?;
     Ok(())
 }
 
@@ -0,0 +1,150 @@
+//! Paints a fixed demo scene into a window, useful to eyeball renderer changes.
+//!
+//! Run with `cargo run --package renderer --example demo`.
+
+use std::sync::Arc;
+
+use renderer::{
+    kurbo::{Affine, BezPath, Point, Rect, Shape as _, Size},
+    Camera, Color, Font, Frame, Renderer, Scene, SceneItem, StrokeStyle, Style,
+};
+use winit::{
+    dpi::PhysicalSize,
+    event::{Event, WindowEvent},
+    event_loop::EventLoopBuilder,
+    window::WindowBuilder,
+};
+
+fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoopBuilder::new().build();
+    let window = WindowBuilder::new()
+        .with_title("Renderer demo")
+        .with_inner_size(PhysicalSize::new(800, 600))
+        .build(&event_loop)?;
+
+    let mut renderer = Renderer::new()?;
+    let PhysicalSize { width, height } = window.inner_size();
+    let window_id = u64::from(window.id());
+    renderer.setup(window_id, width, height, &window)?;
+
+    event_loop.run(move |event, _target, control_flow| {
+        control_flow.set_wait();
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => control_flow.set_exit(),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                if let Err(err) = renderer.update(window_id, size.width, size.height) {
+                    log::error!("Unable to resize the demo window: {err}");
+                }
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
+                let PhysicalSize { width, height } = window.inner_size();
+                let scale_factor = window.scale_factor();
+                let frame = Frame {
+                    window_id,
+                    width,
+                    height,
+                    scale_factor,
+                    camera: Camera::default(),
+                };
+                let size = Size::new(
+                    f64::from(width) / scale_factor,
+                    f64::from(height) / scale_factor,
+                );
+                if let Err(err) = renderer.render(&frame, &demo_scene(size)) {
+                    log::error!("Unable to render the demo scene: {err}");
+                    control_flow.set_exit();
+                }
+            }
+            _ => {}
+        }
+    })
+}
+
+/// Two bezier curves, a translucent rectangle, some text and a generated gradient image.
+fn demo_scene(size: Size) -> Scene {
+    let mut scene = Scene::default();
+
+    let mut path = BezPath::new();
+    path.move_to((0.0, size.height));
+    path.quad_to((40.0, 50.0), (size.width, 0.0));
+    scene.push(SceneItem::Path {
+        path,
+        style: stroke_style(Color::rgb8(128, 0, 0), 15.0),
+    });
+
+    let mut path = BezPath::new();
+    path.move_to(Point::ORIGIN);
+    path.quad_to((40.0, 50.0), (size.width, size.height));
+    scene.push(SceneItem::Path {
+        path,
+        style: stroke_style(Color::rgb8(0, 128, 0), 5.0),
+    });
+
+    // Rotate the rectangle around its center to exercise group transforms
+    let rect = Rect::from_origin_size((400.0, 20.0), (100.0, 100.0));
+    let center = rect.center().to_vec2();
+    scene.push(SceneItem::Group {
+        transform: Affine::translate(center)
+            * Affine::rotate(std::f64::consts::FRAC_PI_8)
+            * Affine::translate(-center),
+        items: vec![SceneItem::Path {
+            path: rect.to_path(0.1),
+            style: Style {
+                stroke: None,
+                fill: Some(Color::rgba8(0x00, 0x00, 0x00, 0x7F)),
+            },
+        }],
+    });
+
+    scene.push(SceneItem::Text {
+        content: "Hello from Piet + Winit".to_owned(),
+        origin: Point::new(300.0, 200.0),
+        font_size: 24.0,
+        font: Font::default(),
+        max_width: None,
+        style: Style {
+            stroke: None,
+            fill: Some(Color::rgb8(128, 0, 0)),
+        },
+        editing: None,
+    });
+
+    scene.push(SceneItem::Image {
+        rect: size.to_rect(),
+        width: 256,
+        height: 256,
+        pixels: make_image_data(256, 256),
+        source: None,
+    });
+
+    scene
+}
+
+fn stroke_style(color: Color, width: f64) -> Style {
+    Style {
+        stroke: Some(StrokeStyle { color, width }),
+        fill: None,
+    }
+}
+
+fn make_image_data(width: usize, height: usize) -> Arc<[u8]> {
+    let mut result = vec![0; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let ix = (y * width + x) * 4;
+            result[ix] = x as u8;
+            result[ix + 1] = y as u8;
+            result[ix + 2] = !(x as u8);
+            result[ix + 3] = 127;
+        }
+    }
+    result.into()
+}
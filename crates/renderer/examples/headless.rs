@@ -0,0 +1,80 @@
+//! Paints a small scene offscreen and writes it as a PNG, no display is needed.
+//!
+//! Run with `cargo run --package renderer --example headless -- [output.png] [scale factor]`.
+
+use std::path::PathBuf;
+
+use renderer::{
+    kurbo::{BezPath, Point, Rect, Shape as _},
+    Camera, Color, Font, OffscreenFrame, Renderer, Scene, SceneItem, StrokeStyle, Style,
+};
+
+const WIDTH: f64 = 320.0;
+const HEIGHT: f64 = 240.0;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let output = args
+        .next()
+        .map_or_else(|| PathBuf::from("headless.png"), PathBuf::from);
+    let scale_factor = match args.next() {
+        Some(scale) => scale.parse()?,
+        None => 1.0,
+    };
+
+    let frame = OffscreenFrame {
+        width: (WIDTH * scale_factor).ceil() as u32,
+        height: (HEIGHT * scale_factor).ceil() as u32,
+        scale_factor,
+        camera: Camera::default(),
+    };
+    let mut renderer = Renderer::new()?;
+    let image = renderer.render_offscreen(&frame, &headless_scene())?;
+    image.save_png(&output)?;
+    println!(
+        "Wrote a {}x{} image to {}",
+        image.width(),
+        image.height(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// A curve, a filled rectangle and a line of text.
+fn headless_scene() -> Scene {
+    let mut scene = Scene::default();
+
+    let mut path = BezPath::new();
+    path.move_to((20.0, HEIGHT - 20.0));
+    path.quad_to((60.0, 20.0), (WIDTH - 20.0, 40.0));
+    scene.push(SceneItem::Path {
+        path,
+        style: Style {
+            stroke: Some(StrokeStyle {
+                color: Color::rgb8(0, 96, 160),
+                width: 6.0,
+            }),
+            fill: None,
+        },
+    });
+
+    scene.push(SceneItem::Path {
+        path: Rect::new(180.0, 120.0, 280.0, 200.0).to_path(0.1),
+        style: Style {
+            stroke: None,
+            fill: Some(Color::rgba8(0xE0, 0x60, 0x20, 0xB0)),
+        },
+    });
+
+    scene.push(SceneItem::Text {
+        content: "Rendered offscreen".to_owned(),
+        origin: Point::new(20.0, 20.0),
+        font_size: 18.0,
+        font: Font::default(),
+        max_width: None,
+        style: Style::default(),
+        editing: None,
+    });
+
+    scene
+}
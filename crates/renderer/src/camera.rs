@@ -0,0 +1,87 @@
+//! Viewpoint mapping the unbounded board onto a window.
+
+use piet_common::kurbo::{Affine, Point, Vec2};
+
+/// Smallest supported zoom, further out the board content shrinks to noise.
+pub const MIN_ZOOM: f64 = 0.05;
+/// Largest supported zoom, further in a single board unit covers most of the window.
+pub const MAX_ZOOM: f64 = 20.0;
+
+/// Converts between screen coordinates, logical pixels from the top left corner of the window,
+/// and world coordinates, the unbounded board space the document elements live in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// World position shown at the top left corner of the window.
+    offset: Vec2,
+    /// Number of screen logical pixels covered by one world unit.
+    zoom: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Constructor functions
+impl Camera {
+    /// Camera showing the world position `offset` at the top left corner of the window.
+    pub fn new(offset: Vec2, zoom: f64) -> Camera {
+        let zoom = if zoom.is_finite() {
+            zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+        } else {
+            1.0
+        };
+        Camera { offset, zoom }
+    }
+}
+
+/// Reference functions
+impl Camera {
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Affine transform mapping world coordinates to screen coordinates.
+    pub fn transform(&self) -> Affine {
+        Affine::scale(self.zoom) * Affine::translate(-self.offset)
+    }
+
+    pub fn to_world(&self, screen: Point) -> Point {
+        (screen.to_vec2() / self.zoom + self.offset).to_point()
+    }
+
+    pub fn to_screen(&self, world: Point) -> Point {
+        ((world.to_vec2() - self.offset) * self.zoom).to_point()
+    }
+}
+
+/// Mutable functions
+impl Camera {
+    /// Move the board along with a drag of `delta` screen logical pixels.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset -= delta / self.zoom;
+    }
+
+    /// Multiply the zoom by `factor` while keeping the world point under the `anchor` screen position in place.
+    pub fn zoom_at(&mut self, anchor: Point, factor: f64) {
+        self.set_zoom_at(anchor, self.zoom * factor);
+    }
+
+    /// Set the zoom while keeping the world point under the `anchor` screen position in place.
+    pub fn set_zoom_at(&mut self, anchor: Point, zoom: f64) {
+        if !zoom.is_finite() {
+            return;
+        }
+        let world_anchor = self.to_world(anchor);
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = world_anchor.to_vec2() - anchor.to_vec2() / self.zoom;
+    }
+}
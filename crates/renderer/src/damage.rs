@@ -0,0 +1,421 @@
+//! Areas of a frame which changed since the previous frame, so that only those are repainted.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use piet_common::kurbo::{Affine, PathEl, Point, Rect};
+
+use crate::{
+    camera::Camera,
+    scene::{Scene, SceneItem, Style},
+    Frame,
+};
+
+/// Physical pixels added around the items damaged, covering the antialiasing of their edges.
+const DAMAGE_MARGIN: f64 = 2.0;
+/// Damaged areas are merged into a single one beyond this count.
+const MAX_DAMAGED_AREAS: usize = 8;
+/// Part of the frame from which the whole frame is repainted at once rather than area by area.
+const FULL_REPAINT_RATIO: f64 = 0.5;
+
+/// What was painted into the pixel buffer of a window, compared with the next frame to find the damaged areas.
+#[derive(Clone, Debug)]
+pub(crate) struct PaintedFrame {
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    camera: Camera,
+    /// Area in physical pixels and fingerprint of each item of the scene painted, in paint order.
+    items: Vec<(Rect, u64)>,
+    background: u32,
+    /// Whether the board was composited from tiles beneath the items.
+    tiled: bool,
+    /// Areas of the board changed since the frame was painted, where the tiles are outdated.
+    invalidated: Vec<Rect>,
+}
+
+/// Constructor functions
+impl PaintedFrame {
+    pub(crate) fn new(frame: &Frame, scene: &Scene, tiled: bool) -> PaintedFrame {
+        let transform = screen_transform(frame.scale_factor, frame.camera);
+        PaintedFrame {
+            width: frame.width,
+            height: frame.height,
+            scale_factor: frame.scale_factor,
+            camera: frame.camera,
+            items: scene
+                .items()
+                .iter()
+                .filter_map(|item| {
+                    let area = damage_bounds(item)?;
+                    let area = transform
+                        .transform_rect_bbox(area)
+                        .inflate(DAMAGE_MARGIN, DAMAGE_MARGIN);
+                    Some((area, fingerprint(item)))
+                })
+                .collect(),
+            background: scene.background.as_rgba_u32(),
+            tiled,
+            invalidated: Vec::new(),
+        }
+    }
+}
+
+/// Reference functions
+impl PaintedFrame {
+    /// Areas in physical pixels of the `next` frame to repaint over this one, empty when nothing changed.
+    /// Items painted in both frames in the same order are left alone, the other ones are damaged
+    /// where they were and where they are now.
+    pub(crate) fn damaged_areas(&self, next: &PaintedFrame) -> Vec<Rect> {
+        let whole = Rect::new(0.0, 0.0, f64::from(next.width), f64::from(next.height));
+        let moved = self.width != next.width
+            || self.height != next.height
+            || self.scale_factor != next.scale_factor
+            || self.camera != next.camera
+            || self.background != next.background
+            || self.tiled != next.tiled;
+        if moved {
+            return vec![whole];
+        }
+
+        // Match the items of both frames by fingerprint, keeping the paint order
+        let mut previous: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, (_, fingerprint)) in self.items.iter().enumerate().rev() {
+            previous.entry(*fingerprint).or_default().push(index);
+        }
+        let mut kept = vec![false; self.items.len()];
+        let mut last = None;
+        let mut damaged = Vec::new();
+        for (area, fingerprint) in &next.items {
+            let matched = previous.get_mut(fingerprint).and_then(|indices| {
+                while let Some(index) = indices.pop() {
+                    if last.map_or(true, |last| index > last) {
+                        return Some(index);
+                    }
+                }
+                None
+            });
+            match matched {
+                Some(index) => {
+                    kept[index] = true;
+                    last = Some(index);
+                }
+                None => damaged.push(*area),
+            }
+        }
+        let transform = screen_transform(next.scale_factor, next.camera);
+        damaged.extend(self.invalidated.iter().map(|area| {
+            transform
+                .transform_rect_bbox(*area)
+                .inflate(DAMAGE_MARGIN, DAMAGE_MARGIN)
+        }));
+        damaged.extend(
+            self.items
+                .iter()
+                .zip(kept)
+                .filter(|(_, kept)| !kept)
+                .map(|((area, _), _)| *area),
+        );
+
+        let areas = merge_areas(
+            damaged
+                .into_iter()
+                .map(|area| area.intersect(whole).expand())
+                .filter(|area| area.area() > 0.0),
+        );
+        let damaged_area: f64 = areas.iter().map(Rect::area).sum();
+        if damaged_area > whole.area() * FULL_REPAINT_RATIO {
+            vec![whole]
+        } else {
+            areas
+        }
+    }
+}
+
+/// Mutable functions
+impl PaintedFrame {
+    /// Record that the board `area` changed, to be repainted with the next frame.
+    pub(crate) fn invalidate(&mut self, area: Rect) {
+        self.invalidated.push(area);
+    }
+}
+
+/// Transform mapping the board coordinates to the frame physical pixels.
+fn screen_transform(scale_factor: f64, camera: Camera) -> Affine {
+    Affine::scale(scale_factor) * camera.transform()
+}
+
+/// Merge the overlapping areas, down to a single area when there are too many of them.
+fn merge_areas(areas: impl Iterator<Item = Rect>) -> Vec<Rect> {
+    let mut merged: Vec<Rect> = Vec::new();
+    for mut area in areas {
+        while let Some(index) = merged
+            .iter()
+            .position(|other| other.intersect(area).area() > 0.0)
+        {
+            area = area.union(merged.swap_remove(index));
+        }
+        merged.push(area);
+    }
+    if merged.len() > MAX_DAMAGED_AREAS {
+        let union = merged
+            .iter()
+            .copied()
+            .reduce(|union, area| union.union(area));
+        merged = union.into_iter().collect();
+    }
+    merged
+}
+
+/// Area the item may paint over, wider than its bounding box where joins and glyphs reach past it.
+fn damage_bounds(item: &SceneItem) -> Option<Rect> {
+    match item {
+        SceneItem::Path { style, .. } => {
+            // Joins stick out further than half the stroke width at sharp corners
+            let width = style.stroke.as_ref().map_or(0.0, |stroke| stroke.width);
+            Some(item.bounding_box()?.inflate(width, width))
+        }
+        SceneItem::Text { font_size, .. } => {
+            // Glyphs may reach past their advance, and the caret past the end of the line
+            let margin = font_size / 2.0;
+            Some(item.bounding_box()?.inflate(margin, margin))
+        }
+        SceneItem::Image { .. } => item.bounding_box(),
+        SceneItem::Group { transform, items } => items
+            .iter()
+            .filter_map(damage_bounds)
+            .map(|area| transform.transform_rect_bbox(area))
+            .reduce(|union, area| union.union(area)),
+        SceneItem::Simplified { item, .. } => damage_bounds(item),
+    }
+}
+
+/// Hash of everything affecting how the item is painted, images are told apart by their pixel buffer.
+fn fingerprint(item: &SceneItem) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_item(item, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_item(item: &SceneItem, hasher: &mut DefaultHasher) {
+    match item {
+        SceneItem::Path { path, style } => {
+            0_u8.hash(hasher);
+            for element in path.elements() {
+                match *element {
+                    PathEl::MoveTo(p) => hash_points(hasher, 0, &[p]),
+                    PathEl::LineTo(p) => hash_points(hasher, 1, &[p]),
+                    PathEl::QuadTo(p1, p2) => hash_points(hasher, 2, &[p1, p2]),
+                    PathEl::CurveTo(p1, p2, p3) => hash_points(hasher, 3, &[p1, p2, p3]),
+                    PathEl::ClosePath => 4_u8.hash(hasher),
+                }
+            }
+            hash_style(style, hasher);
+        }
+        SceneItem::Text {
+            content,
+            origin,
+            font_size,
+            font,
+            max_width,
+            style,
+            editing,
+        } => {
+            1_u8.hash(hasher);
+            content.hash(hasher);
+            hash_points(hasher, 0, &[*origin]);
+            font_size.to_bits().hash(hasher);
+            font.generic_name().hash(hasher);
+            max_width.map(f64::to_bits).hash(hasher);
+            hash_style(style, hasher);
+            if let Some(editing) = editing {
+                editing.caret.hash(hasher);
+                editing.selection.hash(hasher);
+                editing.composition.hash(hasher);
+            }
+        }
+        SceneItem::Image {
+            rect,
+            width,
+            height,
+            pixels,
+            source,
+        } => {
+            2_u8.hash(hasher);
+            hash_rect(hasher, *rect);
+            width.hash(hasher);
+            height.hash(hasher);
+            Arc::as_ptr(pixels).hash(hasher);
+            if let Some(source) = source {
+                hash_rect(hasher, *source);
+            }
+        }
+        SceneItem::Group { transform, items } => {
+            3_u8.hash(hasher);
+            for coeff in transform.as_coeffs() {
+                coeff.to_bits().hash(hasher);
+            }
+            items.len().hash(hasher);
+            for item in items {
+                hash_item(item, hasher);
+            }
+        }
+        SceneItem::Simplified { item, levels } => {
+            4_u8.hash(hasher);
+            hash_item(item, hasher);
+            for (max_scale, level) in levels {
+                max_scale.to_bits().hash(hasher);
+                hash_item(level, hasher);
+            }
+        }
+    }
+}
+
+fn hash_style(style: &Style, hasher: &mut DefaultHasher) {
+    if let Some(stroke) = &style.stroke {
+        stroke.color.as_rgba_u32().hash(hasher);
+        stroke.width.to_bits().hash(hasher);
+    }
+    style.fill.map(|fill| fill.as_rgba_u32()).hash(hasher);
+}
+
+fn hash_points(hasher: &mut DefaultHasher, tag: u8, points: &[Point]) {
+    tag.hash(hasher);
+    for point in points {
+        point.x.to_bits().hash(hasher);
+        point.y.to_bits().hash(hasher);
+    }
+}
+
+fn hash_rect(hasher: &mut DefaultHasher, rect: Rect) {
+    for value in [rect.x0, rect.y0, rect.x1, rect.y1] {
+        value.to_bits().hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use piet_common::{
+        kurbo::{Shape as _, Vec2},
+        Color,
+    };
+
+    use super::*;
+
+    fn frame(camera: Camera) -> Frame {
+        Frame {
+            window_id: 0,
+            width: 1000,
+            height: 1000,
+            scale_factor: 1.0,
+            camera,
+        }
+    }
+
+    fn square(x: f64) -> SceneItem {
+        SceneItem::Path {
+            path: Rect::new(x, 0.0, x + 10.0, 10.0).to_path(0.1),
+            style: Style {
+                stroke: None,
+                fill: Some(Color::BLACK),
+            },
+        }
+    }
+
+    fn scene(items: Vec<SceneItem>) -> Scene {
+        let mut scene = Scene::default();
+        for item in items {
+            scene.push(item);
+        }
+        scene
+    }
+
+    fn painted(items: Vec<SceneItem>) -> PaintedFrame {
+        PaintedFrame::new(&frame(Camera::default()), &scene(items), false)
+    }
+
+    #[test]
+    fn overlapping_areas_merge() {
+        let areas = merge_areas(
+            [
+                Rect::new(0.0, 0.0, 10.0, 10.0),
+                Rect::new(50.0, 50.0, 60.0, 60.0),
+                Rect::new(5.0, 5.0, 20.0, 20.0),
+                // Overlaps the union of the first and third areas only
+                Rect::new(15.0, 0.0, 25.0, 4.0),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(areas.len(), 2);
+        assert!(areas.contains(&Rect::new(0.0, 0.0, 25.0, 20.0)));
+        assert!(areas.contains(&Rect::new(50.0, 50.0, 60.0, 60.0)));
+    }
+
+    #[test]
+    fn touching_areas_stay_apart() {
+        let areas = merge_areas(
+            [
+                Rect::new(0.0, 0.0, 10.0, 10.0),
+                Rect::new(10.0, 0.0, 20.0, 10.0),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(areas.len(), 2);
+    }
+
+    #[test]
+    fn too_many_areas_merge_into_one() {
+        let areas = merge_areas((0..=MAX_DAMAGED_AREAS).map(|index| {
+            let x = index as f64 * 20.0;
+            Rect::new(x, 0.0, x + 10.0, 10.0)
+        }));
+        let last = MAX_DAMAGED_AREAS as f64 * 20.0;
+        assert_eq!(areas, vec![Rect::new(0.0, 0.0, last + 10.0, 10.0)]);
+    }
+
+    #[test]
+    fn unchanged_frame_has_no_damage() {
+        let previous = painted(vec![square(0.0), square(100.0)]);
+        let next = painted(vec![square(0.0), square(100.0)]);
+        assert!(previous.damaged_areas(&next).is_empty());
+    }
+
+    #[test]
+    fn moved_item_is_damaged_where_it_was_and_is() {
+        let previous = painted(vec![square(0.0), square(100.0)]);
+        let next = painted(vec![square(0.0), square(200.0)]);
+        let areas = previous.damaged_areas(&next);
+        assert_eq!(areas.len(), 2);
+        assert!(areas
+            .iter()
+            .any(|area| area.contains(Point::new(105.0, 5.0))));
+        assert!(areas
+            .iter()
+            .any(|area| area.contains(Point::new(205.0, 5.0))));
+        assert!(!areas.iter().any(|area| area.contains(Point::new(5.0, 5.0))));
+    }
+
+    #[test]
+    fn invalidated_area_is_damaged() {
+        let mut previous = painted(vec![square(0.0)]);
+        previous.invalidate(Rect::new(300.0, 300.0, 310.0, 310.0));
+        let areas = previous.damaged_areas(&painted(vec![square(0.0)]));
+        assert_eq!(areas.len(), 1);
+        assert!(areas[0].contains(Point::new(305.0, 305.0)));
+    }
+
+    #[test]
+    fn camera_change_repaints_the_whole_frame() {
+        let previous = painted(vec![square(0.0)]);
+        let mut camera = Camera::default();
+        camera.pan(Vec2::new(10.0, 0.0));
+        let next = PaintedFrame::new(&frame(camera), &scene(vec![square(0.0)]), false);
+        assert_eq!(
+            previous.damaged_areas(&next),
+            vec![Rect::new(0.0, 0.0, 1000.0, 1000.0)]
+        );
+    }
+}
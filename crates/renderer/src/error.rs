@@ -8,4 +8,14 @@ pub enum RendererError {
     MissingPixelBuffer(u64),
     #[error("Failed to render")]
     FailedRender(#[from] pixels::Error),
+    #[error("Unable to paint into a bitmap: {0}")]
+    Bitmap(String),
+    #[error("Image of {0}x{1} pixels is too large")]
+    ImageSize(u32, u32),
+    #[error("Pixel buffer of window id: {window_id} holds {len} bytes instead of the {expected} bytes of the frame")]
+    BufferSize {
+        window_id: u64,
+        len: usize,
+        expected: usize,
+    },
 }
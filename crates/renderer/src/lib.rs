@@ -1,21 +1,55 @@
 //! Graphics API renderer for app
 
+mod camera;
+mod damage;
 mod error;
+mod offscreen;
+mod scene;
+mod svg;
+mod text;
+mod tile;
+mod worker;
 
 use std::collections::HashMap;
 
 use anyhow::bail;
 use piet_common::{
-    kurbo::{BezPath, Point, Rect, Size},
-    BitmapTarget, Color, Device, FontFamily, ImageFormat, InterpolationMode, RenderContext, Text,
-    TextLayoutBuilder,
+    kurbo::{Affine, Rect},
+    BitmapTarget, Device, ImageFormat, Piet, RenderContext as _,
 };
 use pixels::{
     raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle},
     Pixels, SurfaceTexture,
 };
 
-use crate::error::RendererError;
+use crate::{
+    damage::PaintedFrame,
+    error::RendererError,
+    offscreen::image_len,
+    scene::{paint_group, paint_scene},
+    tile::{paint_tiles, TileCache},
+};
+
+pub use camera::{Camera, MAX_ZOOM, MIN_ZOOM};
+pub use offscreen::{OffscreenFrame, RenderedImage};
+pub use piet_common::{kurbo, Color};
+pub use scene::{Scene, SceneItem, StrokeStyle, Style};
+pub use svg::{scene_to_svg, SvgOptions};
+pub use text::{Font, MeasuredText, TextEditing};
+pub use tile::{Board, TileContent, TILE_SIZE};
+
+/// Describes the window surface a frame is painted into.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub window_id: u64,
+    /// Width of the surface in physical pixels.
+    pub width: u32,
+    /// Height of the surface in physical pixels.
+    pub height: u32,
+    pub scale_factor: f64,
+    /// Viewpoint over the board the scene is painted from.
+    pub camera: Camera,
+}
 
 /// The Renderer struct holds a Device and an optional Pixels buffer.
 /// The Device is used to interface with the graphics API (for example `OpenGL`) and perform rendering operations.
@@ -25,6 +59,11 @@ pub struct Renderer {
     device: Device,
     /// A Pixels buffer represents a block of pixel data in memory that can be uploaded to the GPU. Before rendering a frame, a new Pixels buffer is created to hold the pixel data for that frame. Then, the Pixels buffer is passed to the Device to be rendered.
     pixel_buffers: HashMap<u64, Pixels>,
+    /// What the pixel buffer of each window holds, only the areas changed since are painted again.
+    painted: HashMap<u64, PaintedFrame>,
+    /// Tiles of the board shared by the windows painted with [`Renderer::render_tiled`],
+    /// rasterized by worker threads each holding a device of its own.
+    tiles: TileCache,
 }
 
 /// Constructor functions
@@ -36,6 +75,8 @@ impl Renderer {
             Ok(device) => Ok(Renderer {
                 device,
                 pixel_buffers: HashMap::new(),
+                painted: HashMap::new(),
+                tiles: TileCache::new(),
             }),
             Err(err) => bail!(RendererError::RendererDevice(err.to_string())),
         }
@@ -67,36 +108,155 @@ impl Renderer {
 
         pixel.resize_surface(width, height)?;
         pixel.resize_buffer(width, height)?;
+        self.painted.remove(&window_id);
 
         Ok(())
     }
 
     pub fn remove(&mut self, window_id: u64) -> anyhow::Result<()> {
         self.pixel_buffers.remove(&window_id);
+        self.painted.remove(&window_id);
         Ok(())
     }
 
-    pub fn render(
+    /// Paint the scene into the pixel buffer of the frame window and present it.
+    /// Only the areas where the scene changed since the previous frame are painted again,
+    /// the whole frame is when the window, the camera or the background changed.
+    pub fn render(&mut self, frame: &Frame, scene: &Scene) -> anyhow::Result<()> {
+        self.present(frame, None, scene)
+    }
+
+    /// Paint the board from tiles cached across frames, then `overlay` on top of it with its background beneath.
+    /// The tiles missing are rasterized in the background from the content `board` gives for their area,
+    /// the tile workers are started by the first frame painted from tiles.
+    pub fn render_tiled(
         &mut self,
-        window_id: u64,
-        width: u32,
-        height: u32,
-        scale_factor: f64,
+        frame: &Frame,
+        board: &dyn Board,
+        overlay: &Scene,
     ) -> anyhow::Result<()> {
-        let pixel = match self.pixel_buffers.get_mut(&window_id) {
-            None => bail!(RendererError::MissingPixelBuffer(window_id)),
-            Some(p) => p,
-        };
-        let mut bitmap = self
-            .device
-            .bitmap_target(width as usize, height as usize, scale_factor)
-            .unwrap();
+        self.present(frame, Some(board), overlay)
+    }
+
+    /// Whether tiles of the board are being rasterized in the background, the frames painted meanwhile
+    /// show placeholders for them and are to be rendered again until this returns `false`.
+    pub fn rasterizing(&self) -> bool {
+        self.tiles.rasterizing()
+    }
+
+    /// Drop the cached tiles overlapping the board `area` after its content changed,
+    /// they are rasterized again when next painted.
+    pub fn invalidate(&mut self, area: Rect) {
+        self.tiles.invalidate(area);
+        for painted in self.painted.values_mut() {
+            painted.invalidate(area);
+        }
+    }
 
-        draw_canvas(&mut bitmap, width, height);
+    /// Drop every cached tile, when the whole board changed.
+    pub fn invalidate_all(&mut self) {
+        self.tiles.clear();
+        self.painted.clear();
+    }
+
+    /// Paint the scene into an image held in memory, no window is needed.
+    pub fn render_offscreen(
+        &mut self,
+        frame: &OffscreenFrame,
+        scene: &Scene,
+    ) -> anyhow::Result<RenderedImage> {
+        let OffscreenFrame {
+            width,
+            height,
+            scale_factor,
+            camera,
+        } = *frame;
+        let mut pixels = vec![0; image_len(width, height)?];
+        let mut bitmap =
+            match self
+                .device
+                .bitmap_target(width as usize, height as usize, scale_factor)
+            {
+                Ok(bitmap) => bitmap,
+                Err(err) => bail!(RendererError::Bitmap(err.to_string())),
+            };
+
+        draw_scene(&mut bitmap, scene, &camera);
+        if let Err(err) = bitmap.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels) {
+            bail!(RendererError::Bitmap(err.to_string()))
+        }
+        Ok(RenderedImage::from_premultiplied(width, height, pixels))
+    }
+}
+
+/// Private functions
+impl Renderer {
+    /// Paint the areas of the frame damaged since the previous one into the pixel buffer of its window, and present it.
+    fn present(
+        &mut self,
+        frame: &Frame,
+        board: Option<&dyn Board>,
+        scene: &Scene,
+    ) -> anyhow::Result<()> {
+        let Frame {
+            window_id,
+            width,
+            height,
+            ..
+        } = *frame;
+        // The tiles rasterized since the last frame replace the placeholders painted in their area
+        for area in self.tiles.collect() {
+            for painted in self.painted.values_mut() {
+                painted.invalidate(area);
+            }
+        }
+        let Some(pixel) = self.pixel_buffers.get_mut(&window_id) else {
+            bail!(RendererError::MissingPixelBuffer(window_id));
+        };
+        let painted = PaintedFrame::new(frame, scene, board.is_some());
         let buffer = pixel.frame_mut();
-        bitmap
-            .copy_raw_pixels(piet_common::ImageFormat::RgbaPremul, buffer)
-            .ok();
+        let expected = image_len(width, height)?;
+        if buffer.len() != expected {
+            // The buffer is resized along with the window surface, the frame is painted once it is
+            self.painted.remove(&window_id);
+            bail!(RendererError::BufferSize {
+                window_id,
+                len: buffer.len(),
+                expected,
+            });
+        }
+        let areas = match self.painted.get(&window_id) {
+            Some(previous) => previous.damaged_areas(&painted),
+            None => vec![Rect::new(0.0, 0.0, f64::from(width), f64::from(height))],
+        };
+
+        let mut result = Ok(());
+        for area in areas {
+            let tiles = match board {
+                Some(board) => self.tiles.tiles(&mut self.device, frame, area, board),
+                None => Ok(Vec::new()),
+            };
+            result = tiles.and_then(|tiles| {
+                paint_area(&mut self.device, buffer, frame, area, |ctx| {
+                    ctx.clear(None, scene.background);
+                    paint_tiles(ctx, frame, &tiles);
+                    paint_group(ctx, frame.camera.transform(), scene.items());
+                })
+            });
+            if result.is_err() {
+                break;
+            }
+        }
+        match result {
+            Ok(()) => {
+                self.painted.insert(window_id, painted);
+            }
+            Err(err) => {
+                // Paint everything again next time as the buffer holds part of this frame
+                log::warn!("Unable to paint the frame: {err}");
+                self.painted.remove(&window_id);
+            }
+        }
 
         if let Err(err) = pixel.render() {
             bail!(RendererError::FailedRender(err))
@@ -105,70 +265,54 @@ impl Renderer {
     }
 }
 
-fn draw_canvas(bitmap: &mut BitmapTarget<'_>, width: u32, height: u32) {
-    let mut ctx = bitmap.render_context();
-
-    let data = "Hello from Piet + Winit";
-    let size = Size::new(width as f64, height as f64);
-    let rect = size.to_rect();
-    ctx.fill(rect, &Color::WHITE);
-
-    let mut path = BezPath::new();
-    path.move_to((0.0, size.height));
-    path.quad_to((40.0, 50.0), (size.width, 0.0));
-
-    // Create a color
-    let stroke_color = Color::rgb8(128, 0, 0);
-    // Stroke the path with thickness 1.0
-    ctx.stroke(path, &stroke_color, 15.0);
-
-    // Create an arbitrary bezier path
-    let mut path = BezPath::new();
-    path.move_to(Point::ORIGIN);
-    path.quad_to((40.0, 50.0), (size.width, size.height));
-    // Create a color
-    let stroke_color = Color::rgb8(0, 128, 0);
-    // Stroke the path with thickness 5.0
-    ctx.stroke(path, &stroke_color, 5.0);
-
-    // Rectangles: the path for practical people
-    let rect = Rect::from_origin_size((400.0, 20.0), (100.0, 100.0));
-    // Note the Color:rgba8 which includes an alpha channel (7F in this case)
-    let fill_color = Color::rgba8(0x00, 0x00, 0x00, 0x7F);
-    ctx.fill(rect, &fill_color);
-
-    // draw test
-    let text = ctx.text();
-    let layout = text
-        .new_text_layout(data)
-        .font(FontFamily::SANS_SERIF, 24.0)
-        .max_width(400.0)
-        .text_color(Color::rgb8(128, 0, 0))
-        .build()
-        .unwrap();
-    ctx.draw_text(&layout, (300.0, 200.0));
-
-    // Let's burn some CPU to make a (partially transparent) image buffer
-    let image_data = make_image_data(256, 256);
-    let image = ctx
-        .make_image(256, 256, &image_data, ImageFormat::RgbaSeparate)
-        .unwrap();
-    // The image is automatically scaled to fit the rect you pass to draw_image
-    ctx.draw_image(&image, size.to_rect(), InterpolationMode::Bilinear);
+/// Paint the `area` of the frame, in physical pixels, over the same area of `buffer` holding the whole frame.
+/// `paint` is given a render context with the frame logical coordinates.
+/// Fails with [`piet_common::Error::InvalidInput`] when the area reaches past the end of `buffer`.
+fn paint_area(
+    device: &mut Device,
+    buffer: &mut [u8],
+    frame: &Frame,
+    area: Rect,
+    paint: impl FnOnce(&mut Piet<'_>),
+) -> Result<(), piet_common::Error> {
+    let (x, y) = (area.x0 as usize, area.y0 as usize);
+    let (width, height) = (area.width() as usize, area.height() as usize);
+    let mut bitmap = device.bitmap_target(width, height, frame.scale_factor)?;
+    {
+        let mut ctx = bitmap.render_context();
+        let offset =
+            Affine::translate((-area.x0 / frame.scale_factor, -area.y0 / frame.scale_factor));
+        ctx.transform(offset);
+        paint(&mut ctx);
+        ctx.finish()?;
+    }
 
-    ctx.finish().ok();
+    let bytes_per_pixel = ImageFormat::RgbaPremul.bytes_per_pixel();
+    let row_len = width * bytes_per_pixel;
+    let stride = frame.width as usize * bytes_per_pixel;
+    if row_len == stride {
+        // Whole rows are contiguous in the buffer
+        let start = y * stride;
+        let Some(rows) = buffer.get_mut(start..start + height * stride) else {
+            return Err(piet_common::Error::InvalidInput);
+        };
+        bitmap.copy_raw_pixels(ImageFormat::RgbaPremul, rows)?;
+        return Ok(());
+    }
+    let mut pixels = vec![0; height * row_len];
+    bitmap.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)?;
+    for (row, pixels) in pixels.chunks_exact(row_len).enumerate() {
+        let start = (y + row) * stride + x * bytes_per_pixel;
+        let Some(destination) = buffer.get_mut(start..start + row_len) else {
+            return Err(piet_common::Error::InvalidInput);
+        };
+        destination.copy_from_slice(pixels);
+    }
+    Ok(())
 }
 
-fn make_image_data(width: usize, height: usize) -> Vec<u8> {
-    let mut result = vec![0; width * height * 4];
-    for y in 0..height {
-        for x in 0..width {
-            let ix = (y * width + x) * 4;
-            result[ix] = x as u8;
-            result[ix + 1] = y as u8;
-            result[ix + 2] = !(x as u8);
-            result[ix + 3] = 127;
-        }
-    }
-    result
+fn draw_scene(bitmap: &mut BitmapTarget<'_>, scene: &Scene, camera: &Camera) {
+    let mut ctx = bitmap.render_context();
+    paint_scene(&mut ctx, scene, camera.transform());
+    ctx.finish().ok();
 }
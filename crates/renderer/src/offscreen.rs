@@ -0,0 +1,115 @@
+//! Rendering into memory, without any window or display.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::bail;
+use piet_common::ImageFormat;
+
+use crate::{camera::Camera, error::RendererError};
+
+/// Describes an image painted offscreen.
+#[derive(Clone, Copy, Debug)]
+pub struct OffscreenFrame {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// Number of image pixels covered by one logical pixel, `2.0` paints the scene at twice the size.
+    pub scale_factor: f64,
+    /// Viewpoint over the board the scene is painted from.
+    pub camera: Camera,
+}
+
+/// A painted image, RGBA pixels with separate alpha row by row.
+#[derive(Clone, Debug)]
+pub struct RenderedImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Constructor functions
+impl RenderedImage {
+    /// Image from RGBA pixels with premultiplied alpha, as painted by the bitmap target.
+    pub(crate) fn from_premultiplied(width: u32, height: u32, mut pixels: Vec<u8>) -> Self {
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3];
+            if alpha != 0 && alpha != u8::MAX {
+                for channel in &mut pixel[..3] {
+                    let value = u32::from(*channel) * 255 / u32::from(alpha);
+                    *channel = u8::try_from(value).unwrap_or(u8::MAX);
+                }
+            }
+        }
+        RenderedImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Reference functions
+impl RenderedImage {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// RGBA pixels with separate alpha, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Encode the image as PNG.
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut png = Vec::new();
+        encode_png(&mut png, self.width, self.height, &self.pixels)?;
+        Ok(png)
+    }
+
+    /// Encode the image as PNG into the file at `path`.
+    pub fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        encode_png(&mut writer, self.width, self.height, &self.pixels)?;
+        // Dropping the writer would flush it silently, failing to write the end of the file
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Encode RGBA pixels with separate alpha as PNG.
+pub(crate) fn encode_png<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Number of bytes of a RGBA image, fails for images too large to fit in memory.
+pub(crate) fn image_len(width: u32, height: u32) -> anyhow::Result<usize> {
+    let len = usize::try_from(width)
+        .ok()
+        .zip(usize::try_from(height).ok())
+        .and_then(|(width, height)| width.checked_mul(height))
+        .and_then(|len| len.checked_mul(ImageFormat::RgbaPremul.bytes_per_pixel()));
+    match len {
+        Some(len) => Ok(len),
+        None => bail!(RendererError::ImageSize(width, height)),
+    }
+}
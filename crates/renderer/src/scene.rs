@@ -0,0 +1,337 @@
+//! Description of the content painted into a frame.
+
+use std::sync::Arc;
+
+use piet_common::{
+    kurbo::{Affine, BezPath, Line, Point, Rect, Shape as _},
+    Color, ImageFormat, InterpolationMode, LineCap, LineJoin, RenderContext, TextLayout,
+    TextLayoutBuilder,
+};
+
+use crate::text::{layout_builder, text_size, Font, MeasuredText, TextEditing};
+
+/// Highlight behind the selected text.
+const SELECTION_COLOR: Color = Color::rgba8(0x33, 0x99, 0xff, 0x55);
+/// Width of the caret and of the composition underline, relative to the font size.
+const CARET_WIDTH_PER_SIZE: f64 = 0.06;
+/// Side in pixels below which paths and text are painted as a box of their color, their details would not show.
+const PLACEHOLDER_SIZE: f64 = 2.0;
+
+/// Hand drawn lines look best with rounded ends and corners.
+const ROUND_STROKE: piet_common::StrokeStyle = piet_common::StrokeStyle::new()
+    .line_join(LineJoin::Round)
+    .line_cap(LineCap::Round);
+
+/// Stroke attributes applied to the outline of a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub color: Color,
+    pub width: f64,
+}
+
+/// Paint attributes shared by every drawable item.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    /// Outline of the item, nothing is stroked when `None`.
+    pub stroke: Option<StrokeStyle>,
+    /// Fill color of the item, nothing is filled when `None`. Text uses this as its color.
+    pub fill: Option<Color>,
+}
+
+/// A single drawable item of a [`Scene`].
+#[derive(Clone, Debug)]
+pub enum SceneItem {
+    /// An arbitrary path painted with the given style.
+    Path { path: BezPath, style: Style },
+    /// A block of text with its top left corner at `origin`, wrapped to `max_width` when set.
+    Text {
+        content: String,
+        origin: Point,
+        font_size: f64,
+        font: Font,
+        max_width: Option<f64>,
+        style: Style,
+        /// Caret and selection painted along with the text while it is edited.
+        editing: Option<TextEditing>,
+    },
+    /// A RGBA image with separate alpha, scaled to fit `rect`.
+    Image {
+        rect: Rect,
+        width: usize,
+        height: usize,
+        pixels: Arc<[u8]>,
+        /// Area of the image shown in pixels, the whole image when `None`.
+        source: Option<Rect>,
+    },
+    /// Items painted with `transform` applied on top of the parent transform.
+    Group {
+        transform: Affine,
+        items: Vec<SceneItem>,
+    },
+    /// An item along with simpler versions of it, one of which is painted in its place when zoomed out.
+    Simplified {
+        item: Box<SceneItem>,
+        /// Simpler versions from the finest to the coarsest, each with the largest number of pixels
+        /// per unit it is painted at. The coarsest version fit for the frame scale is painted.
+        levels: Vec<(f64, SceneItem)>,
+    },
+}
+
+/// An ordered list of items painted back to front.
+#[derive(Clone, Debug)]
+pub struct Scene {
+    /// Color used to clear the frame before painting the items.
+    pub background: Color,
+    items: Vec<SceneItem>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            background: Color::WHITE,
+            items: Vec::new(),
+        }
+    }
+}
+
+/// Reference functions
+impl SceneItem {
+    /// Area covered by the item including its outline, `None` for an empty group.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        match self {
+            SceneItem::Path { path, style } => {
+                let half_width = style
+                    .stroke
+                    .as_ref()
+                    .map_or(0.0, |stroke| stroke.width / 2.0);
+                Some(path.bounding_box().inflate(half_width, half_width))
+            }
+            SceneItem::Text {
+                content,
+                origin,
+                font_size,
+                font,
+                max_width,
+                ..
+            } => {
+                let mut size = MeasuredText::new(content, *font, *font_size, *max_width).size();
+                if let Some(max_width) = max_width {
+                    size.width = size.width.max(*max_width);
+                }
+                Some(Rect::from_origin_size(*origin, size))
+            }
+            SceneItem::Image { rect, .. } => Some(*rect),
+            SceneItem::Group { transform, items } => items
+                .iter()
+                .filter_map(SceneItem::bounding_box)
+                .map(|rect| transform.transform_rect_bbox(rect))
+                .reduce(|union, rect| union.union(rect)),
+            SceneItem::Simplified { item, .. } => item.bounding_box(),
+        }
+    }
+}
+
+impl Scene {
+    pub fn push(&mut self, item: SceneItem) {
+        self.items.push(item);
+    }
+
+    pub fn items(&self) -> &[SceneItem] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Area covered by every item, `None` when nothing is painted.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        self.items
+            .iter()
+            .filter_map(SceneItem::bounding_box)
+            .reduce(|union, rect| union.union(rect))
+    }
+}
+
+/// Clear the render context with the scene background then paint every item, in order.
+/// `view` maps the scene coordinates to the frame logical coordinates.
+pub(crate) fn paint_scene(ctx: &mut impl RenderContext, scene: &Scene, view: Affine) {
+    ctx.clear(None, scene.background);
+    paint_group(ctx, view, scene.items());
+}
+
+/// Paint the items with `transform` applied on top of the context transform.
+pub(crate) fn paint_group(ctx: &mut impl RenderContext, transform: Affine, items: &[SceneItem]) {
+    let painted = ctx.with_save(|ctx| {
+        ctx.transform(transform);
+        for item in items {
+            paint_item(ctx, item);
+        }
+        Ok(())
+    });
+    if let Err(err) = painted {
+        log::warn!("Unable to restore the render context state: {err}");
+    }
+}
+
+/// Underline the text being composed and paint the caret of an edited text layout.
+fn paint_editing(
+    ctx: &mut impl RenderContext,
+    layout: &impl TextLayout,
+    origin: Point,
+    font_size: f64,
+    color: Color,
+    editing: &TextEditing,
+) {
+    let offset = origin.to_vec2();
+    let width = font_size * CARET_WIDTH_PER_SIZE;
+    if let Some(composition) = &editing.composition {
+        for rect in layout.rects_for_range(composition.clone()) {
+            let underline = Line::new((rect.x0, rect.y1), (rect.x1, rect.y1));
+            ctx.stroke(underline + offset, &color, width);
+        }
+    }
+    let position = layout.hit_test_text_position(editing.caret);
+    if let Some(line) = layout.line_metric(position.line) {
+        let x = position.point.x;
+        let caret = Line::new((x, line.y_offset), (x, line.y_offset + line.height));
+        ctx.stroke(caret + offset, &color, width);
+    }
+}
+
+fn paint_item(ctx: &mut impl RenderContext, item: &SceneItem) {
+    if let Some((area, color)) = placeholder(ctx.current_transform(), item) {
+        ctx.fill(area, &color);
+        return;
+    }
+    match item {
+        SceneItem::Path { path, style } => {
+            if let Some(fill) = &style.fill {
+                ctx.fill(path, fill);
+            }
+            if let Some(stroke) = &style.stroke {
+                ctx.stroke_styled(path, &stroke.color, stroke.width, &ROUND_STROKE);
+            }
+        }
+        SceneItem::Text {
+            content,
+            origin,
+            font_size,
+            font,
+            max_width,
+            style,
+            editing,
+        } => {
+            let color = style.fill.unwrap_or(Color::BLACK);
+            let builder = layout_builder(ctx.text(), content, *font, *font_size, *max_width);
+            let layout = match builder.text_color(color).build() {
+                Ok(layout) => layout,
+                Err(err) => {
+                    log::warn!("Unable to layout text {content:?}: {err}");
+                    return;
+                }
+            };
+            let offset = origin.to_vec2();
+            if let Some(editing) = editing {
+                for rect in layout.rects_for_range(editing.selection.clone()) {
+                    ctx.fill(rect + offset, &SELECTION_COLOR);
+                }
+            }
+            ctx.draw_text(&layout, *origin);
+            if let Some(editing) = editing {
+                paint_editing(ctx, &layout, *origin, *font_size, color, editing);
+            }
+        }
+        SceneItem::Image {
+            rect,
+            width,
+            height,
+            pixels,
+            source,
+        } => match ctx.make_image(*width, *height, pixels, ImageFormat::RgbaSeparate) {
+            Ok(image) => match source {
+                Some(source) => {
+                    ctx.draw_image_area(&image, *source, *rect, InterpolationMode::Bilinear);
+                }
+                None => ctx.draw_image(&image, *rect, InterpolationMode::Bilinear),
+            },
+            Err(err) => log::warn!("Unable to create a {width}x{height} image: {err}"),
+        },
+        SceneItem::Group { transform, items } => paint_group(ctx, *transform, items),
+        SceneItem::Simplified { item, levels } => {
+            let scale = ctx.current_transform().determinant().abs().sqrt();
+            let shown = levels
+                .iter()
+                .rev()
+                .find(|(max_scale, _)| scale <= *max_scale)
+                .map_or(&**item, |(_, level)| level);
+            paint_item(ctx, shown);
+        }
+    }
+}
+
+/// Box painted in place of a path or text smaller than a couple of pixels once painted with `transform`,
+/// along with its color.
+fn placeholder(transform: Affine, item: &SceneItem) -> Option<(Rect, Color)> {
+    let (area, color) = match item {
+        SceneItem::Path { style, .. } => {
+            let color = style
+                .fill
+                .or_else(|| style.stroke.as_ref().map(|stroke| stroke.color))?;
+            (item.bounding_box()?, color)
+        }
+        SceneItem::Text {
+            content,
+            origin,
+            font_size,
+            max_width,
+            style,
+            ..
+        } => {
+            // Lines are at least as tall as the font size, larger text is painted without being measured twice
+            if font_size * transform.determinant().abs().sqrt() >= PLACEHOLDER_SIZE {
+                return None;
+            }
+            // An estimate is close enough for a box of a couple of pixels
+            let mut size = text_size(content, *font_size, *max_width);
+            if let Some(max_width) = max_width {
+                size.width = size.width.max(*max_width);
+            }
+            let color = style.fill.unwrap_or(Color::BLACK);
+            (Rect::from_origin_size(*origin, size), color)
+        }
+        SceneItem::Image { .. } | SceneItem::Group { .. } | SceneItem::Simplified { .. } => {
+            return None
+        }
+    };
+    let painted = transform.transform_rect_bbox(area);
+    (painted.width() < PLACEHOLDER_SIZE && painted.height() < PLACEHOLDER_SIZE)
+        .then_some((area, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> SceneItem {
+        SceneItem::Text {
+            content: content.to_owned(),
+            origin: Point::new(10.0, 20.0),
+            font_size: 20.0,
+            font: Font::default(),
+            max_width: None,
+            style: Style::default(),
+            editing: None,
+        }
+    }
+
+    #[test]
+    fn text_too_small_to_read_is_a_placeholder() {
+        assert!(placeholder(Affine::IDENTITY, &text("readable")).is_none());
+        let (area, color) = placeholder(Affine::scale(0.01), &text("tiny\ntext"))
+            .expect("a placeholder for text smaller than a pixel");
+        assert_eq!(color, Color::BLACK);
+        assert_eq!(area.origin(), Point::new(10.0, 20.0));
+        assert!(area.height() > area.width() / 2.0);
+    }
+}
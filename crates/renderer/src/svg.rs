@@ -0,0 +1,265 @@
+//! Scene export to SVG, painted with the same styles as the on screen rendering.
+
+use std::fmt::{self, Write as _};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use piet_common::{
+    kurbo::{Affine, Point, Rect},
+    Color,
+};
+
+use crate::{
+    offscreen::encode_png,
+    scene::{Scene, SceneItem, Style},
+    text::{Font, MeasuredText},
+};
+
+/// Describes the SVG document a scene is exported to.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgOptions {
+    /// Area of the scene shown by the SVG document, in scene coordinates.
+    pub view_box: Rect,
+    /// Whether the scene background is painted, the document is transparent otherwise.
+    pub background: bool,
+}
+
+/// Export the scene as a standalone SVG document.
+pub fn scene_to_svg(scene: &Scene, options: &SvgOptions) -> String {
+    let mut svg = String::new();
+    // Writing into a string does not fail
+    write_svg(&mut svg, scene, options).ok();
+    svg
+}
+
+fn write_svg(svg: &mut String, scene: &Scene, options: &SvgOptions) -> fmt::Result {
+    let SvgOptions {
+        view_box,
+        background,
+    } = *options;
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="{x} {y} {w} {h}">"#,
+        x = view_box.x0,
+        y = view_box.y0,
+        w = view_box.width(),
+        h = view_box.height(),
+    )?;
+    svg.push('\n');
+    if background {
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            view_box.x0,
+            view_box.y0,
+            view_box.width(),
+            view_box.height(),
+            paint_attributes("fill", scene.background),
+        )?;
+    }
+    for item in scene.items() {
+        write_item(svg, item)?;
+    }
+    svg.push_str("</svg>\n");
+    Ok(())
+}
+
+fn write_item(svg: &mut String, item: &SceneItem) -> fmt::Result {
+    match item {
+        SceneItem::Path { path, style } => writeln!(
+            svg,
+            r#"<path d="{}"{}/>"#,
+            path.to_svg(),
+            style_attributes(style)
+        ),
+        SceneItem::Text {
+            content,
+            origin,
+            font_size,
+            font,
+            max_width,
+            style,
+            ..
+        } => write_text(svg, content, *origin, *font_size, *font, *max_width, style),
+        SceneItem::Image {
+            rect,
+            width,
+            height,
+            pixels,
+            source,
+        } => {
+            let png = u32::try_from(*width)
+                .ok()
+                .zip(u32::try_from(*height).ok())
+                .and_then(|(width, height)| {
+                    let mut png = Vec::new();
+                    encode_png(&mut png, width, height, pixels).ok()?;
+                    Some(png)
+                });
+            let Some(png) = png else {
+                log::warn!("Unable to encode a {width}x{height} image, skipping it");
+                return Ok(());
+            };
+            let href = format!("data:image/png;base64,{}", STANDARD.encode(png));
+            match source {
+                // A nested viewport clips the image to the area shown
+                Some(source) => writeln!(
+                    svg,
+                    r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><image width="{width}" height="{height}" href="{href}"/></svg>"#,
+                    rect.x0,
+                    rect.y0,
+                    rect.width(),
+                    rect.height(),
+                    source.x0,
+                    source.y0,
+                    source.width(),
+                    source.height(),
+                ),
+                None => writeln!(
+                    svg,
+                    r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="{href}"/>"#,
+                    rect.x0,
+                    rect.y0,
+                    rect.width(),
+                    rect.height(),
+                ),
+            }
+        }
+        SceneItem::Group { transform, items } => {
+            writeln!(svg, r#"<g transform="{}">"#, matrix(*transform))?;
+            for item in items {
+                write_item(svg, item)?;
+            }
+            svg.push_str("</g>\n");
+            Ok(())
+        }
+        // Exported drawings are meant to be zoomed into, every detail is kept
+        SceneItem::Simplified { item, .. } => write_item(svg, item),
+    }
+}
+
+/// Text painted with its top left corner at `origin`, one line per `tspan`.
+/// SVG text does not wrap, the lines are broken where the text layout wraps them.
+fn write_text(
+    svg: &mut String,
+    content: &str,
+    origin: Point,
+    font_size: f64,
+    font: Font,
+    max_width: Option<f64>,
+    style: &Style,
+) -> fmt::Result {
+    let color = style.fill.unwrap_or(Color::BLACK);
+    write!(
+        svg,
+        r#"<text x="{}" y="{}" font-family="{}" font-size="{font_size}" dominant-baseline="hanging" xml:space="preserve"{}>"#,
+        origin.x,
+        origin.y,
+        font.generic_name(),
+        paint_attributes("fill", color),
+    )?;
+    let text = MeasuredText::new(content, font, font_size, max_width);
+    for line in text.lines() {
+        let top = text.caret(line.start).y0;
+        write!(
+            svg,
+            r#"<tspan x="{}" y="{}">{}</tspan>"#,
+            origin.x,
+            origin.y + top,
+            escape(&content[line])
+        )?;
+    }
+    svg.push_str("</text>\n");
+    Ok(())
+}
+
+fn style_attributes(style: &Style) -> String {
+    let mut attributes = match style.fill {
+        Some(fill) => paint_attributes("fill", fill),
+        None => r#" fill="none""#.to_owned(),
+    };
+    if let Some(stroke) = &style.stroke {
+        attributes.push_str(&paint_attributes("stroke", stroke.color));
+        write!(
+            attributes,
+            r#" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round""#,
+            stroke.width
+        )
+        .ok();
+    }
+    attributes
+}
+
+/// `name` color attribute along with its opacity when not opaque.
+fn paint_attributes(name: &str, color: Color) -> String {
+    let (r, g, b, a) = color.as_rgba8();
+    let mut attributes = format!(r##" {name}="#{r:02x}{g:02x}{b:02x}""##);
+    if a != u8::MAX {
+        write!(
+            attributes,
+            r#" {name}-opacity="{:.3}""#,
+            f64::from(a) / 255.0
+        )
+        .ok();
+    }
+    attributes
+}
+
+fn matrix(transform: Affine) -> String {
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    format!("matrix({a} {b} {c} {d} {e} {f})")
+}
+
+/// Escape `text` for XML character data, dropping the characters XML 1.0 does not allow.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markup_and_invalid_characters() {
+        assert_eq!(escape(r#"a<b>&"c""#), "a&lt;b&gt;&amp;&quot;c&quot;");
+        assert_eq!(escape("tab\there\u{0}\u{1b}\u{ffff}"), "tab\there");
+        assert_eq!(escape("日本語 ✓"), "日本語 ✓");
+    }
+
+    #[test]
+    fn text_lines_are_tspans() {
+        let mut scene = Scene::default();
+        scene.push(SceneItem::Text {
+            content: "one\ntwo".to_owned(),
+            origin: Point::new(10.0, 20.0),
+            font_size: 10.0,
+            font: Font::default(),
+            max_width: None,
+            style: Style::default(),
+            editing: None,
+        });
+        let svg = scene_to_svg(
+            &scene,
+            &SvgOptions {
+                view_box: Rect::new(0.0, 0.0, 100.0, 100.0),
+                background: false,
+            },
+        );
+        assert_eq!(svg.matches("<tspan").count(), 2);
+        assert!(svg.contains(">one</tspan>"));
+        assert!(svg.contains(">two</tspan>"));
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
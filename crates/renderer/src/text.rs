@@ -0,0 +1,330 @@
+//! Text attributes and metrics, measured with the text layouts painted or estimated from the font size
+//! where no text layout is available.
+
+use std::{cell::RefCell, ops::Range};
+
+use piet_common::{
+    kurbo::{Point, Rect, Size},
+    Device, FontFamily, PietText, PietTextLayout, RenderContext as _, Text, TextLayout as _,
+    TextLayoutBuilder,
+};
+
+/// Average advance of a character, relative to the font size.
+pub(crate) const TEXT_WIDTH_PER_SIZE: f64 = 0.6;
+/// Height of a line of text, relative to the font size.
+const TEXT_LINE_HEIGHT: f64 = 1.2;
+
+/// Generic font families, resolved to a font installed on the system.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Font {
+    #[default]
+    SansSerif,
+    Serif,
+    Monospace,
+}
+
+impl Font {
+    /// Name of the family in CSS and SVG documents.
+    pub fn generic_name(self) -> &'static str {
+        match self {
+            Font::SansSerif => "sans-serif",
+            Font::Serif => "serif",
+            Font::Monospace => "monospace",
+        }
+    }
+
+    pub(crate) fn family(self) -> FontFamily {
+        match self {
+            Font::SansSerif => FontFamily::SANS_SERIF,
+            Font::Serif => FontFamily::SERIF,
+            Font::Monospace => FontFamily::MONOSPACE,
+        }
+    }
+}
+
+/// Caret, selection and input method composition of a text being edited, as byte offsets into its content.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextEditing {
+    pub caret: usize,
+    /// Highlighted range, empty when nothing is selected.
+    pub selection: Range<usize>,
+    /// Range of the text being composed with an input method, underlined until committed.
+    pub composition: Option<Range<usize>>,
+}
+
+thread_local! {
+    /// Text factory laying out the texts measured on this thread, `None` when no device is available.
+    static TEXT: RefCell<Option<PietText>> = RefCell::new(new_text());
+}
+
+/// Text laid out as it is painted, to measure it and to place a caret in it.
+/// The metrics are estimated from the font size when no text layout is available.
+pub struct MeasuredText<'a> {
+    content: &'a str,
+    font_size: f64,
+    max_width: Option<f64>,
+    layout: Option<PietTextLayout>,
+}
+
+/// Constructor functions
+impl<'a> MeasuredText<'a> {
+    pub fn new(content: &'a str, font: Font, font_size: f64, max_width: Option<f64>) -> Self {
+        let layout = TEXT
+            .with(|text| {
+                let mut text = text.borrow_mut();
+                let builder = layout_builder(text.as_mut()?, content, font, font_size, max_width);
+                match builder.build() {
+                    Ok(layout) => Some(layout),
+                    Err(err) => {
+                        log::warn!("Unable to layout text {content:?}: {err}");
+                        None
+                    }
+                }
+            })
+            // Layouts hold a line even without text, an empty one could not be measured
+            .filter(|layout| layout.line_count() > 0);
+        MeasuredText {
+            content,
+            font_size,
+            max_width,
+            layout,
+        }
+    }
+}
+
+/// Reference functions
+impl MeasuredText<'_> {
+    /// Size of the text, the widest line wide.
+    pub fn size(&self) -> Size {
+        match &self.layout {
+            Some(layout) => layout.size(),
+            None => text_size(self.content, self.font_size, self.max_width),
+        }
+    }
+
+    /// Byte ranges of the lines the text is laid out into, without the line breaks.
+    pub fn lines(&self) -> Vec<Range<usize>> {
+        let Some(layout) = &self.layout else {
+            return text_lines(self.content, self.font_size, self.max_width);
+        };
+        (0..layout.line_count())
+            .filter_map(|line| layout.line_metric(line))
+            .map(|metric| {
+                let line = &self.content[metric.start_offset..metric.end_offset];
+                let end = metric.start_offset + line.trim_end_matches(['\n', '\r']).len();
+                metric.start_offset..end
+            })
+            .collect()
+    }
+
+    /// Caret at the byte `offset` of the text, from the top to the bottom of its line.
+    pub fn caret(&self, offset: usize) -> Rect {
+        let line_height = self.font_size * TEXT_LINE_HEIGHT;
+        let Some(layout) = &self.layout else {
+            let top = caret_position(self.content, self.font_size, self.max_width, offset);
+            return Rect::from_origin_size(top, (0.0, line_height));
+        };
+        let position = layout.hit_test_text_position(offset);
+        let (top, height) = layout
+            .line_metric(position.line)
+            .map_or((0.0, line_height), |line| (line.y_offset, line.height));
+        Rect::new(position.point.x, top, position.point.x, top + height)
+    }
+
+    /// Byte offset of the caret closest to `point`.
+    pub fn offset_at(&self, point: Point) -> usize {
+        match &self.layout {
+            Some(layout) => layout.hit_test_point(point).idx,
+            None => text_offset_at(self.content, self.font_size, self.max_width, point),
+        }
+    }
+}
+
+/// Start laying out `content` as every text is laid out, for painting as well as for measuring.
+pub(crate) fn layout_builder<T: Text>(
+    text: &mut T,
+    content: &str,
+    font: Font,
+    font_size: f64,
+    max_width: Option<f64>,
+) -> T::TextLayoutBuilder {
+    let builder = text
+        .new_text_layout(content.to_owned())
+        .font(font.family(), font_size);
+    match max_width {
+        Some(max_width) => builder.max_width(max_width),
+        None => builder,
+    }
+}
+
+/// Text factory of a render context of its own, the device is only needed to create it.
+fn new_text() -> Option<PietText> {
+    let mut device = match Device::new() {
+        Ok(device) => device,
+        Err(err) => {
+            log::warn!("Unable to create a device to measure text, its size is estimated: {err}");
+            return None;
+        }
+    };
+    let mut bitmap = match device.bitmap_target(1, 1, 1.0) {
+        Ok(bitmap) => bitmap,
+        Err(err) => {
+            log::warn!("Unable to create a bitmap to measure text, its size is estimated: {err}");
+            return None;
+        }
+    };
+    let mut ctx = bitmap.render_context();
+    let text = ctx.text().clone();
+    ctx.finish().ok();
+    Some(text)
+}
+
+/// Byte ranges of the lines `content` is estimated to be laid out into, without the line breaks.
+/// Lines longer than `max_width` wrap after the last space fitting, or between characters for long words.
+pub(crate) fn text_lines(
+    content: &str,
+    font_size: f64,
+    max_width: Option<f64>,
+) -> Vec<Range<usize>> {
+    let advance = font_size * TEXT_WIDTH_PER_SIZE;
+    let columns = max_width
+        .filter(|_| advance > 0.0)
+        .map(|max_width| ((max_width / advance).floor() as usize).max(1));
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in content.split('\n') {
+        let end = start + line.len();
+        match columns {
+            Some(columns) => wrap_line(content, start..end, columns, &mut lines),
+            None => lines.push(start..end),
+        }
+        start = end + 1;
+    }
+    lines
+}
+
+/// Estimated size of `content` laid out with `font_size`, wrapped to `max_width`.
+pub(crate) fn text_size(content: &str, font_size: f64, max_width: Option<f64>) -> Size {
+    let lines = text_lines(content, font_size, max_width);
+    let columns = lines
+        .iter()
+        .map(|line| content[line.clone()].chars().count())
+        .max()
+        .unwrap_or_default();
+    Size::new(
+        columns as f64 * font_size * TEXT_WIDTH_PER_SIZE,
+        lines.len() as f64 * font_size * TEXT_LINE_HEIGHT,
+    )
+}
+
+/// Estimated top of the caret at the byte `offset` of `content`, relative to the text origin.
+fn caret_position(content: &str, font_size: f64, max_width: Option<f64>, offset: usize) -> Point {
+    let lines = text_lines(content, font_size, max_width);
+    // A caret at a wrap belongs to the start of the next line, as with the text layouts
+    let index = lines
+        .iter()
+        .rposition(|line| line.start <= offset)
+        .unwrap_or_default();
+    let line = &lines[index];
+    let columns = content[line.start..offset.clamp(line.start, line.end)]
+        .chars()
+        .count();
+    Point::new(
+        columns as f64 * font_size * TEXT_WIDTH_PER_SIZE,
+        index as f64 * font_size * TEXT_LINE_HEIGHT,
+    )
+}
+
+/// Estimated byte offset of the caret closest to `point`, relative to the text origin.
+fn text_offset_at(content: &str, font_size: f64, max_width: Option<f64>, point: Point) -> usize {
+    let lines = text_lines(content, font_size, max_width);
+    let line_height = font_size * TEXT_LINE_HEIGHT;
+    let index = if line_height > 0.0 {
+        ((point.y / line_height).floor().max(0.0) as usize).min(lines.len() - 1)
+    } else {
+        0
+    };
+    let line = &lines[index];
+    let advance = font_size * TEXT_WIDTH_PER_SIZE;
+    let column = if advance > 0.0 {
+        (point.x / advance).round().max(0.0) as usize
+    } else {
+        0
+    };
+    content[line.clone()]
+        .char_indices()
+        .nth(column)
+        .map_or(line.end, |(offset, _)| line.start + offset)
+}
+
+/// Split the `line` range of `content` into lines of at most `columns` characters.
+fn wrap_line(content: &str, line: Range<usize>, columns: usize, lines: &mut Vec<Range<usize>>) {
+    let mut start = line.start;
+    let mut count = 0;
+    // Offset following the last space of the current line, where it can wrap
+    let mut wrap = None;
+    for (offset, c) in content[line.clone()].char_indices() {
+        let offset = line.start + offset;
+        if count == columns {
+            // Spaces hang past the end of the line instead of starting the next one
+            if c != ' ' {
+                let end = wrap.filter(|wrap| *wrap > start).unwrap_or(offset);
+                lines.push(start..end);
+                count = content[end..offset].chars().count();
+                start = end;
+                wrap = None;
+            }
+        }
+        if c == ' ' {
+            wrap = Some(offset + 1);
+        }
+        count = (count + 1).min(columns);
+    }
+    lines.push(start..line.end);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of `content` wrapped at `columns` estimated characters.
+    fn wrapped(content: &str, columns: f64) -> Vec<&str> {
+        let font_size = 10.0;
+        let max_width = columns * font_size * TEXT_WIDTH_PER_SIZE;
+        text_lines(content, font_size, Some(max_width))
+            .into_iter()
+            .map(|line| &content[line])
+            .collect()
+    }
+
+    #[test]
+    fn lines_break_at_line_breaks() {
+        let content = "one\ntwo\n";
+        let lines: Vec<&str> = text_lines(content, 10.0, None)
+            .into_iter()
+            .map(|line| &content[line])
+            .collect();
+        assert_eq!(lines, vec!["one", "two", ""]);
+    }
+
+    #[test]
+    fn lines_wrap_after_the_last_space_fitting() {
+        assert_eq!(wrapped("one two three", 8.0), vec!["one two ", "three"]);
+    }
+
+    #[test]
+    fn spaces_hang_past_the_end_of_the_line() {
+        assert_eq!(wrapped("abc   def", 3.0), vec!["abc   ", "def"]);
+    }
+
+    #[test]
+    fn long_words_wrap_between_characters() {
+        assert_eq!(wrapped("abcdefgh", 3.0), vec!["abc", "def", "gh"]);
+        assert_eq!(wrapped("éèàù", 2.0), vec!["éè", "àù"]);
+    }
+
+    #[test]
+    fn narrow_widths_keep_a_character_per_line() {
+        assert_eq!(wrapped("ab", 0.1), vec!["a", "b"]);
+    }
+}
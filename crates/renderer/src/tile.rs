@@ -0,0 +1,410 @@
+//! The board rasterized into square tiles at discrete zoom levels, cached so that panning and zooming
+//! only composite the tiles while the board does not change.
+
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
+
+use lru::LruCache;
+use piet_common::{
+    kurbo::{Affine, Rect},
+    Color, Device, ImageFormat, InterpolationMode, RenderContext,
+};
+
+use crate::{scene::paint_group, worker::TileWorkers, Frame, Scene};
+
+/// Side of a tile in pixels.
+pub const TILE_SIZE: usize = 256;
+/// Tiles kept in memory, 64 megabytes of pixels. The least recently painted tiles are dropped first.
+const MAX_CACHED_TILES: NonZeroUsize = match NonZeroUsize::new(256) {
+    Some(count) => count,
+    None => panic!("the tile cache holds some tiles"),
+};
+/// Levels above a tile searched for a coarser tile shown in its place until it is rasterized.
+const MAX_PLACEHOLDER_LEVELS: i32 = 4;
+
+/// A tile of the board, `x` and `y` count the tiles from the board origin at zoom `level`.
+/// The tiles of a level are rasterized with `2^level` pixels per board unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct TileKey {
+    level: i32,
+    x: i64,
+    y: i64,
+}
+
+/// Content of the board the tiles are rasterized from.
+pub trait Board {
+    /// Content of the board `area` gathered for a tile, the tile workers build the scene of the tile from it.
+    fn tile(&self, area: Rect) -> Box<dyn TileContent>;
+}
+
+/// Content of the board within a tile, it must not change once handed to the renderer.
+pub trait TileContent: Send {
+    /// Items painted into the tile, every item touching its area.
+    fn scene(&self) -> Scene;
+}
+
+/// A tile along with its pixels.
+pub(crate) type Tile = (TileKey, Arc<[u8]>);
+
+/// Pixels of a cached tile, RGBA with premultiplied alpha over a transparent background.
+struct CachedTile {
+    pixels: Arc<[u8]>,
+    /// Whether the board changed in the tile area since it was rasterized. Stale tiles are painted
+    /// until the workers rasterize them again, so that edited areas do not blank out meanwhile.
+    stale: bool,
+}
+
+/// Rasterized tiles of the board.
+pub(crate) struct TileCache {
+    tiles: LruCache<TileKey, CachedTile>,
+    /// Threads rasterizing the tiles, `None` when the tiles are rasterized on the calling thread.
+    workers: Option<TileWorkers>,
+    /// Whether the workers were started, by the first tiles requested.
+    started: bool,
+    /// Tiles requested from the workers along with the id of the job, results of other jobs are outdated.
+    pending: HashMap<TileKey, u64>,
+}
+
+/// Constructor functions
+impl CachedTile {
+    fn new(pixels: Arc<[u8]>) -> CachedTile {
+        CachedTile {
+            pixels,
+            stale: false,
+        }
+    }
+}
+
+/// Reference functions
+impl TileKey {
+    /// Area of the board covered by the tile.
+    pub(crate) fn area(self) -> Rect {
+        let size = tile_extent(self.level);
+        let (x, y) = (self.x as f64 * size, self.y as f64 * size);
+        Rect::new(x, y, x + size, y + size)
+    }
+
+    /// Transform mapping the board coordinates to the tile pixels.
+    fn transform(self) -> Affine {
+        let size = TILE_SIZE as f64;
+        Affine::translate((-(self.x as f64) * size, -(self.y as f64) * size))
+            * Affine::scale(level_scale(self.level))
+    }
+
+    /// Tile `levels` levels up containing this one.
+    fn ancestor(self, levels: i32) -> TileKey {
+        let factor = 1 << levels;
+        TileKey {
+            level: self.level - levels,
+            x: self.x.div_euclid(factor),
+            y: self.y.div_euclid(factor),
+        }
+    }
+
+    /// The four tiles one level down covering this one.
+    fn children(self) -> [TileKey; 4] {
+        let (level, x, y) = (self.level + 1, self.x * 2, self.y * 2);
+        [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| TileKey {
+            level,
+            x: x + dx,
+            y: y + dy,
+        })
+    }
+}
+
+/// Constructor functions
+impl TileCache {
+    /// Cache rasterizing the tiles on worker threads, or on the calling thread when none can start.
+    /// The workers are started once tiles are first requested.
+    pub(crate) fn new() -> TileCache {
+        TileCache {
+            tiles: LruCache::new(MAX_CACHED_TILES),
+            workers: None,
+            started: false,
+            pending: HashMap::new(),
+        }
+    }
+}
+
+/// Reference functions
+impl TileCache {
+    /// Whether tiles are being rasterized by the workers, frames painted meanwhile show placeholders.
+    pub(crate) fn rasterizing(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// Mutable functions
+impl TileCache {
+    /// Tiles covering the `area` of the frame in physical pixels along with their pixels, in paint order.
+    /// The tiles missing or stale are rasterized from the scene `board` gives for their area, by the workers
+    /// when running. Meanwhile the stale tiles are painted, or else the cached tiles of other levels covering them.
+    pub(crate) fn tiles(
+        &mut self,
+        device: &mut Device,
+        frame: &Frame,
+        area: Rect,
+        board: &dyn Board,
+    ) -> Result<Vec<Tile>, piet_common::Error> {
+        if !self.started {
+            self.started = true;
+            self.workers = TileWorkers::spawn();
+        }
+        let level = zoom_level(frame.camera.zoom() * frame.scale_factor);
+        let size = tile_extent(level);
+        let screen = Affine::scale(frame.scale_factor) * frame.camera.transform();
+        let world = screen.inverse().transform_rect_bbox(area);
+        let columns = (world.x0 / size).floor() as i64..(world.x1 / size).ceil() as i64;
+        let rows = (world.y0 / size).floor() as i64..(world.y1 / size).ceil() as i64;
+
+        let mut tiles = Vec::new();
+        let mut placeholders = Vec::new();
+        for y in rows {
+            for x in columns.clone() {
+                let key = TileKey { level, x, y };
+                let cached = self
+                    .tiles
+                    .get(&key)
+                    .map(|tile| (Arc::clone(&tile.pixels), tile.stale));
+                if let Some((pixels, false)) = cached {
+                    tiles.push((key, pixels));
+                } else if let Some(workers) = &mut self.workers {
+                    if !self.pending.contains_key(&key) {
+                        let (id, dropped) = workers.request(key, board.tile(key.area()));
+                        for key in dropped {
+                            self.pending.remove(&key);
+                        }
+                        self.pending.insert(key, id);
+                    }
+                    if let Some((pixels, _)) = cached {
+                        tiles.push((key, pixels));
+                        continue;
+                    }
+                    for placeholder in self.placeholders(key) {
+                        if !placeholders
+                            .iter()
+                            .any(|(other, _)| *other == placeholder.0)
+                        {
+                            placeholders.push(placeholder);
+                        }
+                    }
+                } else {
+                    let pixels = rasterize(device, key, &board.tile(key.area()).scene())?;
+                    self.tiles.put(key, CachedTile::new(Arc::clone(&pixels)));
+                    tiles.push((key, pixels));
+                }
+            }
+        }
+        placeholders.append(&mut tiles);
+        Ok(placeholders)
+    }
+
+    /// Cache the tiles the workers rasterized since the last call, returns the areas of the board they cover.
+    pub(crate) fn collect(&mut self) -> Vec<Rect> {
+        let Some(workers) = &self.workers else {
+            return Vec::new();
+        };
+        let mut areas = Vec::new();
+        for finished in workers.finished() {
+            if self.pending.get(&finished.key) != Some(&finished.id) {
+                // The tile was invalidated since it was requested
+                continue;
+            }
+            self.pending.remove(&finished.key);
+            match finished.pixels {
+                Ok(pixels) => {
+                    self.tiles.put(finished.key, CachedTile::new(pixels));
+                    areas.push(finished.key.area());
+                }
+                Err(err) => log::warn!("Unable to rasterize the tile {:?}: {err}", finished.key),
+            }
+        }
+        areas
+    }
+
+    /// Mark the tiles of every level overlapping the board `area` as stale, they are rasterized again
+    /// when next painted.
+    pub(crate) fn invalidate(&mut self, area: Rect) {
+        for (key, tile) in &mut self.tiles {
+            if touches(*key, area) {
+                tile.stale = true;
+            }
+        }
+        // The tiles being rasterized are requested again with the board as it is now
+        self.pending.retain(|key, _| !touches(*key, area));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.tiles.clear();
+        self.pending.clear();
+    }
+}
+
+/// Private functions
+impl TileCache {
+    /// Cached tiles of other levels painted in place of the tile `key` until it is rasterized:
+    /// the closest coarser tile, or else the finer tiles left from before zooming out.
+    fn placeholders(&self, key: TileKey) -> Vec<Tile> {
+        let coarser = (1..=MAX_PLACEHOLDER_LEVELS).find_map(|levels| {
+            let ancestor = key.ancestor(levels);
+            let tile = self.tiles.peek(&ancestor)?;
+            Some((ancestor, Arc::clone(&tile.pixels)))
+        });
+        match coarser {
+            Some(tile) => vec![tile],
+            None => key
+                .children()
+                .into_iter()
+                .filter_map(|child| Some((child, Arc::clone(&self.tiles.peek(&child)?.pixels))))
+                .collect(),
+        }
+    }
+}
+
+/// Paint the tiles over the frame, scaled from their level to the camera zoom.
+pub(crate) fn paint_tiles(ctx: &mut impl RenderContext, frame: &Frame, tiles: &[Tile]) {
+    let scale = frame.scale_factor;
+    let screen = Affine::scale(scale) * frame.camera.transform();
+    for (key, pixels) in tiles {
+        // Tiles are snapped to the physical pixels so that no seam shows between them
+        let area = screen.transform_rect_bbox(key.area()).round();
+        let area = Affine::scale(1.0 / scale).transform_rect_bbox(area);
+        match ctx.make_image(TILE_SIZE, TILE_SIZE, pixels, ImageFormat::RgbaPremul) {
+            Ok(image) => ctx.draw_image(&image, area, InterpolationMode::Bilinear),
+            Err(err) => log::warn!("Unable to create the image of the tile {key:?}: {err}"),
+        }
+    }
+}
+
+/// Paint the tile of the board with the items of `board`.
+pub(crate) fn rasterize(
+    device: &mut Device,
+    key: TileKey,
+    board: &Scene,
+) -> Result<Arc<[u8]>, piet_common::Error> {
+    let mut bitmap = device.bitmap_target(TILE_SIZE, TILE_SIZE, 1.0)?;
+    {
+        let mut ctx = bitmap.render_context();
+        ctx.clear(None, Color::TRANSPARENT);
+        paint_group(&mut ctx, key.transform(), board.items());
+        ctx.finish()?;
+    }
+    let mut pixels = vec![0; TILE_SIZE * TILE_SIZE * ImageFormat::RgbaPremul.bytes_per_pixel()];
+    bitmap.copy_raw_pixels(ImageFormat::RgbaPremul, &mut pixels)?;
+    Ok(Arc::from(pixels))
+}
+
+/// Level of the tiles painted with `pixels_per_unit` physical pixels per board unit,
+/// the tiles are never magnified so that they stay as sharp as the items painted directly.
+fn zoom_level(pixels_per_unit: f64) -> i32 {
+    pixels_per_unit.log2().ceil() as i32
+}
+
+fn level_scale(level: i32) -> f64 {
+    2.0_f64.powi(level)
+}
+
+/// Side of the tiles of `level` in board units.
+fn tile_extent(level: i32) -> f64 {
+    TILE_SIZE as f64 / level_scale(level)
+}
+
+/// Whether the board `area` reaches into the tile `key`, antialiasing reaches a pixel past the edges of the items.
+fn touches(key: TileKey, area: Rect) -> bool {
+    let pixel = 1.0 / level_scale(key.level);
+    overlaps(key.area().inflate(pixel, pixel), area)
+}
+
+/// Whether the areas share some surface or, for a degenerate area, some point.
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.x0 <= b.x1 && b.x0 <= a.x1 && a.y0 <= b.y1 && b.y0 <= a.y1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use piet_common::kurbo::Shape as _;
+
+    use super::*;
+    use crate::{Camera, SceneItem, Style};
+
+    /// A black square in the middle of the first tile.
+    struct Square;
+
+    /// Part of the square within `Rect`.
+    struct SquareTile(Rect);
+
+    impl Board for Square {
+        fn tile(&self, area: Rect) -> Box<dyn TileContent> {
+            Box::new(SquareTile(area))
+        }
+    }
+
+    impl TileContent for SquareTile {
+        fn scene(&self) -> Scene {
+            let mut scene = Scene::default();
+            let square = Rect::new(100.0, 100.0, 150.0, 150.0);
+            if overlaps(square, self.0) {
+                scene.push(SceneItem::Path {
+                    path: square.to_path(0.1),
+                    style: Style {
+                        stroke: None,
+                        fill: Some(Color::BLACK),
+                    },
+                });
+            }
+            scene
+        }
+    }
+
+    fn frame() -> Frame {
+        Frame {
+            window_id: 0,
+            width: 200,
+            height: 200,
+            scale_factor: 1.0,
+            camera: Camera::default(),
+        }
+    }
+
+    /// Request the tiles of the frame until none is being rasterized.
+    fn rasterized(cache: &mut TileCache, device: &mut Device, board: &dyn Board) -> Vec<Tile> {
+        let area = Rect::new(0.0, 0.0, 200.0, 200.0);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            cache.collect();
+            let tiles = cache
+                .tiles(device, &frame(), area, board)
+                .expect("the tiles are rasterized");
+            if !cache.rasterizing() || Instant::now() > deadline {
+                return tiles;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn invalidated_tiles_are_painted_until_rasterized_again() {
+        let mut device = Device::new().expect("a device is available");
+        let mut cache = TileCache::new();
+        let tiles = rasterized(&mut cache, &mut device, &Square);
+        assert_eq!(tiles.len(), 1);
+        assert!(!cache.rasterizing());
+
+        cache.invalidate(Rect::new(120.0, 120.0, 130.0, 130.0));
+        let area = Rect::new(0.0, 0.0, 200.0, 200.0);
+        let stale = cache
+            .tiles(&mut device, &frame(), area, &Square)
+            .expect("the tiles are rasterized");
+        // The stale tile is painted while the workers rasterize it again
+        assert_eq!(stale.len(), 1);
+        assert!(Arc::ptr_eq(&stale[0].1, &tiles[0].1) || !cache.rasterizing());
+
+        let tiles = rasterized(&mut cache, &mut device, &Square);
+        assert_eq!(tiles.len(), 1);
+        assert!(!cache.rasterizing());
+    }
+}
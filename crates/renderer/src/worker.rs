@@ -0,0 +1,192 @@
+//! Threads rasterizing the tiles of the board in the background, so that painting a dense board
+//! does not hold up the event loop. Devices cannot move between threads, each worker creates its own.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use parking_lot::{Condvar, Mutex};
+use piet_common::Device;
+
+use crate::tile::{rasterize, TileContent, TileKey};
+
+/// Most workers started, the event loop thread keeps a core of its own besides them.
+const MAX_WORKERS: usize = 4;
+/// Jobs waiting beyond this count drop the oldest ones, requested for frames long gone.
+const MAX_QUEUED_JOBS: usize = 1024;
+
+/// Tile to rasterize from the board `content` within its area.
+struct Job {
+    id: u64,
+    key: TileKey,
+    content: Box<dyn TileContent>,
+}
+
+/// Pixels of a tile rasterized by a worker, or why it could not be.
+pub(crate) struct Finished {
+    pub(crate) id: u64,
+    pub(crate) key: TileKey,
+    pub(crate) pixels: Result<Arc<[u8]>, String>,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    /// Set when the pool is dropped, the workers stop once they see it.
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    /// Notified when a job is queued or the queue is closed.
+    available: Condvar,
+}
+
+/// Pool of threads rasterizing tiles, the most recently requested tiles first.
+pub(crate) struct TileWorkers {
+    shared: Arc<Shared>,
+    finished: Receiver<Finished>,
+    threads: Vec<JoinHandle<()>>,
+    next_id: u64,
+}
+
+/// Constructor functions
+impl TileWorkers {
+    /// Start the workers, `None` when none of them could create a device.
+    pub(crate) fn spawn() -> Option<TileWorkers> {
+        let count = thread::available_parallelism()
+            .map_or(1, |count| count.get().saturating_sub(1))
+            .clamp(1, MAX_WORKERS);
+        let shared = Arc::new(Shared::default());
+        let (finished_sender, finished) = mpsc::channel();
+        let (ready_sender, ready) = mpsc::channel();
+        let mut threads = Vec::new();
+        for index in 0..count {
+            let shared = Arc::clone(&shared);
+            let finished = finished_sender.clone();
+            let ready = ready_sender.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("tile_worker_{index}"))
+                .spawn(move || work(&shared, &finished, &ready));
+            match spawned {
+                Ok(thread) => threads.push(thread),
+                Err(err) => log::warn!("Unable to start a tile worker: {err}"),
+            }
+        }
+        drop(ready_sender);
+
+        // Wait for the workers to create their device, those which could not have stopped already
+        let started = ready
+            .iter()
+            .take(threads.len())
+            .filter(|started| *started)
+            .count();
+        let workers = TileWorkers {
+            shared,
+            finished,
+            threads,
+            next_id: 0,
+        };
+        if started == 0 {
+            log::warn!("No tile worker could start, tiles are rasterized on the event loop thread");
+            return None;
+        }
+        log::info!("Started {started} tile workers");
+        Some(workers)
+    }
+}
+
+/// Reference functions
+impl TileWorkers {
+    /// Tiles rasterized since the last call.
+    pub(crate) fn finished(&self) -> impl Iterator<Item = Finished> + '_ {
+        self.finished.try_iter()
+    }
+}
+
+/// Mutable functions
+impl TileWorkers {
+    /// Queue the tile to be rasterized from the board `content` within its area, returns the id of the job
+    /// along with the tiles dropped from the queue to make room for it.
+    pub(crate) fn request(
+        &mut self,
+        key: TileKey,
+        content: Box<dyn TileContent>,
+    ) -> (u64, Vec<TileKey>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut dropped = Vec::new();
+        {
+            let mut queue = self.shared.queue.lock();
+            queue.jobs.push_back(Job { id, key, content });
+            while queue.jobs.len() > MAX_QUEUED_JOBS {
+                if let Some(job) = queue.jobs.pop_front() {
+                    dropped.push(job.key);
+                }
+            }
+        }
+        self.shared.available.notify_one();
+        (id, dropped)
+    }
+}
+
+impl Drop for TileWorkers {
+    fn drop(&mut self) {
+        {
+            let mut queue = self.shared.queue.lock();
+            queue.closed = true;
+            queue.jobs.clear();
+        }
+        self.shared.available.notify_all();
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                log::warn!("A tile worker panicked");
+            }
+        }
+    }
+}
+
+/// Body of a worker thread: rasterize the queued tiles, newest first, until the queue is closed.
+fn work(shared: &Shared, finished: &Sender<Finished>, ready: &Sender<bool>) {
+    let mut device = match Device::new() {
+        Ok(device) => device,
+        Err(err) => {
+            log::warn!("Unable to create a device for a tile worker: {err}");
+            ready.send(false).ok();
+            return;
+        }
+    };
+    ready.send(true).ok();
+
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop_back() {
+                    break job;
+                }
+                shared.available.wait(&mut queue);
+            }
+        };
+        let scene = job.content.scene();
+        let pixels = rasterize(&mut device, job.key, &scene).map_err(|err| err.to_string());
+        let result = Finished {
+            id: job.id,
+            key: job.key,
+            pixels,
+        };
+        if finished.send(result).is_err() {
+            // The pool is gone
+            return;
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use crate::{event::KeyboardModifiers, key_code::KeyCode};
+
+/// Application level actions triggered from the menu bar, keyboard shortcuts or programmatically
+/// through the event loop proxy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    New,
+    NewWindow,
+    Open,
+    OpenRecent,
+    Save,
+    SaveAs,
+    ExportSvg,
+    Undo,
+    Redo,
+    Cut,
+    Copy,
+    Paste,
+    Delete,
+    SelectAll,
+    StraightRouting,
+    ElbowRouting,
+    CurvedRouting,
+    NextStartArrowHead,
+    NextEndArrowHead,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    InsertImage,
+    SelectTool,
+    PenTool,
+    EraserTool,
+    PreciseEraserTool,
+    RectangleTool,
+    EllipseTool,
+    DiamondTool,
+    LineTool,
+    ArrowTool,
+    ElbowArrowTool,
+    CurvedArrowTool,
+    TextTool,
+    Settings,
+    SendFeedback,
+    Help,
+    KeyboardShortcuts,
+    Github,
+    About,
+}
+
+/// Reference functions
+impl Command {
+    pub const ALL: [Command; 41] = [
+        Command::New,
+        Command::NewWindow,
+        Command::Open,
+        Command::OpenRecent,
+        Command::Save,
+        Command::SaveAs,
+        Command::ExportSvg,
+        Command::Undo,
+        Command::Redo,
+        Command::Cut,
+        Command::Copy,
+        Command::Paste,
+        Command::Delete,
+        Command::SelectAll,
+        Command::StraightRouting,
+        Command::ElbowRouting,
+        Command::CurvedRouting,
+        Command::NextStartArrowHead,
+        Command::NextEndArrowHead,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ResetZoom,
+        Command::InsertImage,
+        Command::SelectTool,
+        Command::PenTool,
+        Command::EraserTool,
+        Command::PreciseEraserTool,
+        Command::RectangleTool,
+        Command::EllipseTool,
+        Command::DiamondTool,
+        Command::LineTool,
+        Command::ArrowTool,
+        Command::ElbowArrowTool,
+        Command::CurvedArrowTool,
+        Command::TextTool,
+        Command::Settings,
+        Command::SendFeedback,
+        Command::Help,
+        Command::KeyboardShortcuts,
+        Command::Github,
+        Command::About,
+    ];
+
+    /// Stable identifier of the command, used to invoke it by name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::New => "file.new",
+            Command::NewWindow => "file.new_window",
+            Command::Open => "file.open",
+            Command::OpenRecent => "file.open_recent",
+            Command::Save => "file.save",
+            Command::SaveAs => "file.save_as",
+            Command::ExportSvg => "file.export_svg",
+            Command::Undo => "edit.undo",
+            Command::Redo => "edit.redo",
+            Command::Cut => "edit.cut",
+            Command::Copy => "edit.copy",
+            Command::Paste => "edit.paste",
+            Command::Delete => "edit.delete",
+            Command::SelectAll => "edit.select_all",
+            Command::StraightRouting => "edit.straight_routing",
+            Command::ElbowRouting => "edit.elbow_routing",
+            Command::CurvedRouting => "edit.curved_routing",
+            Command::NextStartArrowHead => "edit.next_start_arrow_head",
+            Command::NextEndArrowHead => "edit.next_end_arrow_head",
+            Command::ZoomIn => "view.zoom_in",
+            Command::ZoomOut => "view.zoom_out",
+            Command::ResetZoom => "view.reset_zoom",
+            Command::InsertImage => "insert.image",
+            Command::SelectTool => "tools.select",
+            Command::PenTool => "tools.pen",
+            Command::EraserTool => "tools.eraser",
+            Command::PreciseEraserTool => "tools.precise_eraser",
+            Command::RectangleTool => "tools.rectangle",
+            Command::EllipseTool => "tools.ellipse",
+            Command::DiamondTool => "tools.diamond",
+            Command::LineTool => "tools.line",
+            Command::ArrowTool => "tools.arrow",
+            Command::ElbowArrowTool => "tools.elbow_arrow",
+            Command::CurvedArrowTool => "tools.curved_arrow",
+            Command::TextTool => "tools.text",
+            Command::Settings => "app.settings",
+            Command::SendFeedback => "help.send_feedback",
+            Command::Help => "help.help",
+            Command::KeyboardShortcuts => "help.keyboard_shortcuts",
+            Command::Github => "help.github",
+            Command::About => "help.about",
+        }
+    }
+
+    /// Text of the menu item triggering the command.
+    pub fn label(self) -> &'static str {
+        match self {
+            Command::New => "New",
+            Command::NewWindow => "New Window",
+            Command::Open => "Open",
+            Command::OpenRecent => "Open Recent",
+            Command::Save => "Save",
+            Command::SaveAs => "Save As",
+            Command::ExportSvg => "Export SVG",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::Cut => "Cut",
+            Command::Copy => "Copy",
+            Command::Paste => "Paste",
+            Command::Delete => "Delete",
+            Command::SelectAll => "Select All",
+            Command::StraightRouting => "Straight Connector",
+            Command::ElbowRouting => "Elbow Connector",
+            Command::CurvedRouting => "Curved Connector",
+            Command::NextStartArrowHead => "Change Start Arrowhead",
+            Command::NextEndArrowHead => "Change End Arrowhead",
+            Command::ZoomIn => "Zoom In",
+            Command::ZoomOut => "Zoom Out",
+            Command::ResetZoom => "Reset Zoom",
+            Command::InsertImage => "Image",
+            Command::SelectTool => "Select",
+            Command::PenTool => "Pen",
+            Command::EraserTool => "Eraser",
+            Command::PreciseEraserTool => "Precise Eraser",
+            Command::RectangleTool => "Rectangle",
+            Command::EllipseTool => "Ellipse",
+            Command::DiamondTool => "Diamond",
+            Command::LineTool => "Line",
+            Command::ArrowTool => "Arrow",
+            Command::ElbowArrowTool => "Elbow Arrow",
+            Command::CurvedArrowTool => "Curved Arrow",
+            Command::TextTool => "Text",
+            Command::Settings => "Settings",
+            Command::SendFeedback => "Send feedback",
+            Command::Help => "Oxdraw help",
+            Command::KeyboardShortcuts => "Keyboard shortcuts",
+            Command::Github => "Github",
+            Command::About => "About Oxdraw",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Command> {
+        Command::ALL
+            .into_iter()
+            .find(|command| command.name() == name)
+    }
+}
+
+/// Key combination triggering a command.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    /// `Command` on macOS, `Control` everywhere else.
+    pub cmd_or_ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// Characters are always lowercase, with no shift flag.
+    pub key: KeyCode,
+}
+
+/// Constructor functions
+impl Shortcut {
+    /// `CmdOrCtrl` along with `c`.
+    pub fn cmd(c: &str) -> Shortcut {
+        Shortcut {
+            cmd_or_ctrl: true,
+            shift: false,
+            alt: false,
+            key: KeyCode::Character(c.into(), false),
+        }
+    }
+
+    /// `CmdOrCtrl+Shift` along with `c`.
+    pub fn cmd_shift(c: &str) -> Shortcut {
+        Shortcut {
+            shift: true,
+            ..Shortcut::cmd(c)
+        }
+    }
+
+    /// The `key` alone, without any modifier.
+    pub fn key(key: KeyCode) -> Shortcut {
+        Shortcut {
+            cmd_or_ctrl: false,
+            shift: false,
+            alt: false,
+            key,
+        }
+    }
+
+    /// The character `c` alone, without any modifier.
+    pub fn character(c: &str) -> Shortcut {
+        Shortcut::key(KeyCode::Character(c.into(), false))
+    }
+
+    /// `Shift` along with the character `c`.
+    pub fn shift(c: &str) -> Shortcut {
+        Shortcut {
+            shift: true,
+            ..Shortcut::character(c)
+        }
+    }
+
+    /// Shortcut of a key pressed while holding `key_mods`.
+    pub fn pressed(key: &KeyCode, key_mods: &KeyboardModifiers) -> Shortcut {
+        let key = match key {
+            KeyCode::Character(c, _) => KeyCode::Character(c.to_lowercase().into(), false),
+            key => key.clone(),
+        };
+        Shortcut {
+            cmd_or_ctrl: key_mods.cmd_or_ctrl(),
+            shift: key_mods.shift,
+            alt: key_mods.alt,
+            key,
+        }
+    }
+}
+
+/// Maps the menu items and keyboard shortcuts to the command they trigger.
+#[derive(Debug)]
+pub struct CommandRegistry {
+    /// Command of each menu item, keyed by the menu item id.
+    menu_items: HashMap<u32, Command>,
+    /// Shortcuts of each command, the first one is shown in the menu.
+    shortcuts: HashMap<Command, Vec<Shortcut>>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = CommandRegistry {
+            menu_items: HashMap::new(),
+            shortcuts: HashMap::new(),
+        };
+        for (command, shortcut) in default_shortcuts() {
+            registry.bind(command, shortcut);
+        }
+        registry
+    }
+}
+
+/// Reference functions
+impl CommandRegistry {
+    /// Command triggered by the menu item, if any.
+    pub fn menu_item(&self, id: u32) -> Option<Command> {
+        self.menu_items.get(&id).copied()
+    }
+
+    /// Command triggered by the key combination, if any.
+    pub fn shortcut(&self, shortcut: &Shortcut) -> Option<Command> {
+        self.shortcuts
+            .iter()
+            .find(|(_, shortcuts)| shortcuts.contains(shortcut))
+            .map(|(command, _)| *command)
+    }
+
+    /// Main shortcut of the command, shown in the menu.
+    pub fn shortcut_of(&self, command: Command) -> Option<&Shortcut> {
+        self.shortcuts.get(&command)?.first()
+    }
+}
+
+/// Mutable functions
+impl CommandRegistry {
+    /// Make the menu item with `id` trigger `command`.
+    pub fn register_menu_item(&mut self, id: u32, command: Command) {
+        self.menu_items.insert(id, command);
+    }
+
+    /// Make `shortcut` trigger `command`, on top of the shortcuts already bound to it.
+    /// The shortcut is taken away from any other command.
+    pub fn bind(&mut self, command: Command, shortcut: Shortcut) {
+        for shortcuts in self.shortcuts.values_mut() {
+            shortcuts.retain(|bound| bound != &shortcut);
+        }
+        self.shortcuts.entry(command).or_default().push(shortcut);
+    }
+
+    /// Remove every shortcut of `command`.
+    pub fn unbind(&mut self, command: Command) {
+        self.shortcuts.remove(&command);
+    }
+}
+
+/// Shortcuts bound when the registry is created, a command may have several.
+fn default_shortcuts() -> [(Command, Shortcut); 35] {
+    [
+        (Command::New, Shortcut::cmd("n")),
+        (Command::NewWindow, Shortcut::cmd_shift("n")),
+        (Command::Open, Shortcut::cmd("o")),
+        (Command::OpenRecent, Shortcut::cmd_shift("o")),
+        (Command::Save, Shortcut::cmd("s")),
+        (Command::SaveAs, Shortcut::cmd_shift("s")),
+        (Command::ExportSvg, Shortcut::cmd_shift("e")),
+        (Command::Undo, Shortcut::cmd("z")),
+        (Command::Redo, Shortcut::cmd_shift("z")),
+        (Command::Redo, Shortcut::cmd("y")),
+        (Command::Cut, Shortcut::cmd("x")),
+        (Command::Copy, Shortcut::cmd("c")),
+        (Command::Paste, Shortcut::cmd("v")),
+        (Command::Delete, Shortcut::key(KeyCode::BackspaceOrDelete)),
+        (Command::Delete, Shortcut::key(KeyCode::FDelete)),
+        (Command::SelectAll, Shortcut::cmd_shift("a")),
+        (Command::ZoomIn, Shortcut::cmd("=")),
+        (Command::ZoomIn, Shortcut::cmd_shift("=")),
+        (Command::ZoomIn, Shortcut::cmd("+")),
+        (Command::ZoomOut, Shortcut::cmd("-")),
+        (Command::ResetZoom, Shortcut::cmd("0")),
+        (Command::InsertImage, Shortcut::cmd_shift("i")),
+        (Command::SelectTool, Shortcut::character("v")),
+        (Command::PenTool, Shortcut::character("p")),
+        (Command::EraserTool, Shortcut::character("e")),
+        (Command::PreciseEraserTool, Shortcut::shift("e")),
+        (Command::RectangleTool, Shortcut::character("r")),
+        (Command::EllipseTool, Shortcut::character("o")),
+        (Command::DiamondTool, Shortcut::character("d")),
+        (Command::LineTool, Shortcut::character("l")),
+        (Command::ArrowTool, Shortcut::character("a")),
+        (Command::ElbowArrowTool, Shortcut::shift("a")),
+        (Command::CurvedArrowTool, Shortcut::character("c")),
+        (Command::TextTool, Shortcut::character("t")),
+        (Command::Settings, Shortcut::cmd(",")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn command_names_are_unique_and_round_trip() {
+        let names: HashSet<_> = Command::ALL.into_iter().map(Command::name).collect();
+        assert_eq!(names.len(), Command::ALL.len());
+        for command in Command::ALL {
+            assert_eq!(Command::from_name(command.name()), Some(command));
+        }
+        assert_eq!(Command::from_name("file.unknown"), None);
+    }
+
+    #[test]
+    fn default_shortcuts_are_not_shared() {
+        let mut seen = HashSet::new();
+        for (command, shortcut) in default_shortcuts() {
+            assert!(seen.insert(shortcut.clone()), "{shortcut:?} bound twice");
+            assert_eq!(
+                CommandRegistry::default().shortcut(&shortcut),
+                Some(command)
+            );
+        }
+    }
+
+    #[test]
+    fn default_bindings() {
+        let registry = CommandRegistry::default();
+        assert_eq!(
+            registry.shortcut_of(Command::Save),
+            Some(&Shortcut::cmd("s"))
+        );
+        // The first shortcut of a command is the one shown in the menu
+        assert_eq!(
+            registry.shortcut_of(Command::Redo),
+            Some(&Shortcut::cmd_shift("z"))
+        );
+        assert_eq!(registry.shortcut(&Shortcut::cmd("y")), Some(Command::Redo));
+        assert_eq!(registry.shortcut_of(Command::About), None);
+    }
+
+    #[test]
+    fn pressed_keys_match_the_shortcuts() {
+        let registry = CommandRegistry::default();
+        let modifiers = KeyboardModifiers {
+            shift: true,
+            ..KeyboardModifiers::default()
+        };
+        // Characters typed with shift are uppercase
+        let pressed = Shortcut::pressed(&KeyCode::Character("E".into(), false), &modifiers);
+        assert_eq!(
+            registry.shortcut(&pressed),
+            Some(Command::PreciseEraserTool)
+        );
+    }
+
+    #[test]
+    fn bind_takes_the_shortcut_away_from_other_commands() {
+        let mut registry = CommandRegistry::default();
+        registry.bind(Command::About, Shortcut::cmd("s"));
+        assert_eq!(registry.shortcut(&Shortcut::cmd("s")), Some(Command::About));
+        assert_eq!(
+            registry.shortcut_of(Command::About),
+            Some(&Shortcut::cmd("s"))
+        );
+        assert_eq!(registry.shortcut_of(Command::Save), None);
+
+        // Further shortcuts are added after the main one
+        registry.bind(Command::About, Shortcut::cmd("q"));
+        assert_eq!(
+            registry.shortcut_of(Command::About),
+            Some(&Shortcut::cmd("s"))
+        );
+        assert_eq!(registry.shortcut(&Shortcut::cmd("q")), Some(Command::About));
+    }
+
+    #[test]
+    fn unbind_removes_every_shortcut() {
+        let mut registry = CommandRegistry::default();
+        registry.unbind(Command::Redo);
+        assert_eq!(registry.shortcut_of(Command::Redo), None);
+        assert_eq!(registry.shortcut(&Shortcut::cmd_shift("z")), None);
+        assert_eq!(registry.shortcut(&Shortcut::cmd("y")), None);
+        // The other commands keep theirs
+        assert_eq!(registry.shortcut(&Shortcut::cmd("z")), Some(Command::Undo));
+    }
+
+    #[test]
+    fn menu_items_trigger_their_command() {
+        let mut registry = CommandRegistry::default();
+        registry.register_menu_item(7, Command::Open);
+        assert_eq!(registry.menu_item(7), Some(Command::Open));
+        assert_eq!(registry.menu_item(8), None);
+    }
+}
@@ -1,29 +1,69 @@
+use std::{collections::HashMap, path::Path};
+
 use anyhow::bail;
 use glam::Vec2;
-use log::info;
-use renderer::Renderer;
+use log::{debug, trace, warn};
+use renderer::{kurbo, Camera, Frame, Renderer};
 use winit::{
-    dpi::{PhysicalPosition, PhysicalSize},
-    event::{KeyEvent as WKeyEvent, MouseScrollDelta, TouchPhase},
+    dpi::{LogicalPosition, PhysicalPosition, PhysicalSize},
+    event::{Force, Ime, KeyEvent as WKeyEvent, MouseScrollDelta, Touch, TouchPhase},
     keyboard::ModifiersState,
     platform::modifier_supplement::KeyEventExtModifierSupplement,
-    window::WindowId,
+    window::{CursorGrabMode, Window, WindowId},
 };
 
 use crate::{
+    command::{Command, CommandRegistry, Shortcut},
     error::WindowsError,
-    event::{KeyEvent, KeyboardModifiers, MouseEvent, MouseTouchEvent, TouchInputEvent},
-    key_code::key_event_to_code,
+    event::{
+        KeyEvent, KeyboardModifiers, MouseButton, MouseEvent, MouseTouchEvent, Stylus,
+        TextInputEvent, TouchInputEvent,
+    },
+    gesture::{Gesture, GestureRecognizer},
+    handler::Handler,
+    key_code::{key_event_to_code, KeyCode},
     menu::{MenuManager, SubMenuKind},
     window::WindowManager,
 };
 
+/// Zoom factor applied by a single zoom in or zoom out command.
+const ZOOM_STEP: f64 = 1.25;
+/// Logical pixels scrolled by a single line of a mouse wheel.
+const SCROLL_LINE_HEIGHT: f32 = 20.0;
+/// Exponential zoom rate per scrolled logical pixel.
+const ZOOM_PER_SCROLL: f32 = 0.005;
+/// Id of the touch events emitted by the mouse, fingers get the next ids.
+const MOUSE_TOUCH_ID: usize = 0;
+
+/// A finger down on the touch screen.
+#[derive(Clone, Copy, Debug)]
+struct Finger {
+    /// Id of the touch events of the finger.
+    id: usize,
+    /// Last position of the finger in logical pixels.
+    position: Vec2,
+}
+
 #[derive(Default)]
 pub struct Context {
     pub mouse_pos: Vec2,
     has_rendered: bool,
+    /// Whether the primary mouse button is held down, mouse moves are only reported as touch moves while it is.
+    mouse_down: bool,
+    /// Whether the mouse moves drag the board around instead of being reported as touch moves.
+    panning: bool,
+    /// Whether the space key is held down, a primary button drag then pans the board.
+    space_down: bool,
+    /// Last pressure reported by a force sensing touchpad, sent along with the mouse drags.
+    touchpad_pressure: Option<f32>,
+    /// Fingers down on the touch screen, keyed by the platform touch id.
+    fingers: HashMap<u64, Finger>,
+    /// Turns the movement of several fingers into camera gestures.
+    gestures: GestureRecognizer,
+    /// Viewpoint over the board for each window.
+    cameras: HashMap<WindowId, Camera>,
     window_size: PhysicalSize<u32>,
-    /// Lock the cursor in position. Useful for dragging knobs.
+    /// Keep the cursor within the window while the application drags something, such as selection handles.
     grab_cursor: bool,
 
     /// Value of grab_cursor before processing event.
@@ -31,17 +71,44 @@ pub struct Context {
 
     /// Keyboard modifiers state.
     pub key_mods: KeyboardModifiers,
+    /// Whether the input methods may compose text, while the application edits text.
+    ime_allowed: bool,
+    /// Whether an input method is composing text, the keys pressed meanwhile are meant for it.
+    composing: bool,
+
+    /// Commands triggered by the menu items and keyboard shortcuts.
+    pub commands: CommandRegistry,
 }
 
 impl Context {
-    /// Process a UI event from window to the renderer.
-    pub fn process_touch(&mut self, event: &TouchInputEvent) {
-        println!("Process mouse touch event {event:?}");
+    /// Forward a touch event to the application, redraw the window if the content changed.
+    pub fn process_touch(
+        &mut self,
+        handler: &mut dyn Handler,
+        window: &Window,
+        event: &TouchInputEvent,
+    ) {
+        let camera = self.camera(&window.id());
+        if handler.touch(&event.to_world(&camera), &self.key_mods, &camera) {
+            window.request_redraw();
+        }
+        // The next mouse drag reports the pressure afresh, it may not come from the touchpad
+        if event.id() == MOUSE_TOUCH_ID
+            && matches!(
+                event,
+                TouchInputEvent::End { .. } | TouchInputEvent::Cancel { .. }
+            )
+        {
+            self.touchpad_pressure = None;
+        }
+        self.grab_cursor = handler.dragging();
+        self.update_cursor_grab(window);
+        self.update_ime(window, handler);
         self.has_rendered = true;
     }
 
     pub fn process_mouse_event(&mut self, event: &MouseEvent) {
-        println!("Process mouse move event {event:?}",);
+        trace!("Process mouse event {event:?}");
 
         // Check if the mouse move event is within the bounds of any active window.
 
@@ -49,16 +116,56 @@ impl Context {
     }
 
     pub fn process_key_event(&mut self, event: &KeyEvent) {
-        println!("Process mouse move event {event:?}",);
+        trace!("Process key event {event:?}");
 
         self.has_rendered = true;
     }
 
+    /// Viewpoint over the board of the window.
+    pub fn camera(&self, window_id: &WindowId) -> Camera {
+        self.cameras.get(window_id).copied().unwrap_or_default()
+    }
+
+    /// Run an application command against the current window.
+    /// Commands the window does not handle itself are forwarded to the application.
+    pub fn command(
+        &mut self,
+        wm: &WindowManager,
+        handler: &mut dyn Handler,
+        command: Command,
+    ) -> anyhow::Result<()> {
+        let window = wm.current_window()?;
+        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+        let center = kurbo::Point::new(size.width / 2.0, size.height / 2.0);
+        let camera = self.cameras.entry(window.id()).or_default();
+        let redraw = match command {
+            Command::ZoomIn => {
+                camera.zoom_at(center, ZOOM_STEP);
+                true
+            }
+            Command::ZoomOut => {
+                camera.zoom_at(center, 1.0 / ZOOM_STEP);
+                true
+            }
+            Command::ResetZoom => {
+                camera.set_zoom_at(center, 1.0);
+                true
+            }
+            _ => handler.command(command, camera),
+        };
+        if redraw {
+            window.request_redraw();
+        }
+        self.update_ime(window, handler);
+        Ok(())
+    }
+
     // Update the UI of the window
     pub fn render(
         &mut self,
         wm: &mut WindowManager,
         renderer: &mut Renderer,
+        handler: &mut dyn Handler,
         window_id: &WindowId,
     ) -> anyhow::Result<()> {
         let Some(window) = wm.window(window_id) else {
@@ -66,8 +173,14 @@ impl Context {
          };
 
         let PhysicalSize { width, height } = window.inner_size();
-        let id = u64::from(*window_id);
-        renderer.render(id, width, height, window.scale_factor())?;
+        let frame = Frame {
+            window_id: u64::from(*window_id),
+            width,
+            height,
+            scale_factor: window.scale_factor(),
+            camera: self.camera(window_id),
+        };
+        handler.render(renderer, &frame)?;
         self.has_rendered = true;
         Ok(())
     }
@@ -97,7 +210,6 @@ impl Context {
         wm: &mut WindowManager,
         renderer: &mut Renderer,
         window_id: &WindowId,
-        scale_factor: f64,
         size: &mut PhysicalSize<u32>,
     ) -> anyhow::Result<()> {
         // the OS suggested size, We have already told the OS about our resize constraints,
@@ -106,18 +218,10 @@ impl Context {
             bail!(WindowsError::WindowStore(*window_id));
         };
 
-        let window_size = window.inner_size();
-
-        let new_width = size.width as f64 / scale_factor;
-        let new_height = size.height as f64 / scale_factor;
-        if approx::relative_eq!(window_size.width as f64, new_width)
-            || approx::relative_eq!(window_size.height as f64, new_height)
-        {
-            return Ok(());
-        }
-
+        // The pixel buffer holds the frame in physical pixels, the whole frame is painted again
+        // as the items scale along
         let id = u64::from(window.id());
-        renderer.update(id, new_width as u32, new_height as u32)?;
+        renderer.update(id, size.width, size.height)?;
         window.request_redraw();
         self.has_rendered = true;
         Ok(())
@@ -147,20 +251,59 @@ impl Context {
     pub fn key_input(
         &mut self,
         wm: &mut WindowManager,
+        handler: &mut dyn Handler,
         window_id: &WindowId,
         event: &WKeyEvent,
     ) -> anyhow::Result<()> {
-        if wm.window(window_id).is_none() {
+        let Some(window) = wm.window(window_id) else {
             bail!(WindowsError::WindowStore(*window_id));
-        }
+        };
 
         let code = key_event_to_code(event.key_without_modifiers(), &self.key_mods);
 
-        let event = match event.state {
+        let key_event = match event.state {
             winit::event::ElementState::Pressed => KeyEvent::Down(code, event.repeat),
             winit::event::ElementState::Released => KeyEvent::Up(code),
         };
-        self.process_key_event(&event);
+        match &key_event {
+            // Space types a space in the text edited, it pans the board otherwise
+            KeyEvent::Down(KeyCode::Space, _) => {
+                self.space_down = handler.text_cursor().is_none();
+            }
+            KeyEvent::Up(KeyCode::Space) => self.space_down = false,
+            _ => {}
+        }
+        self.process_key_event(&key_event);
+
+        // Keys pressed while an input method composes text are meant for it
+        let KeyEvent::Down(code, _) = &key_event else {
+            return Ok(());
+        };
+        if self.composing {
+            return Ok(());
+        }
+        // Typed text goes to the text edited, the keys typing it trigger no shortcut
+        let typed = event.text.as_deref().filter(|text| {
+            handler.text_cursor().is_some()
+                && !self.key_mods.cmd_or_ctrl()
+                && !text.chars().any(char::is_control)
+        });
+        let used = match typed {
+            Some(text) => {
+                handler.text_input(&TextInputEvent::Commit(text.to_owned()));
+                true
+            }
+            None => handler.key(code, &self.key_mods),
+        };
+        if used {
+            window.request_redraw();
+            self.update_ime(window, handler);
+        } else {
+            let shortcut = Shortcut::pressed(code, &self.key_mods);
+            if let Some(command) = self.commands.shortcut(&shortcut) {
+                self.command(wm, handler, command)?;
+            }
+        }
 
         Ok(())
     }
@@ -184,35 +327,190 @@ impl Context {
         Ok(())
     }
 
-    /// User have touched into the window
+    /// Forward the text composed with an input method to the application.
+    pub fn ime(
+        &mut self,
+        wm: &mut WindowManager,
+        handler: &mut dyn Handler,
+        window_id: &WindowId,
+        ime: Ime,
+    ) -> anyhow::Result<()> {
+        let Some(window) = wm.window(window_id) else {
+            bail!(WindowsError::WindowStore(*window_id));
+        };
+        let event = match ime {
+            Ime::Enabled => None,
+            Ime::Preedit(text, cursor) => {
+                self.composing = !text.is_empty();
+                Some(TextInputEvent::Preedit { text, cursor })
+            }
+            Ime::Commit(text) => {
+                self.composing = false;
+                Some(TextInputEvent::Commit(text))
+            }
+            // Whatever was being composed is dropped
+            Ime::Disabled => {
+                self.composing = false;
+                Some(TextInputEvent::Preedit {
+                    text: String::new(),
+                    cursor: None,
+                })
+            }
+        };
+        if let Some(event) = event {
+            if handler.text_input(&event) {
+                window.request_redraw();
+            }
+        }
+        self.update_ime(window, handler);
+        Ok(())
+    }
+
+    /// Record the pressure of a force sensing touchpad, from `0.0` to `1.0`.
+    /// - **macOS:** Only platform reporting it.
+    pub fn touchpad_pressure(&mut self, pressure: f32) {
+        self.touchpad_pressure = Some(pressure.clamp(0.0, 1.0));
+    }
+
+    /// User have touched into the window, every finger is tracked separately through its touch id.
     /// - **macOS:** Unsupported.
     pub fn touch(
         &mut self,
         wm: &mut WindowManager,
+        handler: &mut dyn Handler,
         window_id: &WindowId,
-        phase: TouchPhase,
-        location: PhysicalPosition<f64>,
+        touch: &Touch,
     ) -> anyhow::Result<()> {
+        let Touch {
+            phase,
+            location,
+            force,
+            id: touch_id,
+            ..
+        } = *touch;
         let Some(window) = wm.window(window_id) else {
             bail!(WindowsError::WindowStore(*window_id));
         };
 
         let scale = window.scale_factor() as f32;
         let position = Vec2::new(location.x as f32 / scale, location.y as f32 / scale);
-        // TODO(Quadri): Delta should compare to prev position
-        let delta = position;
 
-        let event = match phase {
-            TouchPhase::Started => TouchInputEvent::Begin { id: 0, position },
-            TouchPhase::Moved => TouchInputEvent::Move {
-                id: 0,
-                position,
-                delta,
+        let stylus = force.map(|force| Stylus {
+            pressure: force.normalized() as f32,
+            altitude: match force {
+                Force::Calibrated { altitude_angle, .. } => {
+                    altitude_angle.map(|angle| angle as f32)
+                }
+                Force::Normalized(_) => None,
             },
-            TouchPhase::Ended | TouchPhase::Cancelled => TouchInputEvent::End { id: 0, position },
+        });
+
+        let finger = self.fingers.get(&touch_id).copied();
+        let event = match (phase, finger) {
+            // A move of an unknown finger happens when the touch started outside of the window
+            (TouchPhase::Started, _) | (TouchPhase::Moved, None) => {
+                // A finger starting again without ending keeps its id
+                let id = finger.map_or_else(|| self.free_finger_id(), |finger| finger.id);
+                self.fingers.insert(touch_id, Finger { id, position });
+                TouchInputEvent::Begin {
+                    id,
+                    position,
+                    stylus,
+                }
+            }
+            (TouchPhase::Moved, Some(finger)) => {
+                self.fingers.insert(touch_id, Finger { position, ..finger });
+                TouchInputEvent::Move {
+                    id: finger.id,
+                    position,
+                    delta: position - finger.position,
+                    stylus,
+                }
+            }
+            (TouchPhase::Ended, Some(finger)) => {
+                self.fingers.remove(&touch_id);
+                TouchInputEvent::End {
+                    id: finger.id,
+                    position,
+                }
+            }
+            (TouchPhase::Cancelled, Some(finger)) => {
+                self.fingers.remove(&touch_id);
+                TouchInputEvent::Cancel {
+                    id: finger.id,
+                    position,
+                }
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, None) => return Ok(()),
         };
 
-        self.process_touch(&event);
+        let was_gesture = self.gestures.is_active();
+        let gestures = self.gestures.touch(&event);
+        if !was_gesture && self.gestures.is_active() {
+            // The fingers already down were reported as touches, the application abandons them
+            let cancelled: Vec<_> = self
+                .gestures
+                .fingers()
+                .iter()
+                .filter(|(id, _)| *id != event.id())
+                .map(|(id, position)| TouchInputEvent::Cancel {
+                    id: *id,
+                    position: *position,
+                })
+                .collect();
+            for cancel in &cancelled {
+                self.process_touch(handler, window, cancel);
+            }
+        }
+        if was_gesture || self.gestures.is_active() {
+            for gesture in &gestures {
+                self.gesture(handler, window, gesture);
+            }
+            return Ok(());
+        }
+
+        self.process_touch(handler, window, &event);
+        Ok(())
+    }
+
+    /// Zoom around the cursor with a trackpad pinch, positive `delta` zooms in.
+    /// - **macOS:** Only platform reporting it.
+    pub fn touchpad_magnify(
+        &mut self,
+        wm: &mut WindowManager,
+        handler: &mut dyn Handler,
+        window_id: &WindowId,
+        delta: f64,
+    ) -> anyhow::Result<()> {
+        let Some(window) = wm.window(window_id) else {
+            bail!(WindowsError::WindowStore(*window_id));
+        };
+        let gesture = Gesture::Pinch {
+            center: self.mouse_pos,
+            scale: delta.exp() as f32,
+        };
+        self.gesture(handler, window, &gesture);
+        Ok(())
+    }
+
+    /// Hand a file dropped on the window over to the application, at the last known cursor position.
+    pub fn dropped_file(
+        &mut self,
+        wm: &mut WindowManager,
+        handler: &mut dyn Handler,
+        window_id: &WindowId,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        let Some(window) = wm.window(window_id) else {
+            bail!(WindowsError::WindowStore(*window_id));
+        };
+        let position = self
+            .camera(window_id)
+            .to_world(to_kurbo_point(self.mouse_pos));
+        if handler.dropped_file(path, position) {
+            window.request_redraw();
+        }
+        self.update_ime(window, handler);
         Ok(())
     }
 
@@ -220,6 +518,7 @@ impl Context {
     pub fn cursor_move(
         &mut self,
         wm: &mut WindowManager,
+        handler: &mut dyn Handler,
         window_id: &WindowId,
         position: PhysicalPosition<f64>,
     ) -> anyhow::Result<()> {
@@ -229,9 +528,26 @@ impl Context {
 
         let scale = window.scale_factor() as f32;
         let position = Vec2::new(position.x as f32 / scale, position.y as f32 / scale);
+        let delta = position - self.mouse_pos;
 
         self.mouse_pos = position;
 
+        if self.panning {
+            self.cameras
+                .entry(*window_id)
+                .or_default()
+                .pan(to_kurbo_vec2(delta));
+            window.request_redraw();
+        } else if self.mouse_down {
+            let event = TouchInputEvent::Move {
+                id: MOUSE_TOUCH_ID,
+                position,
+                delta,
+                stylus: self.mouse_stylus(),
+            };
+            self.process_touch(handler, window, &event);
+        }
+
         Ok(())
     }
 
@@ -242,18 +558,24 @@ impl Context {
         delta: MouseScrollDelta,
     ) -> anyhow::Result<()> {
         // TODO(Quadri): Check if the current window supports scrolling with its content.
-        if wm.window(window_id).is_none() {
+        let Some(window) = wm.window(window_id) else {
             bail!(WindowsError::WindowStore(*window_id));
-        }
+        };
 
-        let event = match delta {
-            MouseScrollDelta::LineDelta(x, y) => MouseEvent::Scroll(Vec2::new(x, y)),
-            MouseScrollDelta::PixelDelta(pos) => {
-                MouseEvent::Scroll(Vec2::new(pos.x as f32, pos.y as f32))
-            }
+        // Mouse wheels zoom, trackpads pan unless the zoom modifier is held
+        let scale = window.scale_factor() as f32;
+        let (event, zoom) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (
+                MouseEvent::Scroll(Vec2::new(x, y) * SCROLL_LINE_HEIGHT),
+                true,
+            ),
+            MouseScrollDelta::PixelDelta(pos) => (
+                MouseEvent::Scroll(Vec2::new(pos.x as f32, pos.y as f32) / scale),
+                self.key_mods.control || self.key_mods.command,
+            ),
         };
 
-        self.process_mouse_event(&event);
+        self.scroll(window, &event, zoom);
 
         Ok(())
     }
@@ -261,26 +583,57 @@ impl Context {
     pub fn mouse_input(
         &mut self,
         wm: &mut WindowManager,
+        handler: &mut dyn Handler,
         window_id: &WindowId,
         event: &MouseTouchEvent,
     ) -> anyhow::Result<()> {
         let Some(window) = wm.window(window_id) else {
             bail!(WindowsError::WindowStore(*window_id));
         };
+        let pan_start = matches!(event, MouseTouchEvent::Down(MouseButton::Middle))
+            || (self.space_down && matches!(event, MouseTouchEvent::Down(MouseButton::Left)));
+        if pan_start {
+            self.panning = true;
+            return Ok(());
+        }
+        if self.panning
+            && matches!(
+                event,
+                MouseTouchEvent::Up(MouseButton::Left | MouseButton::Middle)
+            )
+        {
+            self.panning = false;
+            return Ok(());
+        }
+
         let touch = match event {
-            MouseTouchEvent::Down(_) => TouchInputEvent::Begin {
-                id: 0,
-                position: self.mouse_pos,
-            },
-            MouseTouchEvent::Up(_) => TouchInputEvent::End {
-                id: 0,
-                position: self.mouse_pos,
-            },
+            MouseTouchEvent::Down(MouseButton::Left) => {
+                self.mouse_down = true;
+                TouchInputEvent::Begin {
+                    id: MOUSE_TOUCH_ID,
+                    position: self.mouse_pos,
+                    stylus: self.mouse_stylus(),
+                }
+            }
+            MouseTouchEvent::Up(MouseButton::Left) => {
+                // The press may have started outside of the window
+                if !self.mouse_down {
+                    return Ok(());
+                }
+                self.mouse_down = false;
+                TouchInputEvent::End {
+                    id: MOUSE_TOUCH_ID,
+                    position: self.mouse_pos,
+                }
+            }
+            MouseTouchEvent::Down(MouseButton::Right) => {
+                MenuManager::show_context_menu(window, &SubMenuKind::Custom, self.mouse_pos);
+                return Ok(());
+            }
+            MouseTouchEvent::Down(_) | MouseTouchEvent::Up(_) => return Ok(()),
         };
 
-        MenuManager::show_context_menu(window, &SubMenuKind::Custom, self.mouse_pos);
-
-        self.process_touch(&touch);
+        self.process_touch(handler, window, &touch);
         Ok(())
     }
 
@@ -289,32 +642,8 @@ impl Context {
         if self.mouse_pos == delta {
             return Ok(());
         }
-        let event = TouchInputEvent::Move {
-            id: 0,
-            position: self.mouse_pos,
-            delta,
-        };
-        self.process_touch(&event);
-
-        // TODO(Quadri): Grab cursor should set when mouse down happens on any element/view that accepts mouse drag
-        if self.grab_cursor && !self.prev_grab_cursor {
-            info!("grabbing cursor");
-            window
-                .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                .or_else(|_e| window.set_cursor_grab(winit::window::CursorGrabMode::Confined))
-                .unwrap();
-            window.set_cursor_visible(false);
-        }
-
-        if !self.grab_cursor && self.prev_grab_cursor {
-            info!("releasing cursor");
-            window
-                .set_cursor_grab(winit::window::CursorGrabMode::None)
-                .unwrap();
-            window.set_cursor_visible(true);
-        }
 
-        self.prev_grab_cursor = self.grab_cursor;
+        self.update_cursor_grab(window);
         Ok(())
     }
 
@@ -323,23 +652,24 @@ impl Context {
     pub fn update(&mut self, wm: &WindowManager, renderer: &mut Renderer) -> anyhow::Result<()> {
         let window = wm.current_window()?;
 
+        // The pixel buffer holds the frame in physical pixels.
+        // Resizing drops what the renderer painted, so only resize when needed to keep redraws incremental.
         let window_size = window.inner_size();
-        let scale = window.scale_factor() as f32;
-        let width = window_size.width as f32 / scale;
-        let height = window_size.height as f32 / scale;
-
-        // If the window size has changed, force a re-layout.
         if window_size != self.window_size {
             self.window_size = window_size;
+            let id = u64::from(window.id());
+            renderer.update(id, window_size.width, window_size.height)?;
+            window.request_redraw();
+            self.has_rendered = true;
         }
-        let id = u64::from(window.id());
-        renderer.update(id, width as u32, height as u32)?;
 
         // TODO(Quadri): perform re-layout of views
 
-        // Update the current window
-        window.request_redraw();
-        self.has_rendered = true;
+        // Changes made by the input request a redraw as they happen, only the tiles rasterized
+        // in the background are left to show
+        if renderer.rasterizing() {
+            window.request_redraw();
+        }
 
         Ok(())
     }
@@ -354,6 +684,8 @@ impl Context {
         // TODO(Quadri): Handle switching focus between closed window to main window
         // This drops the window, causing it to close.
         wm.remove(window_id);
+        self.cameras.remove(window_id);
+        self.fingers.clear();
         let id = u64::from(*window_id);
         renderer.remove(id)?;
 
@@ -362,3 +694,93 @@ impl Context {
         Ok(())
     }
 }
+
+/// Private functions
+impl Context {
+    /// Move the window camera with a gesture, then let the application know about it.
+    fn gesture(&mut self, handler: &mut dyn Handler, window: &Window, gesture: &Gesture) {
+        let camera = self.cameras.entry(window.id()).or_default();
+        match *gesture {
+            Gesture::Pan { delta } => camera.pan(to_kurbo_vec2(delta)),
+            Gesture::Pinch { center, scale } => {
+                camera.zoom_at(to_kurbo_point(center), f64::from(scale));
+            }
+        }
+        handler.gesture(gesture, camera);
+        window.request_redraw();
+    }
+
+    /// Smallest touch event id not used by the mouse or by a finger down.
+    fn free_finger_id(&self) -> usize {
+        (MOUSE_TOUCH_ID + 1..)
+            .find(|id| self.fingers.values().all(|finger| finger.id != *id))
+            .unwrap_or_default()
+    }
+
+    /// Pressure of the mouse drags, only known on force sensing touchpads.
+    fn mouse_stylus(&self) -> Option<Stylus> {
+        self.touchpad_pressure.map(|pressure| Stylus {
+            pressure,
+            altitude: None,
+        })
+    }
+
+    /// Confine the cursor to the window when a drag starts and release it when the drag ends.
+    /// The cursor stays visible since the dragged elements follow it.
+    fn update_cursor_grab(&mut self, window: &Window) {
+        if self.grab_cursor && !self.prev_grab_cursor {
+            debug!("grabbing cursor");
+            // Platforms unable to confine the cursor, such as macOS, let it leave the window.
+            // It is never locked, a locked cursor stops reporting its position and the drag would freeze.
+            window.set_cursor_grab(CursorGrabMode::Confined).ok();
+        }
+
+        if !self.grab_cursor && self.prev_grab_cursor {
+            debug!("releasing cursor");
+            if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+                warn!("Unable to release the cursor: {err}");
+            }
+        }
+
+        self.prev_grab_cursor = self.grab_cursor;
+    }
+
+    /// Let the input methods compose text while the application edits text, with their candidates below the caret.
+    fn update_ime(&mut self, window: &Window, handler: &dyn Handler) {
+        let cursor = handler.text_cursor();
+        if cursor.is_some() != self.ime_allowed {
+            self.ime_allowed = cursor.is_some();
+            self.composing = false;
+            window.set_ime_allowed(self.ime_allowed);
+        }
+        if let Some(cursor) = cursor {
+            let position = self
+                .camera(&window.id())
+                .to_screen(kurbo::Point::new(cursor.x0, cursor.y1));
+            window.set_ime_position(LogicalPosition::new(position.x, position.y));
+        }
+    }
+
+    /// Zoom the window camera around the cursor, or pan it, with a scroll event.
+    fn scroll(&mut self, window: &Window, event: &MouseEvent, zoom: bool) {
+        let MouseEvent::Scroll(delta) = event else {
+            return;
+        };
+        let anchor = to_kurbo_point(self.mouse_pos);
+        let camera = self.cameras.entry(window.id()).or_default();
+        if zoom {
+            camera.zoom_at(anchor, f64::from(delta.y * ZOOM_PER_SCROLL).exp());
+        } else {
+            camera.pan(to_kurbo_vec2(*delta));
+        }
+        window.request_redraw();
+    }
+}
+
+fn to_kurbo_point(position: Vec2) -> kurbo::Point {
+    kurbo::Point::new(f64::from(position.x), f64::from(position.y))
+}
+
+fn to_kurbo_vec2(delta: Vec2) -> kurbo::Vec2 {
+    kurbo::Vec2::new(f64::from(delta.x), f64::from(delta.y))
+}
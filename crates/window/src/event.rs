@@ -1,29 +1,91 @@
 use glam::Vec2;
+use renderer::{kurbo::Point, Camera};
 
 use crate::key_code::KeyCode;
 
+/// Pen or finger pressure and tilt, for devices reporting them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stylus {
+    /// How hard the surface is pressed, from `0.0` to `1.0`.
+    pub pressure: f32,
+    /// Angle in radians between the stylus and the surface, `PI / 2` when perpendicular.
+    /// `None` when the device does not report it.
+    pub altitude: Option<f32>,
+}
+
 /// Universal user touch input interface event
 #[derive(Clone, Debug)]
 pub enum TouchInputEvent {
     /// Touch event, or mouse down.
-    Begin { id: usize, position: Vec2 },
+    Begin {
+        id: usize,
+        position: Vec2,
+        stylus: Option<Stylus>,
+    },
     /// Touch moved or mouse moved while down.
     Move {
         id: usize,
         position: Vec2,
         delta: Vec2,
+        stylus: Option<Stylus>,
     },
     /// Touch went up or mouse button released.
     End { id: usize, position: Vec2 },
+    /// Touch taken over by a gesture or cancelled by the system at its last `position`,
+    /// what it was doing is abandoned.
+    Cancel { id: usize, position: Vec2 },
 }
 
 impl TouchInputEvent {
+    /// Id of the finger or pointer the event comes from, the same from begin to end.
+    pub fn id(&self) -> usize {
+        match self {
+            TouchInputEvent::Begin { id, .. }
+            | TouchInputEvent::Move { id, .. }
+            | TouchInputEvent::End { id, .. }
+            | TouchInputEvent::Cancel { id, .. } => *id,
+        }
+    }
+
+    /// Pressure and tilt of the touch, `None` when the device does not report them.
+    pub fn stylus(&self) -> Option<Stylus> {
+        match self {
+            TouchInputEvent::Begin { stylus, .. } | TouchInputEvent::Move { stylus, .. } => *stylus,
+            TouchInputEvent::End { .. } | TouchInputEvent::Cancel { .. } => None,
+        }
+    }
+
     pub fn _offset(&self, offset: Vec2) -> TouchInputEvent {
         let mut event = self.clone();
         match &mut event {
             TouchInputEvent::Begin { position, .. }
             | TouchInputEvent::Move { position, .. }
-            | TouchInputEvent::End { position, .. } => *position += offset,
+            | TouchInputEvent::End { position, .. }
+            | TouchInputEvent::Cancel { position, .. } => *position += offset,
+        }
+
+        event
+    }
+
+    /// Map the event from window to board coordinates as seen through `camera`.
+    pub fn to_world(&self, camera: &Camera) -> TouchInputEvent {
+        let to_world = |position: Vec2| {
+            let world = camera.to_world(Point::new(f64::from(position.x), f64::from(position.y)));
+            Vec2::new(world.x as f32, world.y as f32)
+        };
+        let mut event = self.clone();
+        match &mut event {
+            TouchInputEvent::Begin { position, .. }
+            | TouchInputEvent::End { position, .. }
+            | TouchInputEvent::Cancel { position, .. } => {
+                *position = to_world(*position);
+            }
+            TouchInputEvent::Move {
+                position, delta, ..
+            } => {
+                *position = to_world(*position);
+                *delta /= camera.zoom() as f32;
+            }
         }
 
         event
@@ -58,7 +120,8 @@ pub enum MouseEvent {
     Scroll(Vec2),
 }
 
-#[derive(Clone, Debug, Default)]
+/// Keyboard modifiers held down.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct KeyboardModifiers {
     pub shift: bool,
     pub control: bool,
@@ -66,6 +129,17 @@ pub struct KeyboardModifiers {
     pub command: bool,
 }
 
+impl KeyboardModifiers {
+    /// Whether the modifier of the application shortcuts is held down,
+    /// `Command` on macOS and `Control` everywhere else.
+    pub fn cmd_or_ctrl(&self) -> bool {
+        #[cfg(target_os = "macos")]
+        return self.command;
+        #[cfg(not(target_os = "macos"))]
+        return self.control;
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum KeyEvent {
     /// Emitted when a keyboard key is pressed.
@@ -73,3 +147,16 @@ pub enum KeyEvent {
     /// Emitted when a keyboard key is released.
     Up(KeyCode),
 }
+
+/// Text entered while the application edits text, with the keyboard layout and input method applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextInputEvent {
+    /// Text to insert at the caret, typed or the result of an input method composition.
+    Commit(String),
+    /// Text being composed with an input method, shown at the caret until committed.
+    /// An empty `text` clears the composition, `cursor` is the byte range selected within it.
+    Preedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+}
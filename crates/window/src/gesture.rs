@@ -0,0 +1,108 @@
+//! Recognition of the multi-finger gestures moving the board.
+
+use glam::Vec2;
+
+use crate::event::TouchInputEvent;
+
+/// Distance in logical pixels under which two fingers are too close to measure a pinch.
+const MIN_SPAN: f32 = 8.0;
+
+/// Camera change made with several fingers on a touch screen, or with a trackpad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// Drag of the board by `delta` logical pixels.
+    Pan { delta: Vec2 },
+    /// Zoom by `scale` keeping the `center` window position in place.
+    Pinch { center: Vec2, scale: f32 },
+}
+
+/// Turns the movement of two fingers into pan and pinch gestures.
+///
+/// A gesture starts when a second finger goes down and lasts until every finger is up,
+/// the fingers are not meant to be reported as touches meanwhile.
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    /// Touch id and last position of the fingers down, in the order they went down.
+    fingers: Vec<(usize, Vec2)>,
+    /// Whether a gesture is going on.
+    active: bool,
+}
+
+/// Reference functions
+impl GestureRecognizer {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Touch id and last position of the fingers down.
+    pub fn fingers(&self) -> &[(usize, Vec2)] {
+        &self.fingers
+    }
+
+    /// Positions of the two fingers making the gesture.
+    fn pair(&self) -> Option<(Vec2, Vec2)> {
+        match self.fingers.as_slice() {
+            [(_, first), (_, second), ..] => Some((*first, *second)),
+            _ => None,
+        }
+    }
+}
+
+/// Mutable functions
+impl GestureRecognizer {
+    /// Track the finger of the touch event, returns the gestures made by its movement.
+    pub fn touch(&mut self, event: &TouchInputEvent) -> Vec<Gesture> {
+        let id = event.id();
+        let index = self.fingers.iter().position(|(finger, _)| *finger == id);
+        match (event, index) {
+            (TouchInputEvent::Begin { position, .. }, Some(index)) => {
+                self.fingers[index].1 = *position;
+            }
+            (TouchInputEvent::Begin { position, .. }, None) => {
+                self.fingers.push((id, *position));
+                if self.fingers.len() > 1 {
+                    self.active = true;
+                }
+            }
+            (TouchInputEvent::Move { position, .. }, Some(index)) => {
+                let before = self.pair();
+                self.fingers[index].1 = *position;
+                // Only the first two fingers make the gesture
+                if self.active && index < 2 {
+                    if let (Some(before), Some(after)) = (before, self.pair()) {
+                        return gestures(before, after);
+                    }
+                }
+            }
+            (TouchInputEvent::End { .. } | TouchInputEvent::Cancel { .. }, Some(index)) => {
+                self.fingers.remove(index);
+                if self.fingers.is_empty() {
+                    self.active = false;
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+}
+
+/// Gestures moving the `before` positions of two fingers to the `after` ones.
+fn gestures(before: (Vec2, Vec2), after: (Vec2, Vec2)) -> Vec<Gesture> {
+    let mut gestures = Vec::new();
+    let center = (after.0 + after.1) / 2.0;
+    let delta = center - (before.0 + before.1) / 2.0;
+    if delta.length_squared() > f32::EPSILON {
+        gestures.push(Gesture::Pan { delta });
+    }
+
+    let span_before = before.1 - before.0;
+    let span = after.1 - after.0;
+    if span_before.length() < MIN_SPAN || span.length() < MIN_SPAN {
+        return gestures;
+    }
+    let scale = span.length() / span_before.length();
+    if (scale - 1.0).abs() > f32::EPSILON {
+        gestures.push(Gesture::Pinch { center, scale });
+    }
+    gestures
+}
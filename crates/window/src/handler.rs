@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use renderer::{
+    kurbo::{Point, Rect},
+    Camera, Frame, Renderer,
+};
+
+use crate::{
+    command::Command,
+    event::{KeyboardModifiers, TextInputEvent, TouchInputEvent},
+    gesture::Gesture,
+    key_code::KeyCode,
+};
+
+/// Hooks through which the window event loop drives the application.
+pub trait Handler {
+    /// Handle pointer input in board coordinates, seen through `camera` while the `modifiers` are held down.
+    /// Returns `true` when the window content needs to be redrawn.
+    fn touch(
+        &mut self,
+        event: &TouchInputEvent,
+        modifiers: &KeyboardModifiers,
+        camera: &Camera,
+    ) -> bool;
+
+    /// Notified of the gestures made on the window, they already moved the `camera` and the window is redrawn after.
+    fn gesture(&mut self, _gesture: &Gesture, _camera: &Camera) {}
+
+    /// Whether the application is dragging something, the cursor is then kept within the window.
+    fn dragging(&self) -> bool {
+        false
+    }
+
+    /// Handle a key pressed before it triggers any shortcut, such as to move the caret of the text edited.
+    /// Returns `true` when the key is used, the window content is then redrawn and no shortcut is triggered.
+    fn key(&mut self, _key: &KeyCode, _modifiers: &KeyboardModifiers) -> bool {
+        false
+    }
+
+    /// Handle text typed or composed while the application edits text,
+    /// returns `true` when the window content needs to be redrawn.
+    fn text_input(&mut self, _event: &TextInputEvent) -> bool {
+        false
+    }
+
+    /// Board area of the caret while the application edits text, `None` when no text is edited.
+    /// Input methods show their candidates next to it.
+    fn text_cursor(&self) -> Option<Rect> {
+        None
+    }
+
+    /// Handle a file dropped on the window at the board `position`, such as an image to place there.
+    /// Returns `true` when the window content needs to be redrawn.
+    fn dropped_file(&mut self, _path: &Path, _position: Point) -> bool {
+        false
+    }
+
+    /// Run an application command the window does not handle itself against the window showing `camera`,
+    /// returns `true` when the window content needs to be redrawn.
+    fn command(&mut self, command: Command, camera: &mut Camera) -> bool;
+
+    /// Asked before the last window closes, returns `false` to keep it open,
+    /// such as when unsaved changes would be lost.
+    fn close_requested(&mut self) -> bool {
+        true
+    }
+
+    /// Paint the application content into the window described by `frame`.
+    fn render(&mut self, renderer: &mut Renderer, frame: &Frame) -> anyhow::Result<()>;
+}
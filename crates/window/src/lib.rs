@@ -1,8 +1,11 @@
 //! Desktop application window management library. A wrapper around [winit](https://github.com/rust-windowing/winit) for handling window management
 
+mod command;
 mod context;
 mod error;
 mod event;
+mod gesture;
+mod handler;
 mod key_code;
 mod menu;
 mod run;
@@ -15,8 +18,14 @@ use renderer::Renderer;
 use run::winit_runner;
 use winit::event_loop::{EventLoop, EventLoopBuilder, EventLoopProxy};
 
+pub use command::{Command, CommandRegistry, Shortcut};
+pub use event::{KeyboardModifiers, Stylus, TextInputEvent, TouchInputEvent};
+pub use gesture::Gesture;
+pub use handler::Handler;
+pub use key_code::KeyCode;
+
 pub struct Windows {
-    event_loop: Rc<RefCell<EventLoop<()>>>,
+    event_loop: Rc<RefCell<EventLoop<Command>>>,
     wm: window::WindowManager,
     menu: MenuManager,
     ctx: context::Context,
@@ -26,7 +35,7 @@ pub struct Windows {
 
 impl Windows {
     pub fn new() -> anyhow::Result<Windows> {
-        let mut event_loop_builder = EventLoopBuilder::new();
+        let mut event_loop_builder = EventLoopBuilder::with_user_event();
 
         let menu = MenuManager::new(&mut event_loop_builder);
 
@@ -47,14 +56,16 @@ impl Windows {
         })
     }
 
-    pub fn proxy_handler(&self) -> EventLoopProxy<()> {
+    /// Proxy running commands from anywhere, including other threads, through `send_event`.
+    pub fn proxy_handler(&self) -> EventLoopProxy<Command> {
         self.event_loop.borrow_mut().create_proxy()
     }
 
-    pub fn run(mut self) -> anyhow::Result<()> {
+    /// Launch the event loop, the `handler` receives the window events until the app exits.
+    pub fn run<H: Handler + 'static>(mut self, handler: H) -> anyhow::Result<()> {
         self.wm.run()?;
-        self.menu.setup()?;
-        winit_runner(self);
+        self.menu.setup(&mut self.ctx.commands)?;
+        winit_runner(self, handler);
         Ok(())
     }
 }
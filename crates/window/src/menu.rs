@@ -4,7 +4,7 @@
 #![allow(dead_code)]
 #![allow(unreachable_code)]
 #![allow(clippy::unnecessary_wraps)]
-use std::{collections::HashMap, path::Path, process::Command, str};
+use std::{collections::HashMap, path::Path, process, str};
 
 use anyhow::{bail, Context};
 use glam::Vec2;
@@ -23,7 +23,11 @@ use winit::platform::macos::{EventLoopBuilderExtMacOS, WindowExtMacOS};
 use winit::platform::windows::{EventLoopBuilderExtWindows, WindowExtWindows};
 use winit::{event_loop::EventLoopBuilder, window::Window};
 
-use crate::error::WindowsError;
+use crate::{
+    command::{Command, CommandRegistry, Shortcut},
+    error::WindowsError,
+    key_code::KeyCode,
+};
 
 #[cfg(target_os = "macos")]
 pub const CMD_OR_CTRL: Modifiers = Modifiers::META;
@@ -39,6 +43,8 @@ pub enum SubMenuKind {
     Help,
     Window,
     View,
+    Insert,
+    Tools,
     Custom,
 }
 
@@ -50,7 +56,7 @@ pub struct MenuManager {
 }
 /// Constructor member
 impl MenuManager {
-    pub fn new(event_loop_builder: &mut EventLoopBuilder<()>) -> MenuManager {
+    pub fn new(event_loop_builder: &mut EventLoopBuilder<Command>) -> MenuManager {
         let menu_bar = Menu::new();
         #[cfg(target_os = "linux")]
         {
@@ -112,24 +118,31 @@ impl MenuManager {
         Ok(())
     }
 
-    pub fn setup(&mut self) -> anyhow::Result<()> {
+    /// Build the menu bar, the command of every menu item is registered in `commands`.
+    pub fn setup(&mut self, commands: &mut CommandRegistry) -> anyhow::Result<()> {
         // Add individual menu in order they appear in the menu bar
         #[cfg(target_os = "macos")]
         {
-            self.add_app_menu()?;
+            self.add_app_menu(commands)?;
         }
-        self.add_file_menu();
-        self.add_edit_menu();
-        self.add_view_menu();
+        self.add_file_menu(commands);
+        self.add_edit_menu(commands);
+        self.add_view_menu(commands);
+        self.add_insert_menu(commands);
+        self.add_tools_menu(commands);
         self.add_window_menu();
-        self.add_help_menu();
+        self.add_help_menu(commands);
         Ok(())
     }
 
-    pub fn listen(&self) {
-        if let Ok(event) = self.channel.try_recv() {
-            println!("listening to menu event {event:?}");
+    /// Poll the command of the last clicked menu item, if any.
+    pub fn listen(&self, commands: &CommandRegistry) -> Option<Command> {
+        let event = self.channel.try_recv().ok()?;
+        let command = commands.menu_item(event.id);
+        if command.is_none() {
+            log::debug!("No command for menu event {event:?}");
         }
+        command
     }
 
     pub fn show_context_menu(window: &Window, menu: &SubMenuKind, position: Vec2) {
@@ -189,7 +202,7 @@ impl MenuManager {
     }
 
     #[cfg(target_os = "macos")]
-    fn add_app_menu(&mut self) -> anyhow::Result<()> {
+    fn add_app_menu(&mut self, commands: &mut CommandRegistry) -> anyhow::Result<()> {
         let mut workspace_dir = get_workspace_dir()?;
         let icon_path = "/assets/icon.png";
         workspace_dir.push_str(icon_path);
@@ -201,11 +214,7 @@ impl MenuManager {
         let website = env!("CARGO_PKG_HOMEPAGE");
         let icon: Icon = load_app_icon(std::path::Path::new(&workspace_dir))?;
 
-        let settings = MenuItem::new(
-            "Settings",
-            true,
-            Some(Accelerator::new(Some(Modifiers::META), Code::Comma)),
-        );
+        let settings = command_item(commands, Command::Settings);
 
         let app = Submenu::with_items(
             app_name,
@@ -246,52 +255,14 @@ impl MenuManager {
         Ok(())
     }
 
-    fn add_file_menu(&mut self) {
-        // CmdOrCtrl+N
-        let new = MenuItem::new(
-            "New",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::KeyN)),
-        );
-        // CmdOrCtrl+Shift+N
-        let new_window = MenuItem::new(
-            "New Window",
-            true,
-            Some(Accelerator::new(
-                Some(CMD_OR_CTRL | Modifiers::SHIFT),
-                Code::KeyN,
-            )),
-        );
-        // CmdOrCtrl+O
-        let open = MenuItem::new(
-            "Open",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::KeyO)),
-        );
-        // CmdOrCtrl+Shift+O
-        let open_recent = MenuItem::new(
-            "Open Recent",
-            true,
-            Some(Accelerator::new(
-                Some(CMD_OR_CTRL | Modifiers::SHIFT),
-                Code::KeyO,
-            )),
-        );
-        // CmdOrCtrl+O
-        let save = MenuItem::new(
-            "Save",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::KeyS)),
-        );
-        // CmdOrCtrl+Shift+S
-        let save_as = MenuItem::new(
-            "Save",
-            true,
-            Some(Accelerator::new(
-                Some(CMD_OR_CTRL | Modifiers::SHIFT),
-                Code::KeyS,
-            )),
-        );
+    fn add_file_menu(&mut self, commands: &mut CommandRegistry) {
+        let new = command_item(commands, Command::New);
+        let new_window = command_item(commands, Command::NewWindow);
+        let open = command_item(commands, Command::Open);
+        let open_recent = command_item(commands, Command::OpenRecent);
+        let save = command_item(commands, Command::Save);
+        let save_as = command_item(commands, Command::SaveAs);
+        let export_svg = command_item(commands, Command::ExportSvg);
         let file = Submenu::with_items(
             "File",
             true,
@@ -305,6 +276,8 @@ impl MenuManager {
                 &save,
                 &save_as,
                 &PredefinedMenuItem::separator(),
+                &export_svg,
+                &PredefinedMenuItem::separator(),
                 &PredefinedMenuItem::close_window(Some("Close")),
             ],
         );
@@ -312,40 +285,19 @@ impl MenuManager {
         self.menus.insert(SubMenuKind::File, file);
     }
 
-    fn add_edit_menu(&mut self) {
-        // CmdOrCtrl+Z
-        let undo = MenuItem::new(
-            "Undo",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::KeyZ)),
-        );
-
-        // CmdOrCtrl+Shift+Z
-        let redo = MenuItem::new(
-            "Redo",
-            true,
-            Some(Accelerator::new(
-                Some(CMD_OR_CTRL | Modifiers::SHIFT),
-                Code::KeyZ,
-            )),
-        );
-
-        let delete = MenuItem::new("Delete", true, None);
-
-        // CmdOrCtrl+Shift+A
-        let select_all = MenuItem::new(
-            "Select All",
-            true,
-            Some(Accelerator::new(
-                Some(CMD_OR_CTRL | Modifiers::SHIFT),
-                Code::KeyA,
-            )),
-        );
-
-        let copy = PredefinedMenuItem::copy(None);
-        let cut = PredefinedMenuItem::cut(None);
-        let paste = PredefinedMenuItem::paste(None);
-
+    fn add_edit_menu(&mut self, commands: &mut CommandRegistry) {
+        let undo = command_item(commands, Command::Undo);
+        let redo = command_item(commands, Command::Redo);
+        let cut = command_item(commands, Command::Cut);
+        let copy = command_item(commands, Command::Copy);
+        let paste = command_item(commands, Command::Paste);
+        let delete = command_item(commands, Command::Delete);
+        let select_all = command_item(commands, Command::SelectAll);
+        let straight = command_item(commands, Command::StraightRouting);
+        let elbow = command_item(commands, Command::ElbowRouting);
+        let curved = command_item(commands, Command::CurvedRouting);
+        let start_head = command_item(commands, Command::NextStartArrowHead);
+        let end_head = command_item(commands, Command::NextEndArrowHead);
         let edit = Submenu::with_items(
             "Edit",
             true,
@@ -358,26 +310,22 @@ impl MenuManager {
                 &paste,
                 &delete,
                 &select_all,
+                &PredefinedMenuItem::separator(),
+                &straight,
+                &elbow,
+                &curved,
+                &start_head,
+                &end_head,
             ],
         );
         self.menu_bar.append(&edit);
         self.menus.insert(SubMenuKind::Edit, edit);
     }
 
-    fn add_view_menu(&mut self) {
-        // CmdOrCtrl++
-        let zoom_in = MenuItem::new(
-            "Zoom In",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::Equal)),
-        );
-        // CmdOrCtrl+-
-        let zoom_out = MenuItem::new(
-            "Zoom Out",
-            true,
-            Some(Accelerator::new(Some(CMD_OR_CTRL), Code::Minus)),
-        );
-        let zoom_reset = MenuItem::new("Reset Zoom", true, None);
+    fn add_view_menu(&mut self, commands: &mut CommandRegistry) {
+        let zoom_in = command_item(commands, Command::ZoomIn);
+        let zoom_out = command_item(commands, Command::ZoomOut);
+        let zoom_reset = command_item(commands, Command::ResetZoom);
         let view = Submenu::with_items(
             "View",
             true,
@@ -393,6 +341,49 @@ impl MenuManager {
         self.menus.insert(SubMenuKind::View, view);
     }
 
+    fn add_insert_menu(&mut self, commands: &mut CommandRegistry) {
+        let image = command_item(commands, Command::InsertImage);
+        let insert = Submenu::with_items("Insert", true, &[&image]);
+        self.menu_bar.append(&insert);
+        self.menus.insert(SubMenuKind::Insert, insert);
+    }
+
+    fn add_tools_menu(&mut self, commands: &mut CommandRegistry) {
+        let select = command_item(commands, Command::SelectTool);
+        let pen = command_item(commands, Command::PenTool);
+        let eraser = command_item(commands, Command::EraserTool);
+        let precise_eraser = command_item(commands, Command::PreciseEraserTool);
+        let rectangle = command_item(commands, Command::RectangleTool);
+        let ellipse = command_item(commands, Command::EllipseTool);
+        let diamond = command_item(commands, Command::DiamondTool);
+        let line = command_item(commands, Command::LineTool);
+        let arrow = command_item(commands, Command::ArrowTool);
+        let elbow_arrow = command_item(commands, Command::ElbowArrowTool);
+        let curved_arrow = command_item(commands, Command::CurvedArrowTool);
+        let text = command_item(commands, Command::TextTool);
+        let tools = Submenu::with_items(
+            "Tools",
+            true,
+            &[
+                &select,
+                &pen,
+                &eraser,
+                &precise_eraser,
+                &PredefinedMenuItem::separator(),
+                &rectangle,
+                &ellipse,
+                &diamond,
+                &line,
+                &arrow,
+                &elbow_arrow,
+                &curved_arrow,
+                &text,
+            ],
+        );
+        self.menu_bar.append(&tools);
+        self.menus.insert(SubMenuKind::Tools, tools);
+    }
+
     fn add_window_menu(&mut self) {
         // TODO(Quadri): Show list of available windows
         let current_window = CheckMenuItem::new("Oxdraw - current window", false, true, None);
@@ -412,12 +403,12 @@ impl MenuManager {
         self.menus.insert(SubMenuKind::Window, window);
     }
 
-    fn add_help_menu(&mut self) {
-        let feedback = MenuItem::new("Send feedback", true, None);
-        let help = MenuItem::new("Oxdraw help", true, None);
-        let shortcuts = MenuItem::new("Keyboard shortcuts", true, None);
-        let github = MenuItem::new("Github", true, None);
-        let about = MenuItem::new("About Oxdraw", true, None);
+    fn add_help_menu(&mut self, commands: &mut CommandRegistry) {
+        let feedback = command_item(commands, Command::SendFeedback);
+        let help = command_item(commands, Command::Help);
+        let shortcuts = command_item(commands, Command::KeyboardShortcuts);
+        let github = command_item(commands, Command::Github);
+        let about = command_item(commands, Command::About);
 
         let help = Submenu::with_items(
             "Help",
@@ -437,6 +428,81 @@ impl MenuManager {
     }
 }
 
+/// Menu item triggering `command`, showing its main shortcut.
+fn command_item(commands: &mut CommandRegistry, command: Command) -> MenuItem {
+    let accelerator = commands.shortcut_of(command).and_then(accelerator);
+    let item = MenuItem::new(command.label(), true, accelerator);
+    commands.register_menu_item(item.id(), command);
+    item
+}
+
+/// Menu accelerator of the shortcut, `None` for keys the menu cannot show.
+fn accelerator(shortcut: &Shortcut) -> Option<Accelerator> {
+    let code = match &shortcut.key {
+        KeyCode::Character(c, _) => match c.as_str() {
+            "a" => Code::KeyA,
+            "b" => Code::KeyB,
+            "c" => Code::KeyC,
+            "d" => Code::KeyD,
+            "e" => Code::KeyE,
+            "f" => Code::KeyF,
+            "g" => Code::KeyG,
+            "h" => Code::KeyH,
+            "i" => Code::KeyI,
+            "j" => Code::KeyJ,
+            "k" => Code::KeyK,
+            "l" => Code::KeyL,
+            "m" => Code::KeyM,
+            "n" => Code::KeyN,
+            "o" => Code::KeyO,
+            "p" => Code::KeyP,
+            "q" => Code::KeyQ,
+            "r" => Code::KeyR,
+            "s" => Code::KeyS,
+            "t" => Code::KeyT,
+            "u" => Code::KeyU,
+            "v" => Code::KeyV,
+            "w" => Code::KeyW,
+            "x" => Code::KeyX,
+            "y" => Code::KeyY,
+            "z" => Code::KeyZ,
+            "0" => Code::Digit0,
+            "1" => Code::Digit1,
+            "2" => Code::Digit2,
+            "3" => Code::Digit3,
+            "4" => Code::Digit4,
+            "5" => Code::Digit5,
+            "6" => Code::Digit6,
+            "7" => Code::Digit7,
+            "8" => Code::Digit8,
+            "9" => Code::Digit9,
+            "=" => Code::Equal,
+            "-" => Code::Minus,
+            "," => Code::Comma,
+            "." => Code::Period,
+            _ => return None,
+        },
+        KeyCode::BackspaceOrDelete => Code::Backspace,
+        KeyCode::FDelete => Code::Delete,
+        KeyCode::Enter => Code::Enter,
+        KeyCode::Escape => Code::Escape,
+        KeyCode::Tab => Code::Tab,
+        KeyCode::Space => Code::Space,
+        _ => return None,
+    };
+    let mut modifiers = Modifiers::empty();
+    if shortcut.cmd_or_ctrl {
+        modifiers |= CMD_OR_CTRL;
+    }
+    if shortcut.shift {
+        modifiers |= Modifiers::SHIFT;
+    }
+    if shortcut.alt {
+        modifiers |= Modifiers::ALT;
+    }
+    Some(Accelerator::new(Some(modifiers), code))
+}
+
 fn load_app_icon(path: &Path) -> anyhow::Result<Icon> {
     let (rgba, width, height) = {
         let image = image::open(path)?.into_rgba8();
@@ -455,7 +521,7 @@ fn load_app_icon(path: &Path) -> anyhow::Result<Icon> {
 fn get_workspace_dir() -> anyhow::Result<String> {
     let command = env!("CARGO");
     let args = ["locate-project", "--workspace", "--message-format=plain"];
-    let output = Command::new(command).args(args).output()?;
+    let output = process::Command::new(command).args(args).output()?;
 
     let cmd_line: String = format!("{command} {}", args.join(" "));
     let stdout = extract_stdout(&cmd_line, &output)?;
@@ -8,24 +8,28 @@ use glam::Vec2;
 use log::{error, info};
 use winit::{
     dpi::PhysicalSize,
-    event::{DeviceEvent, Event, StartCause, Touch, WindowEvent as WNWindowEvent},
+    event::{DeviceEvent, Event, StartCause, WindowEvent as WNWindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
 };
 
 use crate::{
+    command::Command,
     event::{MouseButton, MouseEvent, MouseTouchEvent},
-    FlowMode, Windows,
+    FlowMode, Handler, Windows,
 };
 
-fn run<F>(event_loop: EventLoop<()>, event_handler: F) -> !
+/// Time between the frames rendered while tiles are rasterized in the background.
+const TILE_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+fn run<F>(event_loop: EventLoop<Command>, event_handler: F) -> !
 where
-    F: 'static + FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: 'static + FnMut(Event<'_, Command>, &EventLoopWindowTarget<Command>, &mut ControlFlow),
 {
     event_loop.run(event_handler)
 }
 
 /// Winit event loop runner. Main window level actions are controlled through this function.
-pub fn winit_runner(mut windows: Windows) {
+pub fn winit_runner<H: Handler + 'static>(mut windows: Windows, mut handler: H) {
     info!("Entering winit event loop");
     let mut context = windows.ctx;
     let mut winit_state = windows.state;
@@ -59,8 +63,8 @@ pub fn winit_runner(mut windows: Windows) {
         .unwrap_or_else(|_| panic!("There is still a reference to event_loop, failed to unwrap"))
         .into_inner();
 
-    let event_handler = move |event: Event<'_, ()>,
-                              _target: &EventLoopWindowTarget<()>,
+    let event_handler = move |event: Event<'_, Command>,
+                              _target: &EventLoopWindowTarget<Command>,
                               control_flow: &mut ControlFlow| {
         // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
         // dispatched any events. This is ideal for games and similar applications.
@@ -106,6 +110,10 @@ pub fn winit_runner(mut windows: Windows) {
                         info!("Closing window {:?}", window_id);
                     }
                     WNWindowEvent::CloseRequested => {
+                        // Every window shows the same document, it is lost along with the last one
+                        if windows.wm.len() == 1 && !handler.close_requested() {
+                            return;
+                        }
                         info!("Closing window {:?}", window_id);
                         if let Err(err) =
                             context.remove(&mut windows.wm, &mut windows.renderer, &window_id)
@@ -125,17 +133,13 @@ pub fn winit_runner(mut windows: Windows) {
                             error!("Unable to resize the window: {}", err);
                         }
                     }
-                    WNWindowEvent::ScaleFactorChanged {
-                        scale_factor,
-                        new_inner_size,
-                    } => {
+                    WNWindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                         info!("Scaling the window");
 
                         if let Err(err) = context.scale_factor(
                             &mut windows.wm,
                             &mut windows.renderer,
                             &window_id,
-                            scale_factor,
                             new_inner_size,
                         ) {
                             error!("Unable to scale the window: {}", err);
@@ -151,17 +155,17 @@ pub fn winit_runner(mut windows: Windows) {
                             error!("Unable to focus window: {}", err);
                         }
                     }
-                    WNWindowEvent::Touch(Touch {
-                        phase, location, ..
-                    }) => {
+                    WNWindowEvent::Touch(touch) => {
                         if let Err(err) =
-                            context.touch(&mut windows.wm, &window_id, phase, location)
+                            context.touch(&mut windows.wm, &mut handler, &window_id, &touch)
                         {
                             error!("Unable to process touch event for window: {}", err);
                         }
                     }
                     WNWindowEvent::KeyboardInput { event, .. } => {
-                        if let Err(err) = context.key_input(&mut windows.wm, &window_id, &event) {
+                        if let Err(err) =
+                            context.key_input(&mut windows.wm, &mut handler, &window_id, &event)
+                        {
                             error!("Unable to process key received for window: {}", err);
                         }
                     }
@@ -172,21 +176,45 @@ pub fn winit_runner(mut windows: Windows) {
                             error!("Unable to process cursor move for window: {}", err);
                         }
                     }
-                    // TODO(Quadri): Implement IME event for key board input
-                    // WNWindowEvent::Ime(_) => {
-
-                    // }
+                    WNWindowEvent::Ime(ime) => {
+                        if let Err(err) =
+                            context.ime(&mut windows.wm, &mut handler, &window_id, ime)
+                        {
+                            error!("Unable to process input method event for window: {}", err);
+                        }
+                    }
+                    WNWindowEvent::DroppedFile(path) => {
+                        if let Err(err) =
+                            context.dropped_file(&mut windows.wm, &mut handler, &window_id, &path)
+                        {
+                            error!("Unable to process file dropped on window: {}", err);
+                        }
+                    }
                     WNWindowEvent::CursorMoved { position, .. } => {
                         // To avoid calling the hover system multiple times in one frame when multiple cursor moved
                         // events are received, instead we set a flag here and emit the MouseMove event during MainEventsCleared.
                         if !winit_state.cursor_moved {
                             winit_state.cursor_moved = true;
                         }
-                        if let Err(err) = context.cursor_move(&mut windows.wm, &window_id, position)
+                        if let Err(err) =
+                            context.cursor_move(&mut windows.wm, &mut handler, &window_id, position)
                         {
                             error!("Unable to process cursor move for window: {}", err);
                         }
                     }
+                    WNWindowEvent::TouchpadMagnify { delta, .. } => {
+                        if let Err(err) = context.touchpad_magnify(
+                            &mut windows.wm,
+                            &mut handler,
+                            &window_id,
+                            delta,
+                        ) {
+                            error!("Unable to process touchpad magnify for window: {}", err);
+                        }
+                    }
+                    WNWindowEvent::TouchpadPressure { pressure, .. } => {
+                        context.touchpad_pressure(pressure);
+                    }
                     WNWindowEvent::MouseWheel { delta, .. } => {
                         if let Err(err) = context.mouse_wheel(&mut windows.wm, &window_id, delta) {
                             error!("Unable to process mouse wheel for window: {}", err);
@@ -207,7 +235,9 @@ pub fn winit_runner(mut windows: Windows) {
                             winit::event::ElementState::Released => MouseTouchEvent::Up(button),
                         };
 
-                        if let Err(err) = context.mouse_input(&mut windows.wm, &window_id, &event) {
+                        if let Err(err) =
+                            context.mouse_input(&mut windows.wm, &mut handler, &window_id, &event)
+                        {
                             error!("Unable to process mouse input for window: {}", err);
                         }
                     }
@@ -223,6 +253,12 @@ pub fn winit_runner(mut windows: Windows) {
                     error!("Unable process mouse motion: {}", err);
                 }
             }
+            Event::UserEvent(command) => {
+                // Command sent programmatically through the event loop proxy
+                if let Err(err) = context.command(&windows.wm, &mut handler, command) {
+                    error!("Unable to run command {:?}: {}", command, err);
+                }
+            }
             Event::Suspended => {
                 info!("Winit app suspended");
                 winit_state.active = false;
@@ -253,6 +289,7 @@ pub fn winit_runner(mut windows: Windows) {
                 }
 
                 winit_state.last_update = Instant::now();
+                winit_state.timeout_reached = false;
                 if let Err(err) = context.update(&windows.wm, &mut windows.renderer) {
                     error!("error : {}", err);
                 };
@@ -264,8 +301,12 @@ pub fn winit_runner(mut windows: Windows) {
                 // It's preferable for applications that do not render continuously to render in
                 // this event rather than in MainEventsCleared, since rendering in here allows
                 // the program to gracefully handle redraws requested by the OS.
-                if let Err(err) = context.render(&mut windows.wm, &mut windows.renderer, &window_id)
-                {
+                if let Err(err) = context.render(
+                    &mut windows.wm,
+                    &mut windows.renderer,
+                    &mut handler,
+                    &window_id,
+                ) {
                     error!("Unable render content for window {:?} : {}", window_id, err);
                     // We exit the application while logging the error if unable to render content into the window.
                     control_flow.set_exit();
@@ -287,11 +328,19 @@ pub fn winit_runner(mut windows: Windows) {
                         winit_state.redraw_request = true;
                     }
                 };
+                if winit_state.mode != FlowMode::Poll && windows.renderer.rasterizing() {
+                    // Render again shortly to show the tiles rasterized in the background
+                    control_flow.set_wait_until(now + TILE_POLL_INTERVAL);
+                }
             }
             _ => (),
         }
 
-        windows.menu.listen();
+        if let Some(command) = windows.menu.listen(&context.commands) {
+            if let Err(err) = context.command(&windows.wm, &mut handler, command) {
+                error!("Unable to run command {:?}: {}", command, err);
+            }
+        }
     };
 
     run(event_loop, event_handler)
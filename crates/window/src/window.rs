@@ -8,7 +8,7 @@ use winit::{
     window::{Fullscreen, Window, WindowBuilder, WindowId},
 };
 
-use crate::error::WindowsError;
+use crate::{command::Command, error::WindowsError};
 
 /// The window attributes provided to the created window by default.
 pub struct WindowOptions {
@@ -47,7 +47,10 @@ impl WindowManager {
     /// Create an instance of the application main window.
     /// `WindowManager` does not create a window by default, create the default window via this method or the application will crash with no specified window error.
     /// Acts as the default parent window for any other created window.
-    pub fn with_main_window(mut self, event_loop: &EventLoop<()>) -> anyhow::Result<WindowManager> {
+    pub fn with_main_window(
+        mut self,
+        event_loop: &EventLoop<Command>,
+    ) -> anyhow::Result<WindowManager> {
         let monitor = &event_loop
             .available_monitors()
             .next()
@@ -101,6 +104,10 @@ impl WindowManager {
         self.windows.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
     /// Retrieve the information of the current window, panics if cannot find the window.
     pub fn current_window(&self) -> anyhow::Result<&Window> {
         if let Some(window_id) = self.focus_window {
@@ -127,7 +134,7 @@ impl WindowManager {
     pub fn create_window(
         &mut self,
         options: WindowOptions,
-        event_loop: &EventLoopWindowTarget<()>,
+        event_loop: &EventLoopWindowTarget<Command>,
     ) -> anyhow::Result<WindowId> {
         let mut builder = WindowBuilder::new();
 